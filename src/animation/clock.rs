@@ -0,0 +1,88 @@
+/// Turns variable frame times into a whole number of fixed simulation steps.
+///
+/// Frame time accumulates and is consumed one `step` at a time; what is left
+/// over is exposed as `alpha`, the fraction of a step the display is ahead of the
+/// simulation, for interpolating between the last two simulated states. After a
+/// long stall at most `max_steps` are run and the rest of the backlog is
+/// dropped, so a slow frame can't snowball into ever slower ones.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedClock {
+    step: f32,
+    max_steps: u32,
+    accumulator: f32,
+}
+
+impl FixedClock {
+    /// A clock stepping every `step` seconds.
+    pub fn new(step: f32) -> Self {
+        FixedClock {
+            step: step.max(f32::EPSILON),
+            max_steps: 5,
+            accumulator: 0.0,
+        }
+    }
+
+    /// A clock stepping `rate` times per second.
+    pub fn from_rate(rate: f32) -> Self {
+        Self::new(1.0 / rate)
+    }
+
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// Seconds per simulation step.
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds `frame_time` seconds and returns how many steps to simulate now.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+        let steps = (self.accumulator / self.step).floor();
+        if steps > self.max_steps as f32 {
+            // 追不上就丢掉积压的时间, 只保留不足一步的部分
+            self.accumulator = self.accumulator.rem_euclid(self.step);
+            return self.max_steps;
+        }
+        self.accumulator -= steps * self.step;
+        steps as u32
+    }
+
+    /// How far between the previous and the latest step to draw, in [0, 1).
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+impl Default for FixedClock {
+    fn default() -> Self {
+        Self::from_rate(60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_and_alpha() {
+        let mut clock = FixedClock::new(0.25);
+        assert_eq!(clock.advance(0.6), 2);
+        assert!((clock.alpha() - 0.4).abs() < 1e-5);
+        assert_eq!(clock.advance(0.15), 1);
+        assert!(clock.alpha().abs() < 1e-5);
+    }
+
+    #[test]
+    fn stalls_drop_the_backlog() {
+        let mut clock = FixedClock::new(0.1).with_max_steps(3);
+        assert_eq!(clock.advance(10.05), 3);
+        assert!((clock.alpha() - 0.5).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,306 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EaseMode {
+    In,
+    Out,
+    InOut,
+}
+
+/// Where the jumps of a `steps()` curve happen, as in CSS.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StepPosition {
+    JumpStart,
+    JumpEnd,
+    JumpNone,
+    JumpBoth,
+}
+
+/// CSS `cubic-bezier(x1, y1, x2, y2)`. The curve runs from (0, 0) to (1, 1).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CubicBezier {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+impl CubicBezier {
+    pub const EASE: CubicBezier = CubicBezier::new(0.25, 0.1, 0.25, 1.0);
+    pub const EASE_IN: CubicBezier = CubicBezier::new(0.42, 0.0, 1.0, 1.0);
+    pub const EASE_OUT: CubicBezier = CubicBezier::new(0.0, 0.0, 0.58, 1.0);
+    pub const EASE_IN_OUT: CubicBezier = CubicBezier::new(0.42, 0.0, 0.58, 1.0);
+
+    /// `x1` and `x2` must lie in [0, 1] for the curve to be a function of x; they are clamped.
+    pub const fn new(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        CubicBezier {
+            x1: x1.clamp(0.0, 1.0),
+            y1,
+            x2: x2.clamp(0.0, 1.0),
+            y2,
+        }
+    }
+
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.x1, self.y1, self.x2, self.y2]
+    }
+
+    fn bezier(a: f32, b: f32, s: f32) -> f32 {
+        let inv = 1.0 - s;
+        3.0 * inv * inv * s * a + 3.0 * inv * s * s * b + s * s * s
+    }
+
+    fn bezier_slope(a: f32, b: f32, s: f32) -> f32 {
+        let inv = 1.0 - s;
+        3.0 * inv * inv * a + 6.0 * inv * s * (b - a) + 3.0 * s * s * (1.0 - b)
+    }
+
+    /// Finds the curve parameter whose x equals `x`: Newton first, bisection if it stalls.
+    fn solve_x(&self, x: f32) -> f32 {
+        let mut s = x;
+        for _ in 0..8 {
+            let err = Self::bezier(self.x1, self.x2, s) - x;
+            if err.abs() < 1e-6 {
+                return s;
+            }
+            let slope = Self::bezier_slope(self.x1, self.x2, s);
+            if slope.abs() < 1e-6 {
+                break;
+            }
+            s -= err / slope;
+        }
+
+        let (mut lo, mut hi) = (0.0f32, 1.0f32);
+        s = x;
+        for _ in 0..32 {
+            let value = Self::bezier(self.x1, self.x2, s);
+            if (value - x).abs() < 1e-6 {
+                break;
+            }
+            if value < x {
+                lo = s;
+            } else {
+                hi = s;
+            }
+            s = (lo + hi) * 0.5;
+        }
+        s
+    }
+
+    pub fn apply(&self, x: f32) -> f32 {
+        if x <= 0.0 || x >= 1.0 {
+            return x.clamp(0.0, 1.0);
+        }
+        Self::bezier(self.y1, self.y2, self.solve_x(x))
+    }
+}
+
+/// An easing curve mapping normalized time to progress. Most curves map 0 to 0 and
+/// 1 to 1 but may overshoot in between (back, elastic, some beziers).
+#[derive(Clone, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    Quad(EaseMode),
+    Cubic(EaseMode),
+    Quart(EaseMode),
+    Expo(EaseMode),
+    Back(EaseMode),
+    Elastic(EaseMode),
+    Bounce(EaseMode),
+    CubicBezier(CubicBezier),
+    Steps(u32, StepPosition),
+    Custom(Arc<dyn Fn(f32) -> f32 + Send + Sync>),
+}
+
+impl fmt::Debug for Easing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Easing::Linear => write!(f, "Linear"),
+            Easing::Quad(mode) => write!(f, "Quad({:?})", mode),
+            Easing::Cubic(mode) => write!(f, "Cubic({:?})", mode),
+            Easing::Quart(mode) => write!(f, "Quart({:?})", mode),
+            Easing::Expo(mode) => write!(f, "Expo({:?})", mode),
+            Easing::Back(mode) => write!(f, "Back({:?})", mode),
+            Easing::Elastic(mode) => write!(f, "Elastic({:?})", mode),
+            Easing::Bounce(mode) => write!(f, "Bounce({:?})", mode),
+            Easing::CubicBezier(curve) => write!(f, "{:?}", curve),
+            Easing::Steps(count, position) => write!(f, "Steps({}, {:?})", count, position),
+            Easing::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl Easing {
+    pub fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Easing::CubicBezier(CubicBezier::new(x1, y1, x2, y2))
+    }
+
+    pub fn steps(count: u32, position: StepPosition) -> Self {
+        Easing::Steps(count, position)
+    }
+
+    pub fn custom(f: impl Fn(f32) -> f32 + Send + Sync + 'static) -> Self {
+        Easing::Custom(Arc::new(f))
+    }
+
+    /// Eases `t`, which is clamped to [0, 1] first.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::Quad(mode) => with_mode(*mode, t, |t| t * t),
+            Easing::Cubic(mode) => with_mode(*mode, t, |t| t * t * t),
+            Easing::Quart(mode) => with_mode(*mode, t, |t| t * t * t * t),
+            Easing::Expo(mode) => with_mode(*mode, t, expo_in),
+            Easing::Back(mode) => with_mode(*mode, t, back_in),
+            Easing::Elastic(mode) => with_mode(*mode, t, elastic_in),
+            Easing::Bounce(mode) => with_mode(*mode, t, bounce_in),
+            Easing::CubicBezier(curve) => curve.apply(t),
+            Easing::Steps(count, position) => steps(*count, *position, t),
+            Easing::Custom(f) => f(t),
+        }
+    }
+}
+
+/// Builds the out and in-out variants from an ease-in curve.
+fn with_mode(mode: EaseMode, t: f32, ease_in: impl Fn(f32) -> f32) -> f32 {
+    match mode {
+        EaseMode::In => ease_in(t),
+        EaseMode::Out => 1.0 - ease_in(1.0 - t),
+        EaseMode::InOut => {
+            if t < 0.5 {
+                ease_in(2.0 * t) * 0.5
+            } else {
+                1.0 - ease_in(2.0 - 2.0 * t) * 0.5
+            }
+        }
+    }
+}
+
+fn expo_in(t: f32) -> f32 {
+    if t <= 0.0 {
+        0.0
+    } else {
+        2f32.powf(10.0 * t - 10.0)
+    }
+}
+
+fn back_in(t: f32) -> f32 {
+    const C1: f32 = 1.70158;
+    const C3: f32 = C1 + 1.0;
+    C3 * t * t * t - C1 * t * t
+}
+
+fn elastic_in(t: f32) -> f32 {
+    const C4: f32 = 2.0 * PI / 3.0;
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    -(2f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * C4).sin()
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N1: f32 = 7.5625;
+    const D1: f32 = 2.75;
+    if t < 1.0 / D1 {
+        N1 * t * t
+    } else if t < 2.0 / D1 {
+        let t = t - 1.5 / D1;
+        N1 * t * t + 0.75
+    } else if t < 2.5 / D1 {
+        let t = t - 2.25 / D1;
+        N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D1;
+        N1 * t * t + 0.984375
+    }
+}
+
+fn bounce_in(t: f32) -> f32 {
+    1.0 - bounce_out(1.0 - t)
+}
+
+/// CSS `steps(n, <jump-term>)` for t in [0, 1].
+fn steps(count: u32, position: StepPosition, t: f32) -> f32 {
+    let count = count.max(1) as f32;
+    let jumps = match position {
+        StepPosition::JumpStart | StepPosition::JumpEnd => count,
+        StepPosition::JumpNone => (count - 1.0).max(1.0),
+        StepPosition::JumpBoth => count + 1.0,
+    };
+    let mut step = (t * count).floor();
+    if matches!(position, StepPosition::JumpStart | StepPosition::JumpBoth) {
+        step += 1.0;
+    }
+    step.clamp(0.0, jumps) / jumps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bezier_solves_x_to_within_tolerance() {
+        for curve in [CubicBezier::EASE, CubicBezier::EASE_IN, CubicBezier::EASE_OUT, CubicBezier::EASE_IN_OUT] {
+            for i in 1..100 {
+                let x = i as f32 / 100.0;
+                let s = curve.solve_x(x);
+                assert!((CubicBezier::bezier(curve.x1, curve.x2, s) - x).abs() < 1e-5, "{:?} at {}", curve, x);
+            }
+        }
+    }
+
+    #[test]
+    fn bezier_matches_css_reference_values() {
+        // 浏览器中 ease 在 0.5 处的取值
+        assert!((CubicBezier::EASE.apply(0.5) - 0.802_403_4).abs() < 1e-4);
+        assert!((CubicBezier::EASE_IN_OUT.apply(0.5) - 0.5).abs() < 1e-5);
+        let linear = CubicBezier::new(0.0, 0.0, 1.0, 1.0);
+        assert!((linear.apply(0.3) - 0.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn bezier_handles_flat_slopes_and_clamps_x() {
+        // x1 = x2 = 1 时起点斜率为零, 牛顿法会停下, 需要二分法兜底
+        let curve = CubicBezier::new(1.0, 0.0, 1.0, 1.0);
+        let mut last = 0.0;
+        for i in 1..100 {
+            let y = curve.apply(i as f32 / 100.0);
+            assert!(y >= last - 1e-6);
+            last = y;
+        }
+        assert_eq!(CubicBezier::new(-1.0, 0.0, 2.0, 1.0).to_array(), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(CubicBezier::EASE.apply(-0.5), 0.0);
+        assert_eq!(CubicBezier::EASE.apply(1.5), 1.0);
+    }
+
+    #[test]
+    fn bezier_may_overshoot_in_y() {
+        let back = Easing::cubic_bezier(0.68, -0.6, 0.32, 1.6);
+        assert!((0..=100).any(|i| back.apply(i as f32 / 100.0) < 0.0));
+        assert!((0..=100).any(|i| back.apply(i as f32 / 100.0) > 1.0));
+    }
+
+    #[test]
+    fn steps_follow_css_jump_terms() {
+        assert_eq!(steps(4, StepPosition::JumpEnd, 0.3), 0.25);
+        assert_eq!(steps(4, StepPosition::JumpStart, 0.3), 0.5);
+        assert_eq!(steps(5, StepPosition::JumpNone, 0.5), 0.5);
+        assert_eq!(steps(5, StepPosition::JumpNone, 1.0), 1.0);
+        assert_eq!(steps(3, StepPosition::JumpBoth, 0.0), 0.25);
+        assert_eq!(steps(3, StepPosition::JumpEnd, 1.0), 1.0);
+    }
+
+    #[test]
+    fn named_curves_start_at_zero_and_end_at_one() {
+        for mode in [EaseMode::In, EaseMode::Out, EaseMode::InOut] {
+            for easing in [Easing::Quad(mode), Easing::Expo(mode), Easing::Back(mode), Easing::Elastic(mode), Easing::Bounce(mode)] {
+                assert!(easing.apply(0.0).abs() < 1e-3, "{:?}", easing);
+                assert!((easing.apply(1.0) - 1.0).abs() < 1e-3, "{:?}", easing);
+            }
+        }
+    }
+}
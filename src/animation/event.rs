@@ -0,0 +1,18 @@
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnimationEvent {
+    Started,
+    /// Playback entered repetition `iteration` (the first repetition is 0).
+    Looped { iteration: u32 },
+    Completed,
+    /// The playhead crossed a named marker; `time` is the marker's position in its iteration.
+    Marker { name: String, time: f32 },
+    /// A state machine took a transition; `fade` is its cross-fade in seconds,
+    /// 0 when it cuts straight to the new state.
+    StateChanged { from: String, to: String, fade: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    pub name: String,
+    pub time: f32,
+}
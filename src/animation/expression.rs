@@ -0,0 +1,723 @@
+use std::collections::HashMap;
+use std::fmt;
+use glam::Vec2;
+use crate::shapes::{Color, Shape};
+use super::Animator;
+use super::noise::Noise;
+use super::property::{Property, PropertySnapshot, PropertyValue};
+use super::state_machine::Parameter;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExpressionError {
+    pub message: String,
+    /// Byte offset into the source.
+    pub position: usize,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sqrt,
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    Round,
+    Fract,
+    Exp,
+    Ln,
+    Pow,
+    Min,
+    Max,
+    Clamp,
+    Mix,
+    Step,
+    Smoothstep,
+    Noise,
+}
+
+impl Function {
+    fn lookup(name: &str) -> Option<(Function, std::ops::RangeInclusive<usize>)> {
+        Some(match name {
+            "sin" => (Function::Sin, 1..=1),
+            "cos" => (Function::Cos, 1..=1),
+            "tan" => (Function::Tan, 1..=1),
+            "asin" => (Function::Asin, 1..=1),
+            "acos" => (Function::Acos, 1..=1),
+            "atan" => (Function::Atan, 1..=1),
+            "atan2" => (Function::Atan2, 2..=2),
+            "sqrt" => (Function::Sqrt, 1..=1),
+            "abs" => (Function::Abs, 1..=1),
+            "sign" => (Function::Sign, 1..=1),
+            "floor" => (Function::Floor, 1..=1),
+            "ceil" => (Function::Ceil, 1..=1),
+            "round" => (Function::Round, 1..=1),
+            "fract" => (Function::Fract, 1..=1),
+            "exp" => (Function::Exp, 1..=1),
+            "ln" => (Function::Ln, 1..=1),
+            "pow" => (Function::Pow, 2..=2),
+            "min" => (Function::Min, 2..=2),
+            "max" => (Function::Max, 2..=2),
+            "clamp" => (Function::Clamp, 3..=3),
+            "mix" | "lerp" => (Function::Mix, 3..=3),
+            "step" => (Function::Step, 2..=2),
+            "smoothstep" => (Function::Smoothstep, 3..=3),
+            "noise" => (Function::Noise, 1..=2),
+            _ => return None,
+        })
+    }
+
+    fn call(self, args: &[f32]) -> f32 {
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0.0);
+        match self {
+            Function::Sin => arg(0).sin(),
+            Function::Cos => arg(0).cos(),
+            Function::Tan => arg(0).tan(),
+            Function::Asin => arg(0).asin(),
+            Function::Acos => arg(0).acos(),
+            Function::Atan => arg(0).atan(),
+            Function::Atan2 => arg(0).atan2(arg(1)),
+            Function::Sqrt => arg(0).sqrt(),
+            Function::Abs => arg(0).abs(),
+            Function::Sign => {
+                if arg(0) == 0.0 {
+                    0.0
+                } else {
+                    arg(0).signum()
+                }
+            }
+            Function::Floor => arg(0).floor(),
+            Function::Ceil => arg(0).ceil(),
+            Function::Round => arg(0).round(),
+            Function::Fract => arg(0) - arg(0).floor(),
+            Function::Exp => arg(0).exp(),
+            Function::Ln => arg(0).ln(),
+            Function::Pow => arg(0).powf(arg(1)),
+            Function::Min => arg(0).min(arg(1)),
+            Function::Max => arg(0).max(arg(1)),
+            Function::Clamp => arg(0).clamp(arg(1).min(arg(2)), arg(2).max(arg(1))),
+            Function::Mix => arg(0) + (arg(1) - arg(0)) * arg(2),
+            Function::Step => (arg(1) >= arg(0)) as u8 as f32,
+            Function::Smoothstep => {
+                let span = arg(1) - arg(0);
+                let t = if span == 0.0 { (arg(2) >= arg(1)) as u8 as f32 } else { ((arg(2) - arg(0)) / span).clamp(0.0, 1.0) };
+                t * t * (3.0 - 2.0 * t)
+            }
+            Function::Noise => Noise::new(arg(1) as u32).sample(arg(0)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f32),
+    Variable(String),
+    Negate(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, lookup: &dyn Fn(&str) -> f32) -> f32 {
+        match self {
+            Node::Number(v) => *v,
+            Node::Variable(name) => lookup(name),
+            Node::Negate(node) => -node.eval(lookup),
+            Node::Binary(op, a, b) => {
+                let (a, b) = (a.eval(lookup), b.eval(lookup));
+                let truth = |v: bool| v as u8 as f32;
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Rem => a.rem_euclid(b),
+                    BinaryOp::Pow => a.powf(b),
+                    BinaryOp::Less => truth(a < b),
+                    BinaryOp::LessEqual => truth(a <= b),
+                    BinaryOp::Greater => truth(a > b),
+                    BinaryOp::GreaterEqual => truth(a >= b),
+                    BinaryOp::Equal => truth(a == b),
+                    BinaryOp::NotEqual => truth(a != b),
+                }
+            }
+            Node::Call(function, args) => {
+                let args = args.iter().map(|arg| arg.eval(lookup)).collect::<Vec<_>>();
+                function.call(&args)
+            }
+        }
+    }
+
+    fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Node::Number(_) => {}
+            Node::Variable(name) => names.push(name),
+            Node::Negate(node) => node.collect_variables(names),
+            Node::Binary(_, a, b) => {
+                a.collect_variables(names);
+                b.collect_variables(names);
+            }
+            Node::Call(_, args) => args.iter().for_each(|arg| arg.collect_variables(names)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Op(&'static str),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ExpressionError> {
+    const OPERATORS: [&str; 12] = ["<=", ">=", "==", "!=", "+", "-", "*", "/", "%", "^", "<", ">"];
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        let start = i;
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+            while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                i += 1;
+            }
+            // 科学计数法, 如 1e-3
+            if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+                let mut j = i + 1;
+                if j < bytes.len() && (bytes[j] == b'+' || bytes[j] == b'-') {
+                    j += 1;
+                }
+                if j < bytes.len() && bytes[j].is_ascii_digit() {
+                    i = j;
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let number = source[start..i].parse::<f32>().map_err(|_| ExpressionError {
+                message: format!("invalid number '{}'", &source[start..i]),
+                position: start,
+            })?;
+            tokens.push((Token::Number(number), start));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'.') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(source[start..i].to_string()), start));
+        } else if c == '(' {
+            tokens.push((Token::Open, start));
+            i += 1;
+        } else if c == ')' {
+            tokens.push((Token::Close, start));
+            i += 1;
+        } else if c == ',' {
+            tokens.push((Token::Comma, start));
+            i += 1;
+        } else if let Some(op) = OPERATORS.iter().find(|op| source[i..].starts_with(**op)) {
+            tokens.push((Token::Op(op), start));
+            i += op.len();
+        } else {
+            return Err(ExpressionError {
+                message: format!("unexpected character '{}'", &source[i..].chars().next().unwrap_or(c)),
+                position: start,
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.index).map_or(self.end, |(_, position)| *position)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, ExpressionError> {
+        Err(ExpressionError { message: message.to_string(), position: self.position() })
+    }
+
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.index += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, ops: &[&'static str], next: fn(&mut Parser) -> Result<Node, ExpressionError>) -> Result<Node, ExpressionError> {
+        let mut node = next(self)?;
+        while let Some(op) = self.eat_op(ops) {
+            let op = match op {
+                "+" => BinaryOp::Add,
+                "-" => BinaryOp::Sub,
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                "%" => BinaryOp::Rem,
+                "<" => BinaryOp::Less,
+                "<=" => BinaryOp::LessEqual,
+                ">" => BinaryOp::Greater,
+                ">=" => BinaryOp::GreaterEqual,
+                "==" => BinaryOp::Equal,
+                _ => BinaryOp::NotEqual,
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(next(self)?));
+        }
+        Ok(node)
+    }
+
+    fn comparison(&mut self) -> Result<Node, ExpressionError> {
+        self.binary(&["<", "<=", ">", ">=", "==", "!="], Parser::additive)
+    }
+
+    fn additive(&mut self) -> Result<Node, ExpressionError> {
+        self.binary(&["+", "-"], Parser::multiplicative)
+    }
+
+    fn multiplicative(&mut self) -> Result<Node, ExpressionError> {
+        self.binary(&["*", "/", "%"], Parser::unary)
+    }
+
+    fn unary(&mut self) -> Result<Node, ExpressionError> {
+        if self.eat_op(&["-"]).is_some() {
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+        if self.eat_op(&["+"]).is_some() {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    /// Right-associative and tighter than unary minus, so `-2^2` is -4.
+    fn power(&mut self) -> Result<Node, ExpressionError> {
+        let base = self.primary()?;
+        if self.eat_op(&["^"]).is_some() {
+            let exponent = self.unary()?;
+            return Ok(Node::Binary(BinaryOp::Pow, Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Node, ExpressionError> {
+        let position = self.position();
+        match self.peek().cloned() {
+            Some(Token::Number(v)) => {
+                self.index += 1;
+                Ok(Node::Number(v))
+            }
+            Some(Token::Open) => {
+                self.index += 1;
+                let node = self.comparison()?;
+                if self.peek() != Some(&Token::Close) {
+                    return self.error("expected ')'");
+                }
+                self.index += 1;
+                Ok(node)
+            }
+            Some(Token::Ident(name)) => {
+                self.index += 1;
+                if self.peek() != Some(&Token::Open) {
+                    return Ok(match name.as_str() {
+                        "pi" => Node::Number(std::f32::consts::PI),
+                        "tau" => Node::Number(std::f32::consts::TAU),
+                        "e" => Node::Number(std::f32::consts::E),
+                        _ => Node::Variable(name),
+                    });
+                }
+                self.index += 1;
+                let Some((function, arity)) = Function::lookup(&name) else {
+                    return Err(ExpressionError { message: format!("unknown function '{}'", name), position });
+                };
+                let mut args = vec![];
+                if self.peek() != Some(&Token::Close) {
+                    loop {
+                        args.push(self.comparison()?);
+                        if self.peek() == Some(&Token::Comma) {
+                            self.index += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                if self.peek() != Some(&Token::Close) {
+                    return self.error("expected ')'");
+                }
+                self.index += 1;
+                if !arity.contains(&args.len()) {
+                    let expected = if arity.start() == arity.end() {
+                        arity.start().to_string()
+                    } else {
+                        format!("{} to {}", arity.start(), arity.end())
+                    };
+                    return Err(ExpressionError {
+                        message: format!("'{}' takes {} arguments, got {}", name, expected, args.len()),
+                        position,
+                    });
+                }
+                Ok(Node::Call(function, args))
+            }
+            _ => self.error("expected a number, variable or '('"),
+        }
+    }
+}
+
+/// A parsed arithmetic expression such as `sin(time * 2) * 0.3 + index * 0.1`.
+///
+/// Supports `+ - * / % ^`, comparisons (1 for true, 0 for false), parentheses,
+/// the constants `pi`, `tau` and `e`, and common math functions plus
+/// `noise(x, seed)`. Variable names may contain dots, e.g. `mouse.x`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let mut parser = Parser { tokens: tokenize(source)?, index: 0, end: source.len() };
+        let root = parser.comparison()?;
+        if parser.index < parser.tokens.len() {
+            return parser.error("unexpected input");
+        }
+        Ok(Expression { source: source.to_string(), root })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Names of the variables the expression reads.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = vec![];
+        self.root.collect_variables(&mut names);
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Evaluates with `lookup` resolving variables.
+    pub fn eval(&self, lookup: &dyn Fn(&str) -> f32) -> f32 {
+        self.root.eval(lookup)
+    }
+
+    /// Evaluates with variables from a map; missing ones read as 0.
+    pub fn eval_with(&self, variables: &HashMap<String, f32>) -> f32 {
+        self.eval(&|name| variables.get(name).copied().unwrap_or(0.0))
+    }
+}
+
+/// Variable names for each component of a property, e.g. `position.x`, `position.y`.
+pub fn property_variables(property: Property) -> &'static [&'static str] {
+    match property {
+        Property::Position => &["position.x", "position.y"],
+        Property::Rotation => &["rotation"],
+        Property::Scale => &["scale.x", "scale.y"],
+        Property::Color => &["color.r", "color.g", "color.b"],
+        Property::Opacity => &["opacity"],
+        Property::Anchor => &["anchor.x", "anchor.y"],
+        Property::StrokeWidth => &["stroke_width"],
+        Property::DashOffset => &["dash_offset"],
+        Property::TrimStart => &["trim_start"],
+        Property::TrimEnd => &["trim_end"],
+        // `frame` is already the frame counter
+        Property::Frame => &["sprite_frame"],
+        Property::ShadowColor => &["shadow_color.r", "shadow_color.g", "shadow_color.b"],
+        Property::ShadowOffset => &["shadow_offset.x", "shadow_offset.y"],
+        Property::ShadowBlur => &["shadow_blur"],
+        Property::ShadowOpacity => &["shadow_opacity"],
+    }
+}
+
+fn components(value: PropertyValue) -> Vec<f32> {
+    match value {
+        PropertyValue::Position(v)
+        | PropertyValue::Scale(v)
+        | PropertyValue::Anchor(v)
+        | PropertyValue::ShadowOffset(v) => v.to_array().to_vec(),
+        PropertyValue::Color(c) | PropertyValue::ShadowColor(c) => c.to_array().to_vec(),
+        PropertyValue::Rotation(v)
+        | PropertyValue::Opacity(v)
+        | PropertyValue::StrokeWidth(v)
+        | PropertyValue::DashOffset(v)
+        | PropertyValue::TrimStart(v)
+        | PropertyValue::TrimEnd(v)
+        | PropertyValue::Frame(v)
+        | PropertyValue::ShadowBlur(v)
+        | PropertyValue::ShadowOpacity(v) => vec![v],
+    }
+}
+
+/// Every property in `snapshot` as named expression variables.
+pub fn snapshot_variables(snapshot: &PropertySnapshot) -> Vec<(&'static str, f32)> {
+    snapshot
+        .values()
+        .iter()
+        .flat_map(|value| property_variables(value.property()).iter().copied().zip(components(*value)))
+        .collect()
+}
+
+/// Sets one property from expressions, one per component; scale also takes a
+/// single expression for uniform scaling.
+///
+/// Besides `time` (seconds since start) and `frame`, expressions read the
+/// target's properties (see `property_variables`) as the layers below left them
+/// when applied through `Animator::apply_over`, e.g. inside a `LayerStack`.
+/// Anything else comes through `Animator::set_parameter`: the scene loader
+/// passes `index` and the target's static properties, which stand in for
+/// properties no layer below sets, and the renderer passes `mouse.x` /
+/// `mouse.y` in view space. Unknown variables read as 0.
+#[derive(Clone, Debug)]
+pub struct ExpressionAnimator {
+    property: Property,
+    components: Vec<Expression>,
+    time: f32,
+    frame_rate: f32,
+    variables: HashMap<String, f32>,
+}
+
+impl ExpressionAnimator {
+    pub fn new(property: Property, components: Vec<Expression>) -> Result<Self, ExpressionError> {
+        let expected = property_variables(property).len();
+        let uniform_scale = property == Property::Scale && components.len() == 1;
+        if components.len() != expected && !uniform_scale {
+            return Err(ExpressionError {
+                message: format!("{:?} takes {} expressions, got {}", property, expected, components.len()),
+                position: 0,
+            });
+        }
+        Ok(ExpressionAnimator {
+            property,
+            components,
+            time: 0.0,
+            frame_rate: 60.0,
+            variables: HashMap::new(),
+        })
+    }
+
+    pub fn parse(property: Property, sources: &[&str]) -> Result<Self, ExpressionError> {
+        let components = sources.iter().map(|source| Expression::parse(source)).collect::<Result<Vec<_>, _>>()?;
+        Self::new(property, components)
+    }
+
+    /// Frames per second used for the `frame` variable.
+    pub fn with_frame_rate(mut self, frame_rate: f32) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: f32) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    pub fn value(&self) -> PropertyValue {
+        self.value_over(&PropertySnapshot::new())
+    }
+
+    /// The value with property variables read from `below` where it has them.
+    pub fn value_over(&self, below: &PropertySnapshot) -> PropertyValue {
+        let pose = snapshot_variables(below);
+        let lookup = |name: &str| match name {
+            "time" => self.time,
+            "frame" => (self.time * self.frame_rate).floor(),
+            _ => pose
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| *v)
+                .or_else(|| self.variables.get(name).copied())
+                .unwrap_or(0.0),
+        };
+        let c = self.components.iter().map(|e| e.eval(&lookup)).collect::<Vec<_>>();
+        let at = |i: usize| c.get(i).or(c.last()).copied().unwrap_or(0.0);
+        let vec2 = Vec2::new(at(0), at(1));
+        match self.property {
+            Property::Position => PropertyValue::Position(vec2),
+            Property::Rotation => PropertyValue::Rotation(at(0)),
+            Property::Scale => PropertyValue::Scale(vec2),
+            Property::Color => PropertyValue::Color(Color::new(at(0), at(1), at(2))),
+            Property::Opacity => PropertyValue::Opacity(at(0)),
+            Property::Anchor => PropertyValue::Anchor(vec2),
+            Property::StrokeWidth => PropertyValue::StrokeWidth(at(0)),
+            Property::DashOffset => PropertyValue::DashOffset(at(0)),
+            Property::TrimStart => PropertyValue::TrimStart(at(0)),
+            Property::TrimEnd => PropertyValue::TrimEnd(at(0)),
+            Property::Frame => PropertyValue::Frame(at(0)),
+            Property::ShadowColor => PropertyValue::ShadowColor(Color::new(at(0), at(1), at(2))),
+            Property::ShadowOffset => PropertyValue::ShadowOffset(vec2),
+            Property::ShadowBlur => PropertyValue::ShadowBlur(at(0)),
+            Property::ShadowOpacity => PropertyValue::ShadowOpacity(at(0)),
+        }
+    }
+}
+
+impl Animator for ExpressionAnimator {
+    fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        self.value().apply(shape);
+    }
+
+    fn apply_over(&self, below: &PropertySnapshot, shape: &mut dyn Shape) {
+        self.value_over(below).apply(shape);
+    }
+
+    fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    fn set_parameter(&mut self, name: &str, value: Parameter) {
+        let value = match value {
+            Parameter::Number(v) => v,
+            Parameter::Bool(v) | Parameter::Trigger(v) => v as u8 as f32,
+        };
+        self.set_variable(name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::layer::{AnimationLayer, LayerStack};
+
+    fn eval(source: &str) -> f32 {
+        Expression::parse(source).unwrap().eval_with(&HashMap::new())
+    }
+
+    fn error(source: &str) -> ExpressionError {
+        Expression::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("10 - 4 - 3"), 3.0);
+        assert_eq!(eval("12 / 3 / 2"), 2.0);
+        assert_eq!(eval("7 % 4 * 2"), 6.0);
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(eval("-2 ^ 2"), -4.0);
+        assert_eq!(eval("2 ^ -1"), 0.5);
+        assert_eq!(eval("2 * 3 ^ 2"), 18.0);
+        assert_eq!(eval("1 + 1 == 2"), 1.0);
+        assert_eq!(eval("1 < 2 == 1"), 1.0);
+        assert_eq!(eval("--3"), 3.0);
+    }
+
+    #[test]
+    fn numbers_constants_and_functions() {
+        assert_eq!(eval("1e-3 * 1000"), 1.0);
+        assert_eq!(eval(".5 + 2.5"), 3.0);
+        assert_eq!(eval("max(1, min(5, 3))"), 3.0);
+        assert_eq!(eval("clamp(5, 2, 0)"), 2.0);
+        assert_eq!(eval("smoothstep(0, 1, 0.5)"), 0.5);
+        assert_eq!(eval("sign(0)"), 0.0);
+        assert!((eval("sin(pi / 2)") - 1.0).abs() < 1e-6);
+        assert!((eval("ln(e)") - 1.0).abs() < 1e-6);
+        assert_eq!(eval("noise(3.5, 2)"), Noise::new(2).sample(3.5));
+    }
+
+    #[test]
+    fn variables() {
+        let expression = Expression::parse("mouse.x * 2 + index + mouse.x").unwrap();
+        assert_eq!(expression.variables(), vec!["index", "mouse.x"]);
+        let variables = HashMap::from([("mouse.x".to_string(), 0.5), ("index".to_string(), 3.0)]);
+        assert_eq!(expression.eval_with(&variables), 4.5);
+        assert_eq!(expression.eval_with(&HashMap::new()), 0.0);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("1 +").position, 3);
+        assert_eq!(error("(1 + 2").message, "expected ')'");
+        assert_eq!(error("1 2").message, "unexpected input");
+        assert_eq!(error("1 2").position, 2);
+        assert_eq!(error("1 # 2").position, 2);
+        assert_eq!(error("foo(1)").message, "unknown function 'foo'");
+        assert_eq!(error("max(1)").message, "'max' takes 2 arguments, got 1");
+        assert_eq!(error("noise()").message, "'noise' takes 1 to 2 arguments, got 0");
+        assert!(error("1.2.3").message.starts_with("invalid number"));
+        assert!(error("").message.starts_with("expected a number"));
+    }
+
+    #[test]
+    fn component_count_is_checked() {
+        assert!(ExpressionAnimator::parse(Property::Position, &["1"]).is_err());
+        assert!(ExpressionAnimator::parse(Property::Scale, &["1"]).is_ok());
+        assert!(ExpressionAnimator::parse(Property::Color, &["1", "2", "3"]).is_ok());
+    }
+
+    #[test]
+    fn time_and_frame() {
+        let mut animator = ExpressionAnimator::parse(Property::Rotation, &["time + frame * 100"])
+            .unwrap()
+            .with_frame_rate(10.0);
+        animator.update(0.25);
+        assert_eq!(animator.value(), PropertyValue::Rotation(200.25));
+        animator.seek(1.0);
+        assert_eq!(animator.value(), PropertyValue::Rotation(1001.0));
+    }
+
+    #[test]
+    fn reads_the_pose_of_the_layers_below() {
+        let mut rest = PropertySnapshot::new();
+        rest.set(PropertyValue::Scale(Vec2::ONE));
+        rest.set(PropertyValue::Rotation(0.5));
+        let double = ExpressionAnimator::parse(Property::Scale, &["2"]).unwrap();
+        let triple = ExpressionAnimator::parse(Property::Scale, &["scale.x * 3", "rotation"]).unwrap();
+        let mut stack = LayerStack::new()
+            .with_rest_pose(rest.clone())
+            .with_layer(AnimationLayer::new(Box::new(double)))
+            .with_layer(AnimationLayer::new(Box::new(triple)));
+        for (name, value) in snapshot_variables(&rest) {
+            stack.set_parameter(name, Parameter::Number(value));
+        }
+        assert_eq!(stack.resolve().get(Property::Scale), Some(PropertyValue::Scale(Vec2::new(6.0, 0.5))));
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use crate::shapes::Shape;
+use super::Animator;
+use super::event::AnimationEvent;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    /// Plays through once and holds the last frame.
+    Once,
+    #[default]
+    Loop,
+    /// Plays forward then backward, without repeating the end frames.
+    PingPong,
+}
+
+/// Steps a sprite through atlas frames, each shown for its own duration.
+///
+/// Raises `Looped` each time the sequence wraps around (or turns, when
+/// ping-ponging) and `Completed` when a `Once` flipbook reaches its end.
+#[derive(Clone, Debug)]
+pub struct FlipbookAnimator {
+    /// Atlas frame indices in play order.
+    frames: Vec<usize>,
+    /// Seconds each entry in `frames` stays on screen.
+    durations: Vec<f32>,
+    mode: LoopMode,
+    time: f32,
+    events: Vec<AnimationEvent>,
+}
+
+impl FlipbookAnimator {
+    /// Shows `frames` in order at `fps` frames per second.
+    pub fn new(frames: Vec<usize>, fps: f32) -> Self {
+        let duration = 1.0 / fps.max(f32::EPSILON);
+        FlipbookAnimator {
+            durations: vec![duration; frames.len()],
+            frames,
+            mode: LoopMode::Loop,
+            time: 0.0,
+            events: vec![],
+        }
+    }
+
+    /// Sets how long each frame is shown; missing entries keep their duration.
+    pub fn with_durations(mut self, durations: &[f32]) -> Self {
+        for (slot, duration) in self.durations.iter_mut().zip(durations) {
+            *slot = duration.max(0.0);
+        }
+        self
+    }
+
+    pub fn with_loop_mode(mut self, mode: LoopMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The play order for one cycle, as indices into `frames`.
+    fn sequence(&self) -> Vec<usize> {
+        let n = self.frames.len();
+        match self.mode {
+            LoopMode::PingPong if n > 2 => (0..n).chain((1..n - 1).rev()).collect(),
+            _ => (0..n).collect(),
+        }
+    }
+
+    fn cycle_length(&self) -> f32 {
+        self.sequence().iter().map(|&i| self.durations[i]).sum()
+    }
+
+    /// Which cycle `time` falls in and the entry of `frames` showing then.
+    fn locate(&self, time: f32) -> (u32, usize) {
+        let sequence = self.sequence();
+        let length = self.cycle_length();
+        if sequence.is_empty() || length <= 0.0 {
+            return (0, 0);
+        }
+        let (cycle, mut offset) = match self.mode {
+            LoopMode::Once if time >= length => return (0, *sequence.last().unwrap()),
+            LoopMode::Once => (0, time.max(0.0)),
+            _ => ((time / length).floor().max(0.0) as u32, time.rem_euclid(length)),
+        };
+        for &i in &sequence {
+            if offset < self.durations[i] {
+                return (cycle, i);
+            }
+            offset -= self.durations[i];
+        }
+        (cycle, *sequence.last().unwrap())
+    }
+
+    /// The atlas frame showing now.
+    pub fn current_frame(&self) -> usize {
+        let (_, i) = self.locate(self.time);
+        self.frames.get(i).copied().unwrap_or(0)
+    }
+}
+
+impl Animator for FlipbookAnimator {
+    fn update(&mut self, delta_time: f32) {
+        self.seek(self.time + delta_time);
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        if !self.frames.is_empty() {
+            shape.set_frame(self.current_frame());
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        match self.mode {
+            LoopMode::Once => self.cycle_length(),
+            _ => f32::INFINITY,
+        }
+    }
+
+    fn seek(&mut self, time: f32) {
+        let from = self.time;
+        self.time = time;
+        if time <= from {
+            return;
+        }
+        match self.mode {
+            LoopMode::Once => {
+                let length = self.cycle_length();
+                if from < length && time >= length {
+                    self.events.push(AnimationEvent::Completed);
+                }
+            }
+            _ => {
+                let (from_cycle, _) = self.locate(from);
+                let (to_cycle, _) = self.locate(time);
+                for iteration in from_cycle + 1..=to_cycle {
+                    self.events.push(AnimationEvent::Looped { iteration });
+                }
+            }
+        }
+    }
+
+    fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
@@ -0,0 +1,295 @@
+use std::ops::{Add, Mul, Sub};
+use glam::Vec2;
+use crate::shapes::{Color, Shape};
+use super::Animator;
+use super::easing::Easing;
+use super::property::PropertyValue;
+
+/// Values that can be blended between keyframes.
+pub trait Animatable: Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self> {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+}
+
+impl Animatable for f32 {}
+impl Animatable for glam::Vec2 {}
+impl Animatable for glam::Vec3 {}
+impl Animatable for Color {}
+
+/// How the segment that starts at a keyframe reaches the next one.
+#[derive(Clone, Debug)]
+pub enum Interpolation {
+    /// Keep this keyframe's value until the next keyframe.
+    Hold,
+    Linear,
+    Eased(Easing),
+}
+
+#[derive(Clone, Debug)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+    pub interpolation: Interpolation,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T, interpolation: Interpolation) -> Self {
+        Keyframe { time, value, interpolation }
+    }
+
+    pub fn linear(time: f32, value: T) -> Self {
+        Self::new(time, value, Interpolation::Linear)
+    }
+
+    pub fn hold(time: f32, value: T) -> Self {
+        Self::new(time, value, Interpolation::Hold)
+    }
+
+    pub fn eased(time: f32, value: T, easing: Easing) -> Self {
+        Self::new(time, value, Interpolation::Eased(easing))
+    }
+}
+
+/// Keyframes of a single value, kept sorted by time.
+#[derive(Clone, Debug)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Animatable> Default for Track<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new() -> Self {
+        Track { keyframes: vec![] }
+    }
+
+    /// Inserts a keyframe in time order, replacing any keyframe at the same time.
+    pub fn add_keyframe(&mut self, keyframe: Keyframe<T>) {
+        let index = self.keyframes.partition_point(|k| k.time < keyframe.time);
+        match self.keyframes.get_mut(index) {
+            Some(existing) if existing.time == keyframe.time => *existing = keyframe,
+            _ => self.keyframes.insert(index, keyframe),
+        }
+    }
+
+    pub fn with_keyframe(mut self, keyframe: Keyframe<T>) -> Self {
+        self.add_keyframe(keyframe);
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Value at `time`. Times outside the keyframe range hold the first/last value.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let first = self.keyframes.first()?;
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return Some(first.value);
+        }
+        let from = &self.keyframes[next - 1];
+        let Some(to) = self.keyframes.get(next) else {
+            return Some(from.value);
+        };
+
+        let t = (time - from.time) / (to.time - from.time);
+        let value = match from.interpolation {
+            Interpolation::Hold => from.value,
+            Interpolation::Linear => T::lerp(from.value, to.value, t),
+            Interpolation::Eased(ref easing) => T::lerp(from.value, to.value, easing.apply(t)),
+        };
+        Some(value)
+    }
+}
+
+/// A track bound to the shape property it drives.
+#[derive(Clone, Debug)]
+pub enum PropertyTrack {
+    Position(Track<Vec2>),
+    Rotation(Track<f32>),
+    Scale(Track<Vec2>),
+    Color(Track<Color>),
+    Opacity(Track<f32>),
+    Anchor(Track<Vec2>),
+    StrokeWidth(Track<f32>),
+    DashOffset(Track<f32>),
+    TrimStart(Track<f32>),
+    TrimEnd(Track<f32>),
+    Frame(Track<f32>),
+    ShadowColor(Track<Color>),
+    ShadowOffset(Track<Vec2>),
+    ShadowBlur(Track<f32>),
+    ShadowOpacity(Track<f32>),
+}
+
+impl PropertyTrack {
+    pub fn duration(&self) -> f32 {
+        match self {
+            PropertyTrack::Position(track) => track.duration(),
+            PropertyTrack::Rotation(track) => track.duration(),
+            PropertyTrack::Scale(track) => track.duration(),
+            PropertyTrack::Color(track) => track.duration(),
+            PropertyTrack::Opacity(track) => track.duration(),
+            PropertyTrack::Anchor(track) => track.duration(),
+            PropertyTrack::StrokeWidth(track) => track.duration(),
+            PropertyTrack::DashOffset(track) => track.duration(),
+            PropertyTrack::TrimStart(track) => track.duration(),
+            PropertyTrack::TrimEnd(track) => track.duration(),
+            PropertyTrack::Frame(track) => track.duration(),
+            PropertyTrack::ShadowColor(track) => track.duration(),
+            PropertyTrack::ShadowOffset(track) => track.duration(),
+            PropertyTrack::ShadowBlur(track) => track.duration(),
+            PropertyTrack::ShadowOpacity(track) => track.duration(),
+        }
+    }
+
+    pub fn sample(&self, time: f32) -> Option<PropertyValue> {
+        match self {
+            PropertyTrack::Position(track) => track.sample(time).map(PropertyValue::Position),
+            PropertyTrack::Rotation(track) => track.sample(time).map(PropertyValue::Rotation),
+            PropertyTrack::Scale(track) => track.sample(time).map(PropertyValue::Scale),
+            PropertyTrack::Color(track) => track.sample(time).map(PropertyValue::Color),
+            PropertyTrack::Opacity(track) => track.sample(time).map(PropertyValue::Opacity),
+            PropertyTrack::Anchor(track) => track.sample(time).map(PropertyValue::Anchor),
+            PropertyTrack::StrokeWidth(track) => track.sample(time).map(PropertyValue::StrokeWidth),
+            PropertyTrack::DashOffset(track) => track.sample(time).map(PropertyValue::DashOffset),
+            PropertyTrack::TrimStart(track) => track.sample(time).map(PropertyValue::TrimStart),
+            PropertyTrack::TrimEnd(track) => track.sample(time).map(PropertyValue::TrimEnd),
+            PropertyTrack::Frame(track) => track.sample(time).map(PropertyValue::Frame),
+            PropertyTrack::ShadowColor(track) => track.sample(time).map(PropertyValue::ShadowColor),
+            PropertyTrack::ShadowOffset(track) => track.sample(time).map(PropertyValue::ShadowOffset),
+            PropertyTrack::ShadowBlur(track) => track.sample(time).map(PropertyValue::ShadowBlur),
+            PropertyTrack::ShadowOpacity(track) => track.sample(time).map(PropertyValue::ShadowOpacity),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct KeyframeAnimator {
+    tracks: Vec<PropertyTrack>,
+    time: f32,
+}
+
+impl KeyframeAnimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_track(&mut self, track: PropertyTrack) {
+        self.tracks.push(track);
+    }
+
+    pub fn with_track(mut self, track: PropertyTrack) -> Self {
+        self.add_track(track);
+        self
+    }
+
+    pub fn tracks(&self) -> &[PropertyTrack] {
+        &self.tracks
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Time of the last keyframe over all tracks.
+    pub fn duration(&self) -> f32 {
+        self.tracks.iter().map(PropertyTrack::duration).fold(0.0, f32::max)
+    }
+
+    pub fn evaluate(&self, time: f32) -> Vec<PropertyValue> {
+        self.tracks.iter().filter_map(|track| track.sample(time)).collect()
+    }
+}
+
+impl Animator for KeyframeAnimator {
+    fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        for value in self.evaluate(self.time) {
+            value.apply(shape);
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        KeyframeAnimator::duration(self)
+    }
+
+    fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::easing::EaseMode;
+
+    fn times(track: &Track<f32>) -> Vec<f32> {
+        track.keyframes().iter().map(|k| k.time).collect()
+    }
+
+    #[test]
+    fn keyframes_stay_sorted() {
+        let track = Track::new()
+            .with_keyframe(Keyframe::linear(2.0, 20.0))
+            .with_keyframe(Keyframe::linear(0.0, 0.0))
+            .with_keyframe(Keyframe::linear(1.0, 10.0));
+        assert_eq!(times(&track), vec![0.0, 1.0, 2.0]);
+        assert_eq!(track.duration(), 2.0);
+    }
+
+    #[test]
+    fn a_keyframe_at_the_same_time_replaces_the_old_one() {
+        let track = Track::new()
+            .with_keyframe(Keyframe::linear(0.0, 0.0))
+            .with_keyframe(Keyframe::linear(1.0, 10.0))
+            .with_keyframe(Keyframe::hold(1.0, 30.0));
+        assert_eq!(times(&track), vec![0.0, 1.0]);
+        assert_eq!(track.keyframes()[1].value, 30.0);
+        assert!(matches!(track.keyframes()[1].interpolation, Interpolation::Hold));
+    }
+
+    #[test]
+    fn values_hold_outside_the_keyframes() {
+        let track = Track::new().with_keyframe(Keyframe::linear(1.0, 10.0)).with_keyframe(Keyframe::linear(2.0, 20.0));
+        assert_eq!(track.sample(-5.0), Some(10.0));
+        assert_eq!(track.sample(1.0), Some(10.0));
+        assert_eq!(track.sample(1.5), Some(15.0));
+        assert_eq!(track.sample(2.0), Some(20.0));
+        assert_eq!(track.sample(9.0), Some(20.0));
+    }
+
+    #[test]
+    fn each_segment_uses_its_starting_keyframes_interpolation() {
+        let track = Track::new()
+            .with_keyframe(Keyframe::hold(0.0, 0.0))
+            .with_keyframe(Keyframe::eased(1.0, 10.0, Easing::Quad(EaseMode::In)))
+            .with_keyframe(Keyframe::linear(2.0, 20.0))
+            .with_keyframe(Keyframe::linear(3.0, 30.0));
+        assert_eq!(track.sample(0.99), Some(0.0));
+        assert_eq!(track.sample(1.5), Some(12.5));
+        assert_eq!(track.sample(2.5), Some(25.0));
+    }
+
+    #[test]
+    fn an_empty_track_has_no_value() {
+        let track = Track::<f32>::new();
+        assert_eq!(track.sample(0.0), None);
+        assert_eq!(track.duration(), 0.0);
+        let animator = KeyframeAnimator::new().with_track(PropertyTrack::Opacity(track));
+        assert!(animator.evaluate(0.0).is_empty());
+    }
+}
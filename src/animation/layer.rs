@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use crate::shapes::Shape;
+use super::Animator;
+use super::event::AnimationEvent;
+use super::property::PropertySnapshot;
+use super::state_machine::Parameter;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    /// Replaces what the layers below produced, blended in by the layer's weight.
+    #[default]
+    Override,
+    /// Adds onto what the layers below produced; see `PropertyValue::add`.
+    Additive,
+}
+
+pub struct AnimationLayer {
+    pub animator: Box<dyn Animator>,
+    pub mode: BlendMode,
+    /// How much of the layer shows, usually in [0, 1].
+    pub weight: f32,
+}
+
+impl AnimationLayer {
+    pub fn new(animator: Box<dyn Animator>) -> Self {
+        AnimationLayer { animator, mode: BlendMode::Override, weight: 1.0 }
+    }
+
+    pub fn additive(animator: Box<dyn Animator>) -> Self {
+        AnimationLayer { mode: BlendMode::Additive, ..Self::new(animator) }
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+/// Several animators driving the same shape, resolved bottom to top every frame
+/// instead of the last one to `apply` winning.
+///
+/// The stack starts from a rest pose, normally the shape's static properties,
+/// so additive layers and partial weights have something to build on.
+/// Properties no layer touches are left to the rest pose.
+#[derive(Default)]
+pub struct LayerStack {
+    rest: PropertySnapshot,
+    layers: Vec<AnimationLayer>,
+}
+
+impl LayerStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rest_pose(mut self, rest: PropertySnapshot) -> Self {
+        self.rest = rest;
+        self
+    }
+
+    /// Adds a layer above the existing ones and returns its index.
+    pub fn add_layer(&mut self, layer: AnimationLayer) -> usize {
+        self.layers.push(layer);
+        self.layers.len() - 1
+    }
+
+    pub fn with_layer(mut self, layer: AnimationLayer) -> Self {
+        self.add_layer(layer);
+        self
+    }
+
+    pub fn layer_mut(&mut self, index: usize) -> Option<&mut AnimationLayer> {
+        self.layers.get_mut(index)
+    }
+
+    pub fn set_weight(&mut self, index: usize, weight: f32) {
+        if let Some(layer) = self.layers.get_mut(index) {
+            layer.weight = weight;
+        }
+    }
+
+    /// The blended result of all layers. Each layer is applied over the pose
+    /// of the layers below it, so expressions read the animated values.
+    pub fn resolve(&self) -> PropertySnapshot {
+        let mut pose = self.rest.clone();
+        for layer in &self.layers {
+            if layer.weight <= 0.0 {
+                continue;
+            }
+            let output = PropertySnapshot::capture_over(&*layer.animator, &pose);
+            pose = match layer.mode {
+                BlendMode::Override => pose.blend(&output, layer.weight),
+                BlendMode::Additive => pose.add(&output, layer.weight),
+            };
+        }
+        pose
+    }
+}
+
+impl Animator for LayerStack {
+    fn update(&mut self, delta_time: f32) {
+        for layer in &mut self.layers {
+            layer.animator.update(delta_time);
+        }
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        self.resolve().apply(shape);
+    }
+
+    fn duration(&self) -> f32 {
+        self.layers.iter().map(|layer| layer.animator.duration()).fold(0.0, f32::max)
+    }
+
+    fn seek(&mut self, time: f32) {
+        for layer in &mut self.layers {
+            layer.animator.seek(time);
+        }
+    }
+
+    fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        self.layers.iter_mut().flat_map(|layer| layer.animator.drain_events()).collect()
+    }
+
+    fn set_parameter(&mut self, name: &str, value: Parameter) {
+        for layer in &mut self.layers {
+            layer.animator.set_parameter(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+    use super::*;
+    use crate::animation::property::{Property, PropertyValue};
+
+    /// Always applies the same values.
+    struct Pose(Vec<PropertyValue>);
+
+    impl Animator for Pose {
+        fn update(&mut self, _delta_time: f32) {}
+
+        fn apply(&self, shape: &mut dyn Shape) {
+            for value in &self.0 {
+                value.apply(shape);
+            }
+        }
+
+        fn seek(&mut self, _time: f32) {}
+    }
+
+    /// Moves whatever position is below it by `.0`, like an expression reading its input.
+    struct Nudge(Vec2);
+
+    impl Animator for Nudge {
+        fn update(&mut self, _delta_time: f32) {}
+
+        fn apply(&self, shape: &mut dyn Shape) {
+            self.apply_over(&PropertySnapshot::new(), shape);
+        }
+
+        fn apply_over(&self, below: &PropertySnapshot, shape: &mut dyn Shape) {
+            let position = match below.get(Property::Position) {
+                Some(PropertyValue::Position(position)) => position,
+                _ => Vec2::ZERO,
+            };
+            shape.set_position(position + self.0);
+        }
+
+        fn seek(&mut self, _time: f32) {}
+    }
+
+    fn pose(values: &[PropertyValue]) -> Box<dyn Animator> {
+        Box::new(Pose(values.to_vec()))
+    }
+
+    fn rest() -> PropertySnapshot {
+        let mut rest = PropertySnapshot::new();
+        rest.set(PropertyValue::Position(Vec2::new(10.0, 0.0)));
+        rest.set(PropertyValue::Scale(Vec2::splat(2.0)));
+        rest.set(PropertyValue::Opacity(0.5));
+        rest
+    }
+
+    #[test]
+    fn untouched_properties_keep_the_rest_pose() {
+        let stack = LayerStack::new()
+            .with_rest_pose(rest())
+            .with_layer(AnimationLayer::new(pose(&[PropertyValue::Rotation(1.0)])));
+        let pose = stack.resolve();
+        assert_eq!(pose.get(Property::Position), Some(PropertyValue::Position(Vec2::new(10.0, 0.0))));
+        assert_eq!(pose.get(Property::Scale), Some(PropertyValue::Scale(Vec2::splat(2.0))));
+        assert_eq!(pose.get(Property::Rotation), Some(PropertyValue::Rotation(1.0)));
+        assert_eq!(LayerStack::new().with_rest_pose(rest()).resolve().values(), rest().values());
+    }
+
+    #[test]
+    fn override_layers_blend_by_weight() {
+        let mut stack = LayerStack::new()
+            .with_rest_pose(rest())
+            .with_layer(AnimationLayer::new(pose(&[PropertyValue::Position(Vec2::new(20.0, 0.0))])).with_weight(0.5));
+        assert_eq!(stack.resolve().get(Property::Position), Some(PropertyValue::Position(Vec2::new(15.0, 0.0))));
+
+        stack.set_weight(0, 1.0);
+        assert_eq!(stack.resolve().get(Property::Position), Some(PropertyValue::Position(Vec2::new(20.0, 0.0))));
+        stack.set_weight(0, 0.0);
+        assert_eq!(stack.resolve().get(Property::Position), Some(PropertyValue::Position(Vec2::new(10.0, 0.0))));
+
+        // 上面的层盖住下面的层
+        stack.set_weight(0, 1.0);
+        stack.add_layer(AnimationLayer::new(pose(&[PropertyValue::Position(Vec2::new(40.0, 0.0))])).with_weight(0.25));
+        assert_eq!(stack.resolve().get(Property::Position), Some(PropertyValue::Position(Vec2::new(25.0, 0.0))));
+    }
+
+    #[test]
+    fn additive_layers_multiply_scale_and_opacity_and_add_the_rest() {
+        let values = [
+            PropertyValue::Position(Vec2::new(4.0, 2.0)),
+            PropertyValue::Scale(Vec2::splat(3.0)),
+            PropertyValue::Opacity(0.5),
+            PropertyValue::Rotation(1.0),
+        ];
+        let stack = LayerStack::new()
+            .with_rest_pose(rest())
+            .with_layer(AnimationLayer::additive(pose(&values)).with_weight(0.5));
+        let pose = stack.resolve();
+        assert_eq!(pose.get(Property::Position), Some(PropertyValue::Position(Vec2::new(12.0, 1.0))));
+        assert_eq!(pose.get(Property::Scale), Some(PropertyValue::Scale(Vec2::splat(4.0))));
+        assert_eq!(pose.get(Property::Opacity), Some(PropertyValue::Opacity(0.375)));
+        // 静止姿态里没有的属性从加法单位元开始
+        assert_eq!(pose.get(Property::Rotation), Some(PropertyValue::Rotation(0.5)));
+    }
+
+    #[test]
+    fn layers_read_the_pose_below_them() {
+        let mut stack = LayerStack::new()
+            .with_rest_pose(rest())
+            .with_layer(AnimationLayer::new(pose(&[PropertyValue::Position(Vec2::new(20.0, 0.0))])))
+            .with_layer(AnimationLayer::new(Box::new(Nudge(Vec2::new(1.0, 0.0)))));
+        assert_eq!(stack.resolve().get(Property::Position), Some(PropertyValue::Position(Vec2::new(21.0, 0.0))));
+
+        // 跳过权重为零的层, 下一层读到的是静止姿态
+        stack.set_weight(0, 0.0);
+        assert_eq!(stack.resolve().get(Property::Position), Some(PropertyValue::Position(Vec2::new(11.0, 0.0))));
+    }
+}
@@ -1,9 +1,76 @@
-
 use super::shapes::*;
+use event::AnimationEvent;
+use property::PropertySnapshot;
+use state_machine::Parameter;
 pub mod physics;
+pub mod property;
+pub mod keyframe;
+pub mod easing;
+pub mod timeline;
+pub mod spring;
+pub mod playback;
+pub mod event;
+pub mod path_follow;
+pub mod morph;
+pub mod state_machine;
+pub mod layer;
+pub mod noise;
+pub mod expression;
+pub mod clock;
+pub mod skeleton;
+pub mod flipbook;
+pub mod particles;
+
 pub trait Animator {
     fn update(&mut self, delta_time: f32);
     fn apply(&self, shape: &mut dyn Shape);
+    /// Like `apply`, for an animator layered over `below`, the pose the layers
+    /// under it produced. Only animators that read properties use `below`.
+    fn apply_over(&self, _below: &PropertySnapshot, shape: &mut dyn Shape) {
+        self.apply(shape)
+    }
+    /// Length of one play-through in seconds, `f32::INFINITY` if it never ends.
+    fn duration(&self) -> f32 {
+        f32::INFINITY
+    }
+    /// Jumps to an absolute time, measured from the animator's start.
+    fn seek(&mut self, time: f32);
+    /// Events produced since the last call, oldest first.
+    fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        Vec::new()
+    }
+    /// Passes an input parameter to animators that react to one, like state machines.
+    fn set_parameter(&mut self, _name: &str, _value: Parameter) {}
+}
+
+impl<A: Animator + ?Sized> Animator for Box<A> {
+    fn update(&mut self, delta_time: f32) {
+        (**self).update(delta_time)
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        (**self).apply(shape)
+    }
+
+    fn apply_over(&self, below: &PropertySnapshot, shape: &mut dyn Shape) {
+        (**self).apply_over(below, shape)
+    }
+
+    fn duration(&self) -> f32 {
+        (**self).duration()
+    }
+
+    fn seek(&mut self, time: f32) {
+        (**self).seek(time)
+    }
+
+    fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        (**self).drain_events()
+    }
+
+    fn set_parameter(&mut self, name: &str, value: Parameter) {
+        (**self).set_parameter(name, value)
+    }
 }
 
 
@@ -12,6 +79,12 @@ pub struct RotationAnimator {
     speed: f32,
 }
 
+impl RotationAnimator {
+    pub fn new(speed: f32) -> Self {
+        RotationAnimator { angle: 0.0, speed }
+    }
+}
+
 impl Animator for RotationAnimator {
     fn update(&mut self, delta_time: f32) {
         self.angle += self.speed * delta_time;
@@ -19,5 +92,10 @@ impl Animator for RotationAnimator {
 
     fn apply(&self, shape: &mut dyn Shape) {
         // 应用旋转角度到图形
+        shape.set_rotation(self.angle);
+    }
+
+    fn seek(&mut self, time: f32) {
+        self.angle = self.speed * time;
     }
 }
@@ -0,0 +1,258 @@
+use glam::Vec2;
+use lyon::math::point;
+use lyon::path::{Path, Polygon};
+use lyon::tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
+use crate::shapes::Shape;
+use crate::shapes::circle::IVertex;
+use crate::shapes::measure::{ArcLengthTable, Contour};
+use super::Animator;
+use super::easing::Easing;
+
+const DEFAULT_SAMPLES: usize = 128;
+
+/// Morphs one filled outline into another.
+///
+/// Both paths are flattened into contours, which are paired up largest to
+/// largest; a contour without a partner grows from (or shrinks to) the centre
+/// of the one it pairs with. Paired contours are resampled to the same number of
+/// points by arc length, given the same winding, and the target's start point is
+/// rotated to the one closest to the source's so the outline doesn't twist on the
+/// way. Every update the blended outline is tessellated again.
+pub struct MorphAnimator {
+    pairs: Vec<(Vec<Vec2>, Vec<Vec2>)>,
+    duration: f32,
+    easing: Easing,
+    color: [f32; 3],
+    time: f32,
+    tessellator: FillTessellator,
+    geometry: VertexBuffers<IVertex, u16>,
+}
+
+impl MorphAnimator {
+    pub fn new(from: &Path, to: &Path, duration: f32) -> Self {
+        Self::with_samples(from, to, duration, DEFAULT_SAMPLES)
+    }
+
+    /// `samples` is the number of points each contour is resampled to.
+    pub fn with_samples(from: &Path, to: &Path, duration: f32, samples: usize) -> Self {
+        let samples = samples.max(3);
+        let mut from = Contour::from_path(from, 0.01);
+        let mut to = Contour::from_path(to, 0.01);
+        let by_area = |a: &Contour, b: &Contour| b.signed_area().abs().total_cmp(&a.signed_area().abs());
+        from.sort_by(by_area);
+        to.sort_by(by_area);
+
+        let count = from.len().max(to.len());
+        let pairs = (0..count)
+            .map(|i| match (from.get(i), to.get(i)) {
+                (Some(a), Some(b)) => match_contours(a, b, samples),
+                (Some(a), None) => {
+                    let a = resample(a, samples);
+                    let centre = centroid(&a);
+                    (a, vec![centre; samples])
+                }
+                (None, Some(b)) => {
+                    let b = resample(b, samples);
+                    let centre = centroid(&b);
+                    (vec![centre; samples], b)
+                }
+                (None, None) => unreachable!(),
+            })
+            .collect();
+
+        let mut animator = MorphAnimator {
+            pairs,
+            duration,
+            easing: Easing::Linear,
+            color: [1.0, 1.0, 1.0],
+            time: 0.0,
+            tessellator: FillTessellator::new(),
+            geometry: VertexBuffers::new(),
+        };
+        animator.rebuild();
+        animator
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self.rebuild();
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self.rebuild();
+        self
+    }
+
+    pub fn geometry(&self) -> &VertexBuffers<IVertex, u16> {
+        &self.geometry
+    }
+
+    /// The blended outline at `progress` (0 = source, 1 = target).
+    pub fn path_at(&self, progress: f32) -> Path {
+        let mut builder = Path::builder();
+        for (from, to) in &self.pairs {
+            let points = from
+                .iter()
+                .zip(to)
+                .map(|(a, b)| {
+                    let p = a.lerp(*b, progress);
+                    point(p.x, p.y)
+                })
+                .collect::<Vec<_>>();
+            builder.add_polygon(Polygon { points: &points, closed: true });
+        }
+        builder.build()
+    }
+
+    fn rebuild(&mut self) {
+        let progress = if self.duration > 0.0 { self.time / self.duration } else { 1.0 };
+        let path = self.path_at(self.easing.apply(progress));
+        let color = self.color;
+        self.geometry.vertices.clear();
+        self.geometry.indices.clear();
+        let result = self.tessellator.tessellate_path(
+            &path,
+            &FillOptions::tolerance(0.02),
+            &mut BuffersBuilder::new(&mut self.geometry, |v: FillVertex| {
+                let pos = v.position().to_array();
+                IVertex {
+                    position: [pos[0], pos[1], 0.0],
+                    color,
+                }
+            }),
+        );
+        if let Err(e) = result {
+            log::warn!("morph tessellation failed: {:?}", e);
+        }
+    }
+}
+
+impl Animator for MorphAnimator {
+    fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        self.rebuild();
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        shape.set_geometry(&self.geometry);
+    }
+
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn seek(&mut self, time: f32) {
+        self.time = time;
+        self.rebuild();
+    }
+}
+
+fn resample(contour: &Contour, samples: usize) -> Vec<Vec2> {
+    let table = ArcLengthTable::from_contour(&Contour { points: contour.points.clone(), closed: true });
+    if table.is_empty() {
+        let at = contour.points.first().copied().unwrap_or(Vec2::ZERO);
+        return vec![at; samples];
+    }
+    table.resample(samples, true)
+}
+
+fn centroid(points: &[Vec2]) -> Vec2 {
+    points.iter().copied().sum::<Vec2>() / points.len().max(1) as f32
+}
+
+fn match_contours(from: &Contour, to: &Contour, samples: usize) -> (Vec<Vec2>, Vec<Vec2>) {
+    let a = resample(from, samples);
+    let mut b = resample(to, samples);
+    if from.signed_area().signum() != to.signed_area().signum() {
+        b.reverse();
+    }
+
+    // 选择让对应点距离平方和最小的起点, 避免扭转
+    let shift = (0..samples)
+        .min_by(|&x, &y| {
+            let cost = |k: usize| (0..samples).map(|i| a[i].distance_squared(b[(i + k) % samples])).sum::<f32>();
+            cost(x).total_cmp(&cost(y))
+        })
+        .unwrap_or(0);
+    b.rotate_left(shift);
+    (a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counter-clockwise unless `clockwise`, starting at its bottom-left corner.
+    fn square(origin: Vec2, size: f32, clockwise: bool) -> Path {
+        let mut corners = vec![origin, origin + Vec2::new(size, 0.0), origin + Vec2::splat(size), origin + Vec2::new(0.0, size)];
+        if clockwise {
+            corners[1..].reverse();
+        }
+        let points = corners.iter().map(|p| point(p.x, p.y)).collect::<Vec<_>>();
+        let mut builder = Path::builder();
+        builder.add_polygon(Polygon { points: &points, closed: true });
+        builder.build()
+    }
+
+    fn corners(path: &Path) -> Vec<Vec<Vec2>> {
+        Contour::from_path(path, 0.01).into_iter().map(|c| c.points).collect()
+    }
+
+    fn assert_same(a: &[Vec2], b: &[Vec2]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert!(a.abs_diff_eq(*b, 1e-5), "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn ends_reproduce_source_and_target() {
+        let from = square(Vec2::ZERO, 1.0, false);
+        let to = square(Vec2::new(2.0, 0.0), 2.0, false);
+        let morph = MorphAnimator::with_samples(&from, &to, 1.0, 4);
+        assert_same(&corners(&morph.path_at(0.0))[0], &corners(&from)[0]);
+        assert_same(&corners(&morph.path_at(1.0))[0], &corners(&to)[0]);
+        let halfway = [Vec2::new(1.0, 0.0), Vec2::new(2.5, 0.0), Vec2::new(2.5, 1.5), Vec2::new(1.0, 1.5)];
+        assert_same(&corners(&morph.path_at(0.5))[0], &halfway);
+    }
+
+    #[test]
+    fn pairs_share_point_count_and_winding() {
+        let from = square(Vec2::ZERO, 1.0, false);
+        // 顺时针, 起点在另一个角
+        let to = square(Vec2::new(0.0, 1.0), 1.0, true);
+        let morph = MorphAnimator::with_samples(&from, &to, 1.0, 16);
+        assert_eq!(morph.pairs.len(), 1);
+        let (a, b) = &morph.pairs[0];
+        assert_eq!(a.len(), 16);
+        assert_eq!(b.len(), 16);
+        let area = |points: &[Vec2]| Contour { points: points.to_vec(), closed: true }.signed_area();
+        assert!(area(a) > 0.0 && area(b) > 0.0);
+        // 目标起点转到离源起点最近的点上
+        assert!(b[0].abs_diff_eq(Vec2::new(0.0, 1.0), 1e-5), "{:?}", b[0]);
+    }
+
+    #[test]
+    fn unpaired_contours_grow_from_or_shrink_to_a_point() {
+        let mut builder = Path::builder();
+        builder.extend_from_paths(&[square(Vec2::ZERO, 2.0, false).as_slice(), square(Vec2::new(5.0, 5.0), 1.0, false).as_slice()]);
+        let two = builder.build();
+        let one = square(Vec2::new(1.0, 1.0), 2.0, false);
+
+        let shrink = MorphAnimator::with_samples(&two, &one, 1.0, 8);
+        assert_eq!(shrink.pairs.len(), 2);
+        assert!(shrink.pairs.iter().all(|(a, b)| a.len() == 8 && b.len() == 8));
+        // 小的轮廓没有配对, 缩到自己的中心
+        let (small, centre) = &shrink.pairs[1];
+        assert!(small.iter().all(|p| p.x >= 5.0 && p.y >= 5.0));
+        assert!(centre.iter().all(|p| p.abs_diff_eq(Vec2::splat(5.5), 1e-5)));
+
+        let grow = MorphAnimator::with_samples(&one, &two, 1.0, 8);
+        let (centre, small) = &grow.pairs[1];
+        assert!(centre.iter().all(|p| p.abs_diff_eq(Vec2::splat(5.5), 1e-5)));
+        assert_eq!(corners(&grow.path_at(1.0)).len(), 2);
+        assert_same(&corners(&grow.path_at(1.0))[1], small);
+    }
+}
@@ -0,0 +1,217 @@
+use glam::Vec2;
+use crate::shapes::{Color, Shape};
+use super::Animator;
+use super::property::{Property, PropertyValue};
+
+/// Seeded 1D gradient (Perlin) noise, summed over octaves. Samples are smooth,
+/// lie in [-1, 1] and depend only on the input and the seed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Noise {
+    pub seed: u32,
+    pub octaves: u32,
+    /// Frequency multiplier from one octave to the next.
+    pub lacunarity: f32,
+    /// Amplitude multiplier from one octave to the next.
+    pub gain: f32,
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Self {
+        Noise {
+            seed,
+            octaves: 1,
+            // 不用整数倍, 免得各层的格点对齐
+            lacunarity: 1.98,
+            gain: 0.5,
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn sample(&self, x: f32) -> f32 {
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for octave in 0..self.octaves.max(1) {
+            let seed = self.seed.wrapping_add(octave.wrapping_mul(0x9e37_79b9));
+            // 每层错开一个相位, 否则所有层在整数处同时为零
+            let phase = hash(-1, seed) as f32 / u32::MAX as f32;
+            sum += gradient_noise(x * frequency + phase, seed) * amplitude;
+            norm += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+        sum / norm
+    }
+}
+
+fn hash(i: i32, seed: u32) -> u32 {
+    // lowbias32 整数哈希
+    let mut h = (i as u32) ^ seed.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h
+}
+
+/// One octave of 1D Perlin noise in [-1, 1]; zero at every integer.
+pub fn gradient_noise(x: f32, seed: u32) -> f32 {
+    let i = x.floor();
+    let f = x - i;
+    let i = i as i32;
+    let gradient = |i: i32| hash(i, seed) as f32 / u32::MAX as f32 * 2.0 - 1.0;
+    let a = gradient(i) * f;
+    let b = gradient(i.wrapping_add(1)) * (f - 1.0);
+    let t = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+    // 一维 Perlin 的取值范围是 [-0.5, 0.5]
+    (a + (b - a) * t) * 2.0
+}
+
+/// Jitters one property with smooth noise, like After Effects' `wiggle(frequency, amplitude)`.
+///
+/// The output is an offset from `base`: added for most properties, and `1 + n` as
+/// a factor for scale and opacity. Without a base it is the bare offset, which is
+/// what an additive layer in a `LayerStack` expects. Vector and color properties
+/// get an independent channel per component, except scale, which stays uniform.
+#[derive(Clone, Debug)]
+pub struct WiggleAnimator {
+    property: Property,
+    /// Wiggles per second.
+    frequency: f32,
+    amplitude: f32,
+    noise: Noise,
+    base: Option<PropertyValue>,
+    time: f32,
+}
+
+impl WiggleAnimator {
+    pub fn new(property: Property, frequency: f32, amplitude: f32) -> Self {
+        WiggleAnimator {
+            property,
+            frequency,
+            amplitude,
+            noise: Noise::new(0),
+            base: None,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.noise.seed = seed;
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.noise = self.noise.with_octaves(octaves);
+        self
+    }
+
+    pub fn with_base(mut self, base: PropertyValue) -> Self {
+        self.base = Some(base);
+        self
+    }
+
+    fn channel(&self, index: u32) -> f32 {
+        let noise = Noise {
+            seed: self.noise.seed.wrapping_add(index.wrapping_mul(0x85eb_ca6b)),
+            ..self.noise
+        };
+        noise.sample(self.time * self.frequency) * self.amplitude
+    }
+
+    /// The offset at the current time.
+    pub fn offset(&self) -> PropertyValue {
+        let vec2 = || Vec2::new(self.channel(0), self.channel(1));
+        match self.property {
+            Property::Position => PropertyValue::Position(vec2()),
+            Property::Rotation => PropertyValue::Rotation(self.channel(0)),
+            Property::Scale => PropertyValue::Scale(Vec2::splat(1.0 + self.channel(0))),
+            Property::Color => PropertyValue::Color(Color::new(self.channel(0), self.channel(1), self.channel(2))),
+            Property::Opacity => PropertyValue::Opacity(1.0 + self.channel(0)),
+            Property::Anchor => PropertyValue::Anchor(vec2()),
+            Property::StrokeWidth => PropertyValue::StrokeWidth(self.channel(0)),
+            Property::DashOffset => PropertyValue::DashOffset(self.channel(0)),
+            Property::TrimStart => PropertyValue::TrimStart(self.channel(0)),
+            Property::TrimEnd => PropertyValue::TrimEnd(self.channel(0)),
+            Property::Frame => PropertyValue::Frame(self.channel(0).round()),
+            Property::ShadowColor => {
+                PropertyValue::ShadowColor(Color::new(self.channel(0), self.channel(1), self.channel(2)))
+            }
+            Property::ShadowOffset => PropertyValue::ShadowOffset(vec2()),
+            Property::ShadowBlur => PropertyValue::ShadowBlur(self.channel(0)),
+            Property::ShadowOpacity => PropertyValue::ShadowOpacity(1.0 + self.channel(0)),
+        }
+    }
+}
+
+impl Animator for WiggleAnimator {
+    fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        let offset = self.offset();
+        match self.base {
+            Some(base) => base.add(offset, 1.0).apply(shape),
+            None => offset.apply(shape),
+        }
+    }
+
+    fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn octaves_are_not_zero_at_integers() {
+        let noise = Noise::new(7).with_octaves(3);
+        assert!((0..8).any(|x| noise.sample(x as f32).abs() > 1e-3));
+        assert!(Noise::new(7).sample(0.0).abs() > 1e-3);
+    }
+
+    #[test]
+    fn samples_are_deterministic_and_bounded() {
+        let noise = Noise::new(3).with_octaves(4);
+        for i in -200..200 {
+            let x = i as f32 * 0.173;
+            let n = noise.sample(x);
+            assert!((-1.0..=1.0).contains(&n), "{} at {}", n, x);
+            assert_eq!(n, noise.sample(x));
+        }
+        assert_ne!(noise.sample(0.5), Noise::new(4).with_octaves(4).sample(0.5));
+    }
+
+    #[test]
+    fn samples_are_continuous() {
+        let noise = Noise::new(11).with_octaves(3);
+        for i in 0..1000 {
+            let x = i as f32 * 0.01;
+            assert!((noise.sample(x + 1e-4) - noise.sample(x)).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn wiggle_seeks_to_the_same_offset() {
+        let mut a = WiggleAnimator::new(Property::Rotation, 2.0, 0.5).with_seed(9);
+        let mut b = a.clone();
+        a.update(0.75);
+        b.seek(0.75);
+        match (a.offset(), b.offset()) {
+            (PropertyValue::Rotation(x), PropertyValue::Rotation(y)) => {
+                assert_eq!(x, y);
+                assert!(x.abs() <= 0.5);
+            }
+            _ => panic!("expected rotation offsets"),
+        }
+    }
+}
@@ -0,0 +1,463 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+use glam::Vec2;
+use lyon::path::Path;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::shapes::measure::ArcLengthTable;
+use crate::shapes::{Color, Shadow, Shape};
+use super::keyframe::Track;
+
+/// Where new particles appear, relative to the emitter's position and rotation.
+#[derive(Clone, Debug, Default)]
+pub enum EmitterShape {
+    #[default]
+    Point,
+    Line { from: Vec2, to: Vec2 },
+    /// Anywhere inside the circle, or only on its edge.
+    Circle { radius: f32, edge: bool },
+    /// Anywhere along the outline, evenly by arc length.
+    Path(ArcLengthTable),
+}
+
+impl EmitterShape {
+    pub fn path(path: &Path) -> Self {
+        EmitterShape::Path(ArcLengthTable::new(path, 0.01))
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> Vec2 {
+        match self {
+            EmitterShape::Point => Vec2::ZERO,
+            EmitterShape::Line { from, to } => from.lerp(*to, rng.gen()),
+            EmitterShape::Circle { radius, edge } => {
+                // 开方后在圆面内均匀分布
+                let distance = if *edge { *radius } else { radius * rng.gen::<f32>().sqrt() };
+                Vec2::from_angle(rng.gen::<f32>() * TAU) * distance
+            }
+            EmitterShape::Path(table) => table
+                .sample(rng.gen::<f32>() * table.length())
+                .map_or(Vec2::ZERO, |(point, _)| point),
+        }
+    }
+}
+
+/// A value picked uniformly between `min` and `max` for each new particle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RandomRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl RandomRange {
+    pub fn new(min: f32, max: f32) -> Self {
+        RandomRange { min, max }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        RandomRange { min: value, max: value }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> f32 {
+        if self.max > self.min {
+            rng.gen_range(self.min..=self.max)
+        } else {
+            self.min
+        }
+    }
+}
+
+/// `count` particles released at once, `time` seconds after the emitter starts.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Burst {
+    pub time: f32,
+    pub count: u32,
+}
+
+/// How particles are born and how they move and change until they die.
+///
+/// The over-life curves are sampled with time normalized to each particle's
+/// lifetime, 0 at birth and 1 at death. Size and speed curves multiply the
+/// particle's own values; color and opacity curves replace them. Empty curves
+/// leave the particle as it was born.
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    pub position: Vec2,
+    /// Turns the emitter shape and the emission direction.
+    pub rotation: f32,
+    pub shape: EmitterShape,
+    /// Particles per second, spread evenly over time.
+    pub rate: f32,
+    pub bursts: Vec<Burst>,
+    /// Seconds the emitter keeps emitting; live particles finish their lives.
+    pub duration: f32,
+    /// Seconds each particle lives.
+    pub lifetime: RandomRange,
+    pub speed: RandomRange,
+    /// Emission direction in radians, counter-clockwise from +x.
+    pub direction: f32,
+    /// Total angle the emission direction varies over; `TAU` emits everywhere.
+    pub spread: f32,
+    /// Radius in view units.
+    pub size: RandomRange,
+    /// Initial rotation, radians.
+    pub angle: RandomRange,
+    /// Radians per second.
+    pub spin: RandomRange,
+    pub color: Color,
+    pub size_over_life: Track<f32>,
+    pub speed_over_life: Track<f32>,
+    pub color_over_life: Track<Color>,
+    pub opacity_over_life: Track<f32>,
+    /// Acceleration in view units per second squared.
+    pub gravity: Vec2,
+    /// Fraction of velocity lost per second, applied exponentially.
+    pub drag: f32,
+    /// New particles are dropped while this many are alive.
+    pub max_particles: usize,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        ParticleEmitter {
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            shape: EmitterShape::Point,
+            rate: 10.0,
+            bursts: vec![],
+            duration: f32::INFINITY,
+            lifetime: RandomRange::constant(1.0),
+            speed: RandomRange::constant(0.5),
+            direction: FRAC_PI_2,
+            spread: 0.0,
+            size: RandomRange::constant(0.02),
+            angle: RandomRange::constant(0.0),
+            spin: RandomRange::constant(0.0),
+            color: Color::WHITE,
+            size_over_life: Track::new(),
+            speed_over_life: Track::new(),
+            color_over_life: Track::new(),
+            opacity_over_life: Track::new(),
+            gravity: Vec2::ZERO,
+            drag: 0.0,
+            max_particles: 1000,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub rotation: f32,
+    pub spin: f32,
+    pub age: f32,
+    pub lifetime: f32,
+    /// Size and color at birth, before the over-life curves.
+    pub base_size: f32,
+    pub base_color: Color,
+    pub size: f32,
+    pub color: Color,
+    pub opacity: f32,
+}
+
+impl Particle {
+    /// Age as a fraction of the lifetime.
+    pub fn life(&self) -> f32 {
+        (self.age / self.lifetime).clamp(0.0, 1.0)
+    }
+}
+
+/// When an emitter releases particles, from its rate, bursts and duration.
+/// Shared by the CPU and GPU particle systems.
+#[derive(Clone, Debug, Default)]
+pub struct EmissionClock {
+    time: f32,
+    /// Fraction of a particle `rate` has built up but not spawned yet.
+    pending: f32,
+    next_burst: usize,
+}
+
+impl EmissionClock {
+    /// Seconds since the emitter started.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// True once the emitter's duration is over and every burst has fired.
+    pub fn is_done(&self, emitter: &ParticleEmitter) -> bool {
+        self.time >= emitter.duration && self.next_burst >= emitter.bursts.len()
+    }
+
+    /// Moves on by `dt` and returns the age, at the end of the step, of every
+    /// particle born during it. Expects `emitter.bursts` sorted by time.
+    pub fn advance(&mut self, emitter: &ParticleEmitter, dt: f32, rng: &mut impl Rng) -> Vec<f32> {
+        let mut ages = vec![];
+        let start = self.time;
+        self.time += dt;
+        let end = self.time.min(emitter.duration);
+        if end > start {
+            self.pending += emitter.rate.max(0.0) * (end - start);
+            let count = self.pending.floor();
+            self.pending -= count;
+            for _ in 0..count as u32 {
+                ages.push(self.time - rng.gen_range(start..end));
+            }
+        }
+        while let Some(burst) = emitter.bursts.get(self.next_burst) {
+            if burst.time >= self.time {
+                break;
+            }
+            self.next_burst += 1;
+            let age = self.time - burst.time.max(start);
+            ages.extend(std::iter::repeat_n(age, burst.count as usize));
+        }
+        ages
+    }
+}
+
+/// Advances one particle by `dt`: ages it, applies forces, then moves it and
+/// updates its look from the emitter's curves.
+fn integrate(emitter: &ParticleEmitter, particle: &mut Particle, dt: f32) {
+    particle.age += dt;
+    let life = particle.life();
+    particle.velocity += emitter.gravity * dt;
+    particle.velocity *= (-emitter.drag * dt).exp();
+    let speed = emitter.speed_over_life.sample(life).unwrap_or(1.0);
+    particle.position += particle.velocity * speed * dt;
+    particle.rotation += particle.spin * dt;
+    particle.size = particle.base_size * emitter.size_over_life.sample(life).unwrap_or(1.0);
+    particle.color = emitter.color_over_life.sample(life).unwrap_or(particle.base_color);
+    particle.opacity = emitter.opacity_over_life.sample(life).unwrap_or(1.0);
+}
+
+/// Particles simulated on the CPU in view space, so they stay where they were
+/// born when the emitter moves. Animating the system as a `Shape` moves and
+/// turns its emitter.
+pub struct ParticleSystem {
+    pub emitter: ParticleEmitter,
+    particles: Vec<Particle>,
+    rng: StdRng,
+    clock: EmissionClock,
+}
+
+impl ParticleSystem {
+    /// The same `seed` always produces the same particles.
+    pub fn new(mut emitter: ParticleEmitter, seed: u64) -> Self {
+        emitter.bursts.sort_by(|a, b| a.time.total_cmp(&b.time));
+        ParticleSystem {
+            emitter,
+            particles: vec![],
+            rng: StdRng::seed_from_u64(seed),
+            clock: EmissionClock::default(),
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// Seconds since the emitter started.
+    pub fn time(&self) -> f32 {
+        self.clock.time()
+    }
+
+    /// True once the emitter has stopped and every particle has died.
+    pub fn is_finished(&self) -> bool {
+        self.clock.is_done(&self.emitter) && self.particles.is_empty()
+    }
+
+    /// Kills every particle and starts emitting from the beginning.
+    pub fn restart(&mut self) {
+        self.particles.clear();
+        self.clock.reset();
+    }
+
+    /// Spawns `count` particles right now, on top of the rate and bursts.
+    pub fn emit(&mut self, count: u32) {
+        for _ in 0..count {
+            self.spawn(0.0);
+        }
+    }
+
+    /// Spawns one particle that is already `age` seconds old, so particles
+    /// born part-way through a step don't bunch up.
+    fn spawn(&mut self, age: f32) {
+        let ParticleSystem { emitter, particles, rng, .. } = self;
+        if particles.len() >= emitter.max_particles {
+            return;
+        }
+        let turn = Vec2::from_angle(emitter.rotation);
+        let direction = emitter.rotation + emitter.direction + (rng.gen::<f32>() - 0.5) * emitter.spread;
+        let base_size = emitter.size.sample(rng);
+        let mut particle = Particle {
+            position: emitter.position + turn.rotate(emitter.shape.sample(rng)),
+            velocity: Vec2::from_angle(direction) * emitter.speed.sample(rng),
+            rotation: emitter.angle.sample(rng),
+            spin: emitter.spin.sample(rng),
+            age: 0.0,
+            lifetime: emitter.lifetime.sample(rng).max(f32::EPSILON),
+            base_size,
+            base_color: emitter.color,
+            size: base_size,
+            color: emitter.color,
+            opacity: 1.0,
+        };
+        integrate(emitter, &mut particle, age);
+        if particle.age < particle.lifetime {
+            particles.push(particle);
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+        for particle in &mut self.particles {
+            integrate(&self.emitter, particle, dt);
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+        for age in self.clock.advance(&self.emitter, dt, &mut self.rng) {
+            self.spawn(age);
+        }
+    }
+}
+
+// 粒子由渲染器按实例绘制, 动画只移动和旋转发射器
+impl Shape for ParticleSystem {
+    fn set_color(&mut self, color: Color) {
+        self.emitter.color = color;
+    }
+
+    fn set_shadow(&mut self, _shadow: Shadow) {}
+
+    fn set_position(&mut self, position: Vec2) {
+        self.emitter.position = position;
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.emitter.rotation = rotation;
+    }
+
+    fn draw(&self, _render_pass: &mut wgpu::RenderPass) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::keyframe::Keyframe;
+
+    fn quiet() -> ParticleEmitter {
+        ParticleEmitter { rate: 0.0, ..ParticleEmitter::default() }
+    }
+
+    fn counts(emitter: &ParticleEmitter, steps: usize, dt: f32) -> Vec<usize> {
+        let mut clock = EmissionClock::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..steps).map(|_| clock.advance(emitter, dt, &mut rng).len()).collect()
+    }
+
+    #[test]
+    fn rate_carries_fractions_between_steps() {
+        let emitter = ParticleEmitter { rate: 10.0, ..quiet() };
+        assert_eq!(counts(&emitter, 4, 0.25), vec![2, 3, 2, 3]);
+
+        let mut clock = EmissionClock::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        let ages = clock.advance(&emitter, 1.0, &mut rng);
+        assert_eq!(ages.len(), 10);
+        assert!(ages.iter().all(|age| (0.0..=1.0).contains(age)));
+    }
+
+    #[test]
+    fn bursts_fire_once() {
+        let emitter = ParticleEmitter {
+            bursts: vec![Burst { time: 0.0, count: 3 }, Burst { time: 0.5, count: 5 }],
+            ..quiet()
+        };
+        assert_eq!(counts(&emitter, 5, 0.25), vec![3, 0, 5, 0, 0]);
+
+        let mut clock = EmissionClock::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        // 一步跨过两次爆发, 粒子年龄从各自的爆发时间算起
+        let ages = clock.advance(&emitter, 0.6, &mut rng);
+        let expected = [0.6, 0.6, 0.6, 0.1, 0.1, 0.1, 0.1, 0.1];
+        assert_eq!(ages.len(), expected.len());
+        assert!(ages.iter().zip(expected).all(|(age, expected)| (age - expected).abs() < 1e-5), "{:?}", ages);
+        assert!(clock.advance(&emitter, 1.0, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn emission_stops_after_the_duration() {
+        let emitter = ParticleEmitter { rate: 10.0, duration: 0.5, ..quiet() };
+        assert_eq!(counts(&emitter, 4, 0.25), vec![2, 3, 0, 0]);
+
+        let mut system = ParticleSystem::new(emitter, 0);
+        system.update(0.5);
+        assert!(!system.is_finished());
+        system.update(1.0);
+        assert!(system.particles().is_empty());
+        assert!(system.is_finished());
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_particles() {
+        let emitter = ParticleEmitter {
+            shape: EmitterShape::Circle { radius: 1.0, edge: false },
+            spread: TAU,
+            speed: RandomRange::new(0.1, 1.0),
+            lifetime: RandomRange::new(1.0, 2.0),
+            ..ParticleEmitter::default()
+        };
+        let run = |seed: u64| {
+            let mut system = ParticleSystem::new(emitter.clone(), seed);
+            for _ in 0..30 {
+                system.update(1.0 / 30.0);
+            }
+            system.particles().iter().map(|p| (p.position, p.velocity, p.lifetime)).collect::<Vec<_>>()
+        };
+        assert!(!run(7).is_empty());
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn max_particles_caps_the_live_count() {
+        let mut system = ParticleSystem::new(ParticleEmitter { rate: 1000.0, max_particles: 5, ..quiet() }, 0);
+        system.update(0.1);
+        assert_eq!(system.particles().len(), 5);
+        system.emit(10);
+        assert_eq!(system.particles().len(), 5);
+    }
+
+    #[test]
+    fn over_life_curves_follow_each_particle() {
+        let fade = |from: f32, to: f32| Track::new().with_keyframe(Keyframe::linear(0.0, from)).with_keyframe(Keyframe::linear(1.0, to));
+        let emitter = ParticleEmitter {
+            lifetime: RandomRange::constant(2.0),
+            size: RandomRange::constant(0.4),
+            speed: RandomRange::constant(1.0),
+            direction: 0.0,
+            size_over_life: fade(1.0, 0.0),
+            opacity_over_life: fade(1.0, 0.0),
+            speed_over_life: fade(2.0, 2.0),
+            color_over_life: Track::new()
+                .with_keyframe(Keyframe::linear(0.0, Color::WHITE))
+                .with_keyframe(Keyframe::linear(1.0, Color::BLACK)),
+            ..quiet()
+        };
+        let mut system = ParticleSystem::new(emitter, 0);
+        system.emit(1);
+        system.update(1.0);
+        let particle = system.particles()[0];
+        assert_eq!(particle.life(), 0.5);
+        assert!((particle.size - 0.2).abs() < 1e-6);
+        assert!((particle.opacity - 0.5).abs() < 1e-6);
+        assert_eq!(particle.color, Color::new(0.5, 0.5, 0.5));
+        // 速度曲线乘在速度上, 不改变速度本身
+        assert!((particle.position.x - 2.0).abs() < 1e-5);
+        assert_eq!(particle.velocity, Vec2::new(1.0, 0.0));
+    }
+}
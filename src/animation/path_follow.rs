@@ -0,0 +1,118 @@
+use lyon::path::Path;
+use crate::shapes::Shape;
+use crate::shapes::measure::ArcLengthTable;
+use super::Animator;
+use super::easing::Easing;
+
+/// Moves a shape along a path at constant speed (before easing), optionally
+/// turning it to face the direction of travel.
+pub struct PathFollowAnimator {
+    table: ArcLengthTable,
+    duration: f32,
+    easing: Easing,
+    /// Extra rotation added to the tangent angle when auto-orienting, in radians.
+    orient: Option<f32>,
+    time: f32,
+}
+
+impl PathFollowAnimator {
+    pub fn new(path: &Path, duration: f32) -> Self {
+        PathFollowAnimator {
+            table: ArcLengthTable::new(path, 0.01),
+            duration,
+            easing: Easing::Linear,
+            orient: None,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Rotates the shape to the path tangent plus `angle_offset`.
+    pub fn with_auto_orient(mut self, angle_offset: f32) -> Self {
+        self.orient = Some(angle_offset);
+        self
+    }
+
+    pub fn table(&self) -> &ArcLengthTable {
+        &self.table
+    }
+}
+
+impl Animator for PathFollowAnimator {
+    fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        let progress = if self.duration > 0.0 { self.time / self.duration } else { 1.0 };
+        let Some((position, tangent)) = self.table.sample_normalized(self.easing.apply(progress)) else {
+            return;
+        };
+        shape.set_position(position);
+        if let Some(offset) = self.orient {
+            shape.set_rotation(tangent.y.atan2(tangent.x) + offset);
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use glam::Vec2;
+    use lyon::math::point;
+    use super::*;
+    use crate::animation::property::{Property, PropertySnapshot, PropertyValue};
+
+    /// Three units right, then four up.
+    fn corner() -> Path {
+        let mut builder = Path::builder();
+        builder.begin(point(0.0, 0.0));
+        builder.line_to(point(3.0, 0.0));
+        builder.line_to(point(3.0, 4.0));
+        builder.end(false);
+        builder.build()
+    }
+
+    fn position(snapshot: &PropertySnapshot) -> Vec2 {
+        match snapshot.get(Property::Position) {
+            Some(PropertyValue::Position(position)) => position,
+            value => panic!("no position: {:?}", value),
+        }
+    }
+
+    #[test]
+    fn moves_at_constant_speed() {
+        let mut animator = PathFollowAnimator::new(&corner(), 7.0);
+        animator.seek(1.0);
+        assert!(position(&PropertySnapshot::capture(&animator)).abs_diff_eq(Vec2::new(1.0, 0.0), 1e-5));
+        animator.update(4.0);
+        let snapshot = PropertySnapshot::capture(&animator);
+        assert!(position(&snapshot).abs_diff_eq(Vec2::new(3.0, 2.0), 1e-5));
+        assert_eq!(snapshot.get(Property::Rotation), None);
+    }
+
+    #[test]
+    fn auto_orient_faces_the_direction_of_travel() {
+        let mut animator = PathFollowAnimator::new(&corner(), 7.0).with_auto_orient(0.25);
+        animator.seek(1.0);
+        let rotation = |animator: &PathFollowAnimator| match PropertySnapshot::capture(animator).get(Property::Rotation) {
+            Some(PropertyValue::Rotation(rotation)) => rotation,
+            value => panic!("no rotation: {:?}", value),
+        };
+        assert!((rotation(&animator) - 0.25).abs() < 1e-5);
+        animator.seek(5.0);
+        assert!((rotation(&animator) - (FRAC_PI_2 + 0.25)).abs() < 1e-5);
+    }
+}
@@ -77,7 +77,9 @@ impl Physics {
         }
     }
 
-    pub fn step(&mut self) -> glam::Vec2 {
+    /// Advances the simulation by `dt` seconds; call it with a fixed `dt`.
+    pub fn step(&mut self, dt: f32) -> glam::Vec2 {
+        self.integration_parameters.dt = dt;
         let physics_hooks = ();
         let event_handler = ();
         self.physics_pipeline.step(
@@ -0,0 +1,277 @@
+use crate::shapes::Shape;
+use super::Animator;
+use super::event::AnimationEvent;
+use super::property::PropertySnapshot;
+use super::state_machine::Parameter;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayState {
+    Playing,
+    Paused,
+    Stopped,
+    /// Reached the end in the current playing direction.
+    Finished,
+}
+
+/// Transport controls for an animator or timeline.
+///
+/// The playhead is always kept inside `[0, duration]`. Playing into either end
+/// clamps there and switches to `Finished`; `play` after finishing restarts from
+/// the opposite end. The effective speed is `rate`, negated while reversed, so a
+/// negative rate on a reversed player runs forwards again.
+pub struct Player<A> {
+    animator: A,
+    time: f32,
+    rate: f32,
+    reversed: bool,
+    state: PlayState,
+}
+
+impl<A: Animator> Player<A> {
+    pub fn new(mut animator: A) -> Self {
+        animator.seek(0.0);
+        Player {
+            animator,
+            time: 0.0,
+            rate: 1.0,
+            reversed: false,
+            state: PlayState::Stopped,
+        }
+    }
+
+    pub fn animator(&self) -> &A {
+        &self.animator
+    }
+
+    pub fn animator_mut(&mut self) -> &mut A {
+        &mut self.animator
+    }
+
+    pub fn state(&self) -> PlayState {
+        self.state
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Playhead as a fraction of the duration, or `None` for endless animators.
+    pub fn progress(&self) -> Option<f32> {
+        let duration = self.animator.duration();
+        if !duration.is_finite() {
+            return None;
+        }
+        if duration <= 0.0 {
+            return Some(1.0);
+        }
+        Some(self.time / duration)
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: f32) {
+        if rate.is_finite() {
+            self.rate = rate;
+        }
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// The animator's duration, with NaN and negative lengths treated as zero.
+    fn length(&self) -> f32 {
+        let duration = self.animator.duration();
+        if duration.is_nan() {
+            0.0
+        } else {
+            duration.max(0.0)
+        }
+    }
+
+    fn effective_rate(&self) -> f32 {
+        if self.reversed {
+            -self.rate
+        } else {
+            self.rate
+        }
+    }
+
+    pub fn play(&mut self) {
+        let duration = self.length();
+        let rate = self.effective_rate();
+        if rate > 0.0 && self.time >= duration {
+            self.seek(0.0);
+        } else if rate < 0.0 && self.time <= 0.0 && duration.is_finite() {
+            self.seek(duration);
+        }
+        self.state = PlayState::Playing;
+    }
+
+    pub fn pause(&mut self) {
+        if self.state == PlayState::Playing {
+            self.state = PlayState::Paused;
+        }
+    }
+
+    /// Stops and rewinds to time zero.
+    pub fn stop(&mut self) {
+        self.seek(0.0);
+        self.state = PlayState::Stopped;
+    }
+
+    /// Flips the playing direction. A finished player starts playing back the way it came.
+    pub fn reverse(&mut self) {
+        self.reversed = !self.reversed;
+        if self.state == PlayState::Finished {
+            self.state = PlayState::Playing;
+        }
+    }
+
+    /// Moves the playhead to `time`, clamped to the animator's duration.
+    pub fn seek(&mut self, time: f32) {
+        // max/min rather than clamp: clamp panics on a NaN bound
+        self.time = time.max(0.0).min(self.length());
+        self.animator.seek(self.time);
+        if self.state == PlayState::Finished {
+            self.state = PlayState::Paused;
+        }
+    }
+
+    /// Seeks to `progress` in `[0, 1]`. Ignored for endless animators.
+    pub fn seek_progress(&mut self, progress: f32) {
+        let duration = self.length();
+        if duration.is_finite() {
+            self.seek(progress.clamp(0.0, 1.0) * duration);
+        }
+    }
+}
+
+impl<A: Animator> Animator for Player<A> {
+    fn update(&mut self, delta_time: f32) {
+        if self.state != PlayState::Playing {
+            return;
+        }
+
+        let duration = self.length();
+        let rate = self.effective_rate();
+        let time = self.time + delta_time * rate;
+        if rate > 0.0 && time >= duration {
+            self.time = duration;
+            self.state = PlayState::Finished;
+        } else if rate < 0.0 && time <= 0.0 {
+            self.time = 0.0;
+            self.state = PlayState::Finished;
+        } else {
+            self.time = time;
+        }
+        self.animator.seek(self.time);
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        self.animator.apply(shape);
+    }
+
+    fn apply_over(&self, below: &PropertySnapshot, shape: &mut dyn Shape) {
+        self.animator.apply_over(below, shape);
+    }
+
+    fn duration(&self) -> f32 {
+        self.animator.duration()
+    }
+
+    fn seek(&mut self, time: f32) {
+        Player::seek(self, time);
+    }
+
+    fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        self.animator.drain_events()
+    }
+
+    fn set_parameter(&mut self, name: &str, value: Parameter) {
+        self.animator.set_parameter(name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::state_machine::StateMachine;
+
+    /// Records the last time it was sought to.
+    struct Probe {
+        duration: f32,
+        time: f32,
+    }
+
+    impl Animator for Probe {
+        fn update(&mut self, delta_time: f32) {
+            self.time += delta_time;
+        }
+
+        fn apply(&self, _shape: &mut dyn Shape) {}
+
+        fn duration(&self) -> f32 {
+            self.duration
+        }
+
+        fn seek(&mut self, time: f32) {
+            self.time = time;
+        }
+    }
+
+    fn player(duration: f32) -> Player<Probe> {
+        Player::new(Probe { duration, time: -1.0 })
+    }
+
+    #[test]
+    fn seek_survives_nan_and_negative_durations() {
+        let mut nan = player(f32::NAN);
+        nan.seek(1.0);
+        assert_eq!(nan.time(), 0.0);
+        let mut negative = player(-1.0);
+        negative.seek(1.0);
+        assert_eq!(negative.time(), 0.0);
+        let mut finite = player(2.0);
+        finite.seek(f32::NAN);
+        assert_eq!(finite.time(), 0.0);
+    }
+
+    #[test]
+    fn playing_clamps_and_finishes_at_either_end() {
+        let mut player = player(1.0);
+        player.play();
+        player.update(0.6);
+        assert_eq!(player.animator().time, 0.6);
+        player.update(0.6);
+        assert_eq!(player.time(), 1.0);
+        assert_eq!(player.state(), PlayState::Finished);
+
+        player.reverse();
+        player.set_rate(2.0);
+        player.update(0.25);
+        assert_eq!(player.time(), 0.5);
+        player.update(1.0);
+        assert_eq!(player.time(), 0.0);
+        assert_eq!(player.state(), PlayState::Finished);
+    }
+
+    #[test]
+    fn play_after_finishing_restarts() {
+        let mut player = player(1.0);
+        player.play();
+        player.update(2.0);
+        player.play();
+        assert_eq!(player.time(), 0.0);
+        assert_eq!(player.state(), PlayState::Playing);
+    }
+
+    #[test]
+    fn parameters_reach_the_wrapped_animator() {
+        let mut player = Player::new(StateMachine::new());
+        player.set_parameter("speed", Parameter::Number(2.0));
+        assert_eq!(player.animator().parameter("speed"), Some(Parameter::Number(2.0)));
+    }
+}
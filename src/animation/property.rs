@@ -0,0 +1,318 @@
+use glam::Vec2;
+use lyon::tessellation::VertexBuffers;
+use serde::{Deserialize, Serialize};
+use crate::shapes::circle::IVertex;
+use crate::shapes::{Color, Shadow, Shape};
+use super::keyframe::Animatable;
+use super::Animator;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Property {
+    Position,
+    Rotation,
+    Scale,
+    Color,
+    Opacity,
+    Anchor,
+    StrokeWidth,
+    DashOffset,
+    TrimStart,
+    TrimEnd,
+    Frame,
+    ShadowColor,
+    ShadowOffset,
+    ShadowBlur,
+    ShadowOpacity,
+}
+
+impl Property {
+    /// The value an additive layer leaves unchanged: zero, or one for scale and the opacities.
+    pub fn additive_identity(self) -> PropertyValue {
+        match self {
+            Property::Position => PropertyValue::Position(Vec2::ZERO),
+            Property::Rotation => PropertyValue::Rotation(0.0),
+            Property::Scale => PropertyValue::Scale(Vec2::ONE),
+            Property::Color => PropertyValue::Color(Color::BLACK),
+            Property::Opacity => PropertyValue::Opacity(1.0),
+            Property::Anchor => PropertyValue::Anchor(Vec2::ZERO),
+            Property::StrokeWidth => PropertyValue::StrokeWidth(0.0),
+            Property::DashOffset => PropertyValue::DashOffset(0.0),
+            Property::TrimStart => PropertyValue::TrimStart(0.0),
+            Property::TrimEnd => PropertyValue::TrimEnd(0.0),
+            Property::Frame => PropertyValue::Frame(0.0),
+            Property::ShadowColor => PropertyValue::ShadowColor(Color::BLACK),
+            Property::ShadowOffset => PropertyValue::ShadowOffset(Vec2::ZERO),
+            Property::ShadowBlur => PropertyValue::ShadowBlur(0.0),
+            Property::ShadowOpacity => PropertyValue::ShadowOpacity(1.0),
+        }
+    }
+}
+
+/// A value for one animatable property of a `Shape`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Position(Vec2),
+    /// Radians, counter-clockwise.
+    Rotation(f32),
+    Scale(Vec2),
+    Color(Color),
+    Opacity(f32),
+    Anchor(Vec2),
+    StrokeWidth(f32),
+    DashOffset(f32),
+    /// Fraction of the outline's length where the visible stroke begins.
+    TrimStart(f32),
+    TrimEnd(f32),
+    /// Sprite frame index; fractions are dropped when applied.
+    Frame(f32),
+    ShadowColor(Color),
+    ShadowOffset(Vec2),
+    /// Blur radius of the shadow, in view units.
+    ShadowBlur(f32),
+    ShadowOpacity(f32),
+}
+
+impl PropertyValue {
+    pub fn property(&self) -> Property {
+        match self {
+            PropertyValue::Position(_) => Property::Position,
+            PropertyValue::Rotation(_) => Property::Rotation,
+            PropertyValue::Scale(_) => Property::Scale,
+            PropertyValue::Color(_) => Property::Color,
+            PropertyValue::Opacity(_) => Property::Opacity,
+            PropertyValue::Anchor(_) => Property::Anchor,
+            PropertyValue::StrokeWidth(_) => Property::StrokeWidth,
+            PropertyValue::DashOffset(_) => Property::DashOffset,
+            PropertyValue::TrimStart(_) => Property::TrimStart,
+            PropertyValue::TrimEnd(_) => Property::TrimEnd,
+            PropertyValue::Frame(_) => Property::Frame,
+            PropertyValue::ShadowColor(_) => Property::ShadowColor,
+            PropertyValue::ShadowOffset(_) => Property::ShadowOffset,
+            PropertyValue::ShadowBlur(_) => Property::ShadowBlur,
+            PropertyValue::ShadowOpacity(_) => Property::ShadowOpacity,
+        }
+    }
+
+    pub fn apply(self, shape: &mut dyn Shape) {
+        match self {
+            PropertyValue::Position(v) => shape.set_position(v),
+            PropertyValue::Rotation(v) => shape.set_rotation(v),
+            PropertyValue::Scale(v) => shape.set_scale(v),
+            PropertyValue::Color(v) => shape.set_color(v),
+            PropertyValue::Opacity(v) => shape.set_opacity(v),
+            PropertyValue::Anchor(v) => shape.set_anchor(v),
+            PropertyValue::StrokeWidth(v) => shape.set_stroke_width(v),
+            PropertyValue::DashOffset(v) => shape.set_dash_offset(v),
+            PropertyValue::TrimStart(v) => shape.set_trim_start(v),
+            PropertyValue::TrimEnd(v) => shape.set_trim_end(v),
+            PropertyValue::Frame(v) => shape.set_frame(v.max(0.0) as usize),
+            PropertyValue::ShadowColor(v) => shape.set_shadow_color(v),
+            PropertyValue::ShadowOffset(v) => shape.set_shadow_offset(v),
+            PropertyValue::ShadowBlur(v) => shape.set_shadow_blur(v.max(0.0)),
+            PropertyValue::ShadowOpacity(v) => shape.set_shadow_opacity(v),
+        }
+    }
+
+    /// Blends toward `other` by `t`. Values of different properties don't mix, so
+    /// `other` wins outright. Frames snap to whichever side is nearer.
+    pub fn lerp(self, other: PropertyValue, t: f32) -> PropertyValue {
+        match (self, other) {
+            (PropertyValue::Position(a), PropertyValue::Position(b)) => PropertyValue::Position(Vec2::lerp(a, b, t)),
+            (PropertyValue::Rotation(a), PropertyValue::Rotation(b)) => PropertyValue::Rotation(f32::lerp(a, b, t)),
+            (PropertyValue::Scale(a), PropertyValue::Scale(b)) => PropertyValue::Scale(Vec2::lerp(a, b, t)),
+            (PropertyValue::Color(a), PropertyValue::Color(b)) => PropertyValue::Color(Color::lerp(a, b, t)),
+            (PropertyValue::Opacity(a), PropertyValue::Opacity(b)) => PropertyValue::Opacity(f32::lerp(a, b, t)),
+            (PropertyValue::Anchor(a), PropertyValue::Anchor(b)) => PropertyValue::Anchor(Vec2::lerp(a, b, t)),
+            (PropertyValue::StrokeWidth(a), PropertyValue::StrokeWidth(b)) => PropertyValue::StrokeWidth(f32::lerp(a, b, t)),
+            (PropertyValue::DashOffset(a), PropertyValue::DashOffset(b)) => PropertyValue::DashOffset(f32::lerp(a, b, t)),
+            (PropertyValue::TrimStart(a), PropertyValue::TrimStart(b)) => PropertyValue::TrimStart(f32::lerp(a, b, t)),
+            (PropertyValue::TrimEnd(a), PropertyValue::TrimEnd(b)) => PropertyValue::TrimEnd(f32::lerp(a, b, t)),
+            (PropertyValue::Frame(a), PropertyValue::Frame(b)) => PropertyValue::Frame(if t < 0.5 { a } else { b }),
+            (PropertyValue::ShadowColor(a), PropertyValue::ShadowColor(b)) => PropertyValue::ShadowColor(Color::lerp(a, b, t)),
+            (PropertyValue::ShadowOffset(a), PropertyValue::ShadowOffset(b)) => PropertyValue::ShadowOffset(Vec2::lerp(a, b, t)),
+            (PropertyValue::ShadowBlur(a), PropertyValue::ShadowBlur(b)) => PropertyValue::ShadowBlur(f32::lerp(a, b, t)),
+            (PropertyValue::ShadowOpacity(a), PropertyValue::ShadowOpacity(b)) => PropertyValue::ShadowOpacity(f32::lerp(a, b, t)),
+            _ => other,
+        }
+    }
+
+    /// Layers `delta`, scaled by `weight`, on top of this value. Scale and the
+    /// opacities multiply; everything else adds.
+    pub fn add(self, delta: PropertyValue, weight: f32) -> PropertyValue {
+        match (self, delta) {
+            (PropertyValue::Position(a), PropertyValue::Position(b)) => PropertyValue::Position(a + b * weight),
+            (PropertyValue::Rotation(a), PropertyValue::Rotation(b)) => PropertyValue::Rotation(a + b * weight),
+            (PropertyValue::Scale(a), PropertyValue::Scale(b)) => PropertyValue::Scale(a * Vec2::ONE.lerp(b, weight)),
+            (PropertyValue::Color(a), PropertyValue::Color(b)) => PropertyValue::Color(a + b * weight),
+            (PropertyValue::Opacity(a), PropertyValue::Opacity(b)) => PropertyValue::Opacity(a * f32::lerp(1.0, b, weight)),
+            (PropertyValue::Anchor(a), PropertyValue::Anchor(b)) => PropertyValue::Anchor(a + b * weight),
+            (PropertyValue::StrokeWidth(a), PropertyValue::StrokeWidth(b)) => PropertyValue::StrokeWidth(a + b * weight),
+            (PropertyValue::DashOffset(a), PropertyValue::DashOffset(b)) => PropertyValue::DashOffset(a + b * weight),
+            (PropertyValue::TrimStart(a), PropertyValue::TrimStart(b)) => PropertyValue::TrimStart(a + b * weight),
+            (PropertyValue::TrimEnd(a), PropertyValue::TrimEnd(b)) => PropertyValue::TrimEnd(a + b * weight),
+            (PropertyValue::Frame(a), PropertyValue::Frame(b)) => PropertyValue::Frame(a + (b * weight).round()),
+            (PropertyValue::ShadowColor(a), PropertyValue::ShadowColor(b)) => PropertyValue::ShadowColor(a + b * weight),
+            (PropertyValue::ShadowOffset(a), PropertyValue::ShadowOffset(b)) => PropertyValue::ShadowOffset(a + b * weight),
+            (PropertyValue::ShadowBlur(a), PropertyValue::ShadowBlur(b)) => PropertyValue::ShadowBlur(a + b * weight),
+            (PropertyValue::ShadowOpacity(a), PropertyValue::ShadowOpacity(b)) => {
+                PropertyValue::ShadowOpacity(a * f32::lerp(1.0, b, weight))
+            }
+            _ => delta,
+        }
+    }
+}
+
+/// A `Shape` that only remembers what was set on it, so the output of several
+/// animators can be captured and blended before it reaches a real shape.
+#[derive(Clone, Debug, Default)]
+pub struct PropertySnapshot {
+    values: Vec<PropertyValue>,
+    geometry: Option<VertexBuffers<IVertex, u16>>,
+}
+
+impl PropertySnapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What `animator` would apply right now.
+    pub fn capture(animator: &dyn Animator) -> Self {
+        let mut snapshot = Self::new();
+        animator.apply(&mut snapshot);
+        snapshot
+    }
+
+    /// What `animator` would apply right now on top of `below`.
+    pub fn capture_over(animator: &dyn Animator, below: &PropertySnapshot) -> Self {
+        let mut snapshot = Self::new();
+        animator.apply_over(below, &mut snapshot);
+        snapshot
+    }
+
+    pub fn values(&self) -> &[PropertyValue] {
+        &self.values
+    }
+
+    pub fn get(&self, property: Property) -> Option<PropertyValue> {
+        self.values.iter().copied().find(|v| v.property() == property)
+    }
+
+    /// Records `value`, replacing an earlier value of the same property.
+    pub fn set(&mut self, value: PropertyValue) {
+        match self.values.iter_mut().find(|v| v.property() == value.property()) {
+            Some(slot) => *slot = value,
+            None => self.values.push(value),
+        }
+    }
+
+    /// Moves from `self` toward `other` by `t`. A property only one side sets keeps
+    /// that side's value; geometry is not interpolated and comes from `other` when
+    /// it has any.
+    pub fn blend(&self, other: &PropertySnapshot, t: f32) -> PropertySnapshot {
+        let mut blended = self.clone();
+        for value in &other.values {
+            let value = match self.get(value.property()) {
+                Some(from) => from.lerp(*value, t),
+                None => *value,
+            };
+            blended.set(value);
+        }
+        if other.geometry.is_some() {
+            blended.geometry.clone_from(&other.geometry);
+        }
+        blended
+    }
+
+    /// Adds `other` on top, scaled by `weight`; see `PropertyValue::add`. Geometry
+    /// can't be added and is left alone.
+    pub fn add(&self, other: &PropertySnapshot, weight: f32) -> PropertySnapshot {
+        let mut sum = self.clone();
+        for value in &other.values {
+            let base = self
+                .get(value.property())
+                .unwrap_or_else(|| value.property().additive_identity());
+            sum.set(base.add(*value, weight));
+        }
+        sum
+    }
+
+    pub fn apply(&self, shape: &mut dyn Shape) {
+        for value in &self.values {
+            value.apply(shape);
+        }
+        if let Some(geometry) = &self.geometry {
+            shape.set_geometry(geometry);
+        }
+    }
+}
+
+impl Shape for PropertySnapshot {
+    fn set_color(&mut self, color: Color) {
+        self.set(PropertyValue::Color(color));
+    }
+
+    fn set_shadow(&mut self, _shadow: Shadow) {}
+
+    fn set_position(&mut self, position: Vec2) {
+        self.set(PropertyValue::Position(position));
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.set(PropertyValue::Rotation(rotation));
+    }
+
+    fn set_scale(&mut self, scale: Vec2) {
+        self.set(PropertyValue::Scale(scale));
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.set(PropertyValue::Opacity(opacity));
+    }
+
+    fn set_anchor(&mut self, anchor: Vec2) {
+        self.set(PropertyValue::Anchor(anchor));
+    }
+
+    fn set_stroke_width(&mut self, width: f32) {
+        self.set(PropertyValue::StrokeWidth(width));
+    }
+
+    fn set_dash_offset(&mut self, offset: f32) {
+        self.set(PropertyValue::DashOffset(offset));
+    }
+
+    fn set_trim_start(&mut self, start: f32) {
+        self.set(PropertyValue::TrimStart(start));
+    }
+
+    fn set_trim_end(&mut self, end: f32) {
+        self.set(PropertyValue::TrimEnd(end));
+    }
+
+    fn set_frame(&mut self, frame: usize) {
+        self.set(PropertyValue::Frame(frame as f32));
+    }
+
+    fn set_shadow_color(&mut self, color: Color) {
+        self.set(PropertyValue::ShadowColor(color));
+    }
+
+    fn set_shadow_offset(&mut self, offset: Vec2) {
+        self.set(PropertyValue::ShadowOffset(offset));
+    }
+
+    fn set_shadow_blur(&mut self, blur_radius: f32) {
+        self.set(PropertyValue::ShadowBlur(blur_radius));
+    }
+
+    fn set_shadow_opacity(&mut self, opacity: f32) {
+        self.set(PropertyValue::ShadowOpacity(opacity));
+    }
+
+    fn set_geometry(&mut self, geometry: &VertexBuffers<IVertex, u16>) {
+        self.geometry = Some(geometry.clone());
+    }
+
+    fn draw(&self, _render_pass: &mut wgpu::RenderPass) {}
+}
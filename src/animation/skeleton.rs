@@ -0,0 +1,213 @@
+use glam::{Mat4, Vec2};
+use crate::shapes::{Color, Shadow, Shape};
+
+/// A bone's transform relative to its parent: scale, then rotate, then translate.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoneTransform {
+    pub position: Vec2,
+    pub rotation: f32,
+    pub scale: Vec2,
+}
+
+impl Default for BoneTransform {
+    fn default() -> Self {
+        BoneTransform {
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        }
+    }
+}
+
+impl BoneTransform {
+    pub fn new(position: Vec2, rotation: f32) -> Self {
+        BoneTransform { position, rotation, ..Self::default() }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_translation(self.position.extend(0.0))
+            * Mat4::from_rotation_z(self.rotation)
+            * Mat4::from_scale(self.scale.extend(1.0))
+    }
+}
+
+/// One joint of a `Skeleton`. The bone extends `length` along its local x axis,
+/// which is only used to work out skin weights.
+///
+/// A bone is a `Shape` whose position, rotation and scale set its pose, so the
+/// usual animators (keyframes, state machines, layer stacks) animate bones too.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bone {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub length: f32,
+    /// Bind pose, the pose the mesh was authored in.
+    pub rest: BoneTransform,
+    pub pose: BoneTransform,
+}
+
+impl Shape for Bone {
+    fn set_color(&mut self, _color: Color) {}
+
+    fn set_shadow(&mut self, _shadow: Shadow) {}
+
+    fn set_position(&mut self, position: Vec2) {
+        self.pose.position = position;
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.pose.rotation = rotation;
+    }
+
+    fn set_scale(&mut self, scale: Vec2) {
+        self.pose.scale = scale;
+    }
+
+    fn draw(&self, _render_pass: &mut wgpu::RenderPass) {}
+}
+
+/// A hierarchy of bones. Parents always come before their children, so world
+/// transforms resolve in a single pass.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+    bones: Vec<Bone>,
+    /// Inverse of each bone's world transform in the bind pose.
+    inverse_bind: Vec<Mat4>,
+}
+
+impl Skeleton {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a bone posed at `rest` relative to `parent` and returns its index.
+    /// Panics if `parent` hasn't been added yet.
+    pub fn add_bone(&mut self, name: &str, parent: Option<usize>, rest: BoneTransform, length: f32) -> usize {
+        let parent_world = match parent {
+            Some(parent) => {
+                assert!(parent < self.bones.len(), "parent bone {} added after its child", parent);
+                self.inverse_bind[parent].inverse()
+            }
+            None => Mat4::IDENTITY,
+        };
+        self.inverse_bind.push((parent_world * rest.matrix()).inverse());
+        self.bones.push(Bone {
+            name: name.to_string(),
+            parent,
+            length,
+            rest,
+            pose: rest,
+        });
+        self.bones.len() - 1
+    }
+
+    pub fn with_bone(mut self, name: &str, parent: Option<usize>, rest: BoneTransform, length: f32) -> Self {
+        self.add_bone(name, parent, rest, length);
+        self
+    }
+
+    pub fn bones(&self) -> &[Bone] {
+        &self.bones
+    }
+
+    pub fn bone(&self, index: usize) -> Option<&Bone> {
+        self.bones.get(index)
+    }
+
+    pub fn bone_mut(&mut self, index: usize) -> Option<&mut Bone> {
+        self.bones.get_mut(index)
+    }
+
+    pub fn bone_index(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|bone| bone.name == name)
+    }
+
+    /// Puts every bone back in its bind pose.
+    pub fn reset_pose(&mut self) {
+        for bone in &mut self.bones {
+            bone.pose = bone.rest;
+        }
+    }
+
+    /// Each bone's posed transform in skeleton space.
+    pub fn world_matrices(&self) -> Vec<Mat4> {
+        let mut world: Vec<Mat4> = Vec::with_capacity(self.bones.len());
+        for bone in &self.bones {
+            let local = bone.pose.matrix();
+            let matrix = match bone.parent {
+                Some(parent) => world[parent] * local,
+                None => local,
+            };
+            world.push(matrix);
+        }
+        world
+    }
+
+    /// Per-bone matrices taking a vertex from the bind pose to the current pose,
+    /// what linear-blend skinning weights together.
+    pub fn skin_matrices(&self) -> Vec<Mat4> {
+        self.world_matrices()
+            .into_iter()
+            .zip(&self.inverse_bind)
+            .map(|(world, inverse_bind)| world * *inverse_bind)
+            .collect()
+    }
+
+    /// Each bone as a segment from its origin to its tip, in the bind pose.
+    pub fn rest_segments(&self) -> Vec<(Vec2, Vec2)> {
+        self.bones
+            .iter()
+            .zip(&self.inverse_bind)
+            .map(|(bone, inverse_bind)| {
+                let world = inverse_bind.inverse();
+                (world.transform_point3(glam::Vec3::ZERO).truncate(), world.transform_point3(glam::Vec3::X * bone.length).truncate())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use glam::Vec3;
+    use super::*;
+
+    /// A two-bone arm along x: `upper` at the origin, `lower` at its tip.
+    fn arm() -> Skeleton {
+        Skeleton::new()
+            .with_bone("upper", None, BoneTransform::new(Vec2::new(1.0, 0.0), 0.0), 1.0)
+            .with_bone("lower", Some(0), BoneTransform::new(Vec2::new(1.0, 0.0), 0.0), 1.0)
+    }
+
+    #[test]
+    fn skin_matrices_are_identity_in_the_bind_pose() {
+        let lower = BoneTransform { scale: Vec2::splat(2.0), ..BoneTransform::new(Vec2::new(1.0, 0.0), -0.7) };
+        let skeleton = Skeleton::new()
+            .with_bone("upper", None, BoneTransform::new(Vec2::new(1.0, 0.0), 0.3), 1.0)
+            .with_bone("lower", Some(0), lower, 1.0);
+        for matrix in skeleton.skin_matrices() {
+            assert!(matrix.abs_diff_eq(Mat4::IDENTITY, 1e-5), "{:?}", matrix);
+        }
+    }
+
+    #[test]
+    fn a_child_follows_its_parents_rotation() {
+        let mut skeleton = arm();
+        skeleton.bone_mut(0).unwrap().set_rotation(FRAC_PI_2);
+
+        // 肘部 (2, 0) 绕肩部 (1, 0) 转到 (1, 1)
+        let elbow = skeleton.world_matrices()[1].transform_point3(Vec3::ZERO);
+        assert!(elbow.abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-5), "{:?}", elbow);
+        let hand = skeleton.skin_matrices()[1].transform_point3(Vec3::new(3.0, 0.0, 0.0));
+        assert!(hand.abs_diff_eq(Vec3::new(1.0, 2.0, 0.0), 1e-5), "{:?}", hand);
+
+        skeleton.reset_pose();
+        assert!(skeleton.skin_matrices()[1].abs_diff_eq(Mat4::IDENTITY, 1e-6));
+    }
+
+    #[test]
+    fn rest_segments_run_along_each_bone() {
+        let segments = arm().rest_segments();
+        assert_eq!(segments, vec![(Vec2::new(1.0, 0.0), Vec2::new(2.0, 0.0)), (Vec2::new(2.0, 0.0), Vec2::new(3.0, 0.0))]);
+    }
+}
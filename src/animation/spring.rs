@@ -0,0 +1,274 @@
+use crate::shapes::{Color, Shape};
+use super::Animator;
+use super::keyframe::Animatable;
+use super::property::PropertyValue;
+
+/// Values a spring can move: anything animatable with a notion of size.
+pub trait SpringValue: Animatable {
+    fn magnitude(self) -> f32;
+}
+
+impl SpringValue for f32 {
+    fn magnitude(self) -> f32 {
+        self.abs()
+    }
+}
+
+impl SpringValue for glam::Vec2 {
+    fn magnitude(self) -> f32 {
+        self.length()
+    }
+}
+
+impl SpringValue for glam::Vec3 {
+    fn magnitude(self) -> f32 {
+        self.length()
+    }
+}
+
+impl SpringValue for Color {
+    fn magnitude(self) -> f32 {
+        self.to_vec3().length()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpringConfig {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub mass: f32,
+    /// The spring settles once its speed and distance to target are both under these.
+    pub rest_speed: f32,
+    pub rest_delta: f32,
+}
+
+impl Default for SpringConfig {
+    fn default() -> Self {
+        SpringConfig {
+            stiffness: 170.0,
+            damping: 26.0,
+            mass: 1.0,
+            rest_speed: 0.001,
+            rest_delta: 0.001,
+        }
+    }
+}
+
+// 积分步长上限, 刚度较大时大步长会发散
+const MAX_STEP: f32 = 1.0 / 240.0;
+
+/// A damped harmonic oscillator pulling `value` towards `target`.
+#[derive(Copy, Clone, Debug)]
+pub struct Spring<T> {
+    pub config: SpringConfig,
+    value: T,
+    velocity: T,
+    target: T,
+}
+
+impl<T: SpringValue> Spring<T> {
+    pub fn new(value: T, config: SpringConfig) -> Self {
+        Spring {
+            config,
+            value,
+            velocity: value * 0.0,
+            target: value,
+        }
+    }
+
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    pub fn velocity(&self) -> T {
+        self.velocity
+    }
+
+    pub fn target(&self) -> T {
+        self.target
+    }
+
+    /// Moves the target without touching the current velocity, so a spring that is
+    /// already in flight bends towards the new target instead of restarting.
+    pub fn set_target(&mut self, target: T) {
+        self.target = target;
+    }
+
+    /// Jumps to `value` and stops.
+    pub fn snap_to(&mut self, value: T) {
+        self.value = value;
+        self.target = value;
+        self.velocity = value * 0.0;
+    }
+
+    pub fn add_velocity(&mut self, impulse: T) {
+        self.velocity = self.velocity + impulse;
+    }
+
+    pub fn is_at_rest(&self) -> bool {
+        self.velocity.magnitude() < self.config.rest_speed
+            && (self.target - self.value).magnitude() < self.config.rest_delta
+    }
+
+    /// Advances by `delta_time`; an infinite step lands on the target.
+    pub fn step(&mut self, delta_time: f32) {
+        if delta_time == f32::INFINITY {
+            self.snap_to(self.target);
+            return;
+        }
+        if self.is_at_rest() {
+            return;
+        }
+
+        // 步数用整数计: 剩余时间很大时 f32 减去一个步长会舍入回原值
+        let delta_time = delta_time.max(0.0);
+        let steps = (delta_time / MAX_STEP).ceil() as u64;
+        let h = delta_time / steps.max(1) as f32;
+        let mass = self.config.mass.max(f32::EPSILON);
+        for _ in 0..steps {
+            let force = (self.target - self.value) * self.config.stiffness - self.velocity * self.config.damping;
+            self.velocity = self.velocity + force * (h / mass);
+            self.value = self.value + self.velocity * h;
+            if self.is_at_rest() {
+                break;
+            }
+        }
+
+        if self.is_at_rest() {
+            self.snap_to(self.target);
+        }
+    }
+}
+
+/// Drives one shape property with a spring, e.g.
+/// `SpringAnimator::new(Spring::new(Vec2::ZERO, config), PropertyValue::Position)`.
+///
+/// Seeking forward simulates the gap; seeking backwards restarts from the spring
+/// as it was passed to `new` and re-simulates, pulling towards the current target.
+pub struct SpringAnimator<T> {
+    spring: Spring<T>,
+    start: Spring<T>,
+    time: f32,
+    bind: fn(T) -> PropertyValue,
+}
+
+impl<T: SpringValue> SpringAnimator<T> {
+    pub fn new(spring: Spring<T>, bind: fn(T) -> PropertyValue) -> Self {
+        SpringAnimator { spring, start: spring, time: 0.0, bind }
+    }
+
+    pub fn set_target(&mut self, target: T) {
+        self.spring.set_target(target);
+    }
+
+    pub fn spring(&self) -> &Spring<T> {
+        &self.spring
+    }
+
+    pub fn spring_mut(&mut self) -> &mut Spring<T> {
+        &mut self.spring
+    }
+}
+
+impl<T: SpringValue> Animator for SpringAnimator<T> {
+    fn update(&mut self, delta_time: f32) {
+        self.spring.step(delta_time);
+        self.time += delta_time.max(0.0);
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        (self.bind)(self.spring.value()).apply(shape);
+    }
+
+    /// Seeking to infinity snaps the spring to its target.
+    fn seek(&mut self, time: f32) {
+        let time = if time.is_nan() { 0.0 } else { time.max(0.0) };
+        if time == f32::INFINITY {
+            self.spring.snap_to(self.spring.target());
+            self.time = time;
+            return;
+        }
+        if time < self.time {
+            // 弹簧只能向前积分, 往回跳时从初始状态重新模拟
+            let target = self.spring.target();
+            self.spring = self.start;
+            self.spring.set_target(target);
+            self.time = 0.0;
+        }
+        self.spring.step(time - self.time);
+        self.time = time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::property::PropertySnapshot;
+
+    fn animator() -> SpringAnimator<f32> {
+        let mut spring = Spring::new(0.0, SpringConfig::default());
+        spring.set_target(1.0);
+        SpringAnimator::new(spring, PropertyValue::Opacity)
+    }
+
+    fn opacity(animator: &SpringAnimator<f32>) -> f32 {
+        let mut snapshot = PropertySnapshot::new();
+        animator.apply(&mut snapshot);
+        match snapshot.get(crate::animation::property::Property::Opacity) {
+            Some(PropertyValue::Opacity(value)) => value,
+            _ => panic!("spring applied no opacity"),
+        }
+    }
+
+    #[test]
+    fn seek_forward_matches_updating() {
+        let mut updated = animator();
+        for _ in 0..30 {
+            updated.update(1.0 / 60.0);
+        }
+        let mut seeked = animator();
+        seeked.seek(0.5);
+        assert!((opacity(&updated) - opacity(&seeked)).abs() < 1e-4);
+        assert!(opacity(&seeked) > 0.5);
+    }
+
+    #[test]
+    fn seek_back_restarts_from_the_initial_state() {
+        let mut animator = animator();
+        animator.seek(0.3);
+        let at_point_three = opacity(&animator);
+        animator.seek(2.0);
+        animator.seek(0.0);
+        assert_eq!(opacity(&animator), 0.0);
+        animator.seek(0.3);
+        assert!((opacity(&animator) - at_point_three).abs() < 1e-6);
+    }
+
+    #[test]
+    fn seeking_far_ahead_settles() {
+        let mut animator = animator();
+        animator.seek(200_000.0);
+        assert_eq!(opacity(&animator), 1.0);
+        animator.seek(f32::INFINITY);
+        assert_eq!(opacity(&animator), 1.0);
+        let mut player = crate::animation::playback::Player::new(SpringAnimator::new(animator.start, PropertyValue::Opacity));
+        player.seek(f32::INFINITY);
+        assert!(player.animator().spring().is_at_rest());
+        // 无阻尼的弹簧永远停不下来, 也要在有限步数内返回
+        let mut spring = Spring::new(0.0, SpringConfig { damping: 0.0, ..SpringConfig::default() });
+        spring.set_target(1.0);
+        spring.step(10_000.0);
+        assert!(spring.value().is_finite());
+        spring.step(f32::INFINITY);
+        assert_eq!(spring.value(), 1.0);
+    }
+
+    #[test]
+    fn spring_settles_on_target() {
+        let mut spring = Spring::new(0.0, SpringConfig::default());
+        spring.set_target(2.0);
+        spring.step(5.0);
+        assert!(spring.is_at_rest());
+        assert_eq!(spring.value(), 2.0);
+    }
+}
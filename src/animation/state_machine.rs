@@ -0,0 +1,522 @@
+use std::collections::HashMap;
+use crate::shapes::Shape;
+use super::Animator;
+use super::event::AnimationEvent;
+use super::property::PropertySnapshot;
+
+/// A value transitions can test. Parameters that were never set read as
+/// `false` / `0.0`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Parameter {
+    Bool(bool),
+    Number(f32),
+    /// Stays set until a transition that tests it is taken.
+    Trigger(bool),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    Bool(String, bool),
+    Greater(String, f32),
+    Less(String, f32),
+    Trigger(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    /// Source state; `None` allows the transition from every other state.
+    pub from: Option<String>,
+    pub to: String,
+    /// All must hold for the transition to be taken.
+    pub conditions: Vec<Condition>,
+    /// Seconds the source state must have played before the transition can be taken.
+    pub exit_time: Option<f32>,
+    /// Length of the cross-fade into the target state, in seconds.
+    pub fade: f32,
+}
+
+impl Transition {
+    pub fn new(from: &str, to: &str) -> Self {
+        Transition {
+            from: Some(from.to_string()),
+            to: to.to_string(),
+            conditions: vec![],
+            exit_time: None,
+            fade: 0.0,
+        }
+    }
+
+    pub fn from_any(to: &str) -> Self {
+        Transition {
+            from: None,
+            ..Self::new("", to)
+        }
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn when(self, name: &str, value: bool) -> Self {
+        self.with_condition(Condition::Bool(name.to_string(), value))
+    }
+
+    pub fn on_trigger(self, name: &str) -> Self {
+        self.with_condition(Condition::Trigger(name.to_string()))
+    }
+
+    pub fn with_exit_time(mut self, exit_time: f32) -> Self {
+        self.exit_time = Some(exit_time);
+        self
+    }
+
+    pub fn with_fade(mut self, fade: f32) -> Self {
+        self.fade = fade.max(0.0);
+        self
+    }
+}
+
+struct State {
+    name: String,
+    animator: Box<dyn Animator>,
+}
+
+enum FadeSource {
+    /// The previous state, still playing underneath.
+    State(usize),
+    /// A frozen pose, used when a fade is interrupted or a state re-enters itself.
+    Pose(PropertySnapshot),
+}
+
+struct CrossFade {
+    from: FadeSource,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Switches between named states, each playing its own animator (usually a
+/// `Timeline`), as parameters change.
+///
+/// At most one transition is taken per update, checked in the order they were
+/// added and before the clock advances. Entering a state restarts its animator.
+/// During a cross-fade the previous state keeps playing and its output is
+/// blended into the new one.
+pub struct StateMachine {
+    states: Vec<State>,
+    transitions: Vec<Transition>,
+    parameters: HashMap<String, Parameter>,
+    current: usize,
+    state_time: f32,
+    fade: Option<CrossFade>,
+    events: Vec<AnimationEvent>,
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateMachine {
+    pub fn new() -> Self {
+        StateMachine {
+            states: vec![],
+            transitions: vec![],
+            parameters: HashMap::new(),
+            current: 0,
+            state_time: 0.0,
+            fade: None,
+            events: vec![],
+        }
+    }
+
+    /// Adds a state; the first one added is where the machine starts.
+    pub fn add_state(&mut self, name: &str, animator: Box<dyn Animator>) {
+        self.states.push(State { name: name.to_string(), animator });
+    }
+
+    pub fn with_state(mut self, name: &str, animator: Box<dyn Animator>) -> Self {
+        self.add_state(name, animator);
+        self
+    }
+
+    pub fn add_transition(&mut self, transition: Transition) {
+        self.transitions.push(transition);
+    }
+
+    pub fn with_transition(mut self, transition: Transition) -> Self {
+        self.add_transition(transition);
+        self
+    }
+
+    pub fn current_state(&self) -> Option<&str> {
+        self.states.get(self.current).map(|state| state.name.as_str())
+    }
+
+    /// Seconds since the current state was entered.
+    pub fn state_time(&self) -> f32 {
+        self.state_time
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<Parameter> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.parameters.insert(name.to_string(), Parameter::Bool(value));
+    }
+
+    pub fn set_number(&mut self, name: &str, value: f32) {
+        self.parameters.insert(name.to_string(), Parameter::Number(value));
+    }
+
+    pub fn set_trigger(&mut self, name: &str) {
+        self.parameters.insert(name.to_string(), Parameter::Trigger(true));
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) {
+        self.parameters.insert(name.to_string(), Parameter::Trigger(false));
+    }
+
+    fn state_index(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|state| state.name == name)
+    }
+
+    fn number(&self, name: &str) -> f32 {
+        match self.parameters.get(name) {
+            Some(Parameter::Number(v)) => *v,
+            Some(Parameter::Bool(v)) | Some(Parameter::Trigger(v)) => *v as u8 as f32,
+            None => 0.0,
+        }
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        match self.parameters.get(name) {
+            Some(Parameter::Bool(v)) | Some(Parameter::Trigger(v)) => *v,
+            Some(Parameter::Number(v)) => *v != 0.0,
+            None => false,
+        }
+    }
+
+    fn holds(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Bool(name, value) => self.flag(name) == *value,
+            Condition::Greater(name, value) => self.number(name) > *value,
+            Condition::Less(name, value) => self.number(name) < *value,
+            Condition::Trigger(name) => self.flag(name),
+        }
+    }
+
+    /// The first transition that can be taken now and the state it leads to.
+    fn next_transition(&self) -> Option<(usize, usize)> {
+        let current = self.current_state()?;
+        self.transitions.iter().enumerate().find_map(|(i, transition)| {
+            match &transition.from {
+                Some(from) if from != current => return None,
+                // 任意状态出发的转换不会转回自身
+                None if transition.to == current => return None,
+                _ => {}
+            }
+            if transition.exit_time.is_some_and(|exit| self.state_time < exit) {
+                return None;
+            }
+            if !transition.conditions.iter().all(|c| self.holds(c)) {
+                return None;
+            }
+            self.state_index(&transition.to).map(|target| (i, target))
+        })
+    }
+
+    fn take(&mut self, transition: usize, target: usize) {
+        let transition = self.transitions[transition].clone();
+        for condition in &transition.conditions {
+            if let Condition::Trigger(name) = condition {
+                self.reset_trigger(name);
+            }
+        }
+
+        let from = if self.fade.is_some() || target == self.current {
+            FadeSource::Pose(self.pose())
+        } else {
+            FadeSource::State(self.current)
+        };
+        self.fade = (transition.fade > 0.0).then_some(CrossFade {
+            from,
+            elapsed: 0.0,
+            duration: transition.fade,
+        });
+
+        self.events.push(AnimationEvent::StateChanged {
+            from: self.states[self.current].name.clone(),
+            to: self.states[target].name.clone(),
+            fade: transition.fade,
+        });
+        self.current = target;
+        self.state_time = 0.0;
+        let animator = &mut self.states[target].animator;
+        animator.seek(0.0);
+        animator.drain_events();
+    }
+
+    /// What the machine would apply right now, cross-fade included.
+    pub fn pose(&self) -> PropertySnapshot {
+        self.pose_over(&PropertySnapshot::new())
+    }
+
+    fn pose_over(&self, below: &PropertySnapshot) -> PropertySnapshot {
+        let Some(state) = self.states.get(self.current) else {
+            return PropertySnapshot::new();
+        };
+        let current = PropertySnapshot::capture_over(&*state.animator, below);
+        match &self.fade {
+            None => current,
+            Some(fade) => {
+                let t = (fade.elapsed / fade.duration).clamp(0.0, 1.0);
+                match &fade.from {
+                    FadeSource::State(from) => {
+                        PropertySnapshot::capture_over(&*self.states[*from].animator, below).blend(&current, t)
+                    }
+                    FadeSource::Pose(pose) => pose.blend(&current, t),
+                }
+            }
+        }
+    }
+}
+
+impl Animator for StateMachine {
+    fn update(&mut self, delta_time: f32) {
+        if self.states.is_empty() {
+            return;
+        }
+        // 先检查转换再推进时间, 这样到达退出时间的那一帧仍然显示原状态的结尾
+        if let Some((transition, target)) = self.next_transition() {
+            self.take(transition, target);
+        }
+        self.state_time += delta_time;
+        let state = &mut self.states[self.current];
+        state.animator.update(delta_time);
+        self.events.extend(state.animator.drain_events());
+
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed += delta_time;
+            if let FadeSource::State(from) = fade.from {
+                let animator = &mut self.states[from].animator;
+                animator.update(delta_time);
+                // 淡出中的状态不再上报事件
+                animator.drain_events();
+            }
+            if fade.elapsed >= fade.duration {
+                self.fade = None;
+            }
+        }
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        self.apply_over(&PropertySnapshot::new(), shape);
+    }
+
+    fn apply_over(&self, below: &PropertySnapshot, shape: &mut dyn Shape) {
+        match &self.fade {
+            None => {
+                if let Some(state) = self.states.get(self.current) {
+                    state.animator.apply_over(below, shape);
+                }
+            }
+            Some(_) => self.pose_over(below).apply(shape),
+        }
+    }
+
+    /// Seeks within the current state and drops any cross-fade.
+    fn seek(&mut self, time: f32) {
+        self.fade = None;
+        self.state_time = time;
+        if let Some(state) = self.states.get_mut(self.current) {
+            state.animator.seek(time);
+        }
+    }
+
+    fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Stores the parameter for transitions and passes it on to every state.
+    fn set_parameter(&mut self, name: &str, value: Parameter) {
+        self.parameters.insert(name.to_string(), value);
+        for state in &mut self.states {
+            state.animator.set_parameter(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+    use super::*;
+    use crate::animation::property::{Property, PropertyValue};
+
+    /// Moves along x from `.0` at one unit per second; `.1` is its clock.
+    struct Ramp(f32, f32);
+
+    impl Animator for Ramp {
+        fn update(&mut self, delta_time: f32) {
+            self.1 += delta_time;
+        }
+
+        fn apply(&self, shape: &mut dyn Shape) {
+            shape.set_position(Vec2::new(self.0 + self.1, 0.0));
+        }
+
+        fn seek(&mut self, time: f32) {
+            self.1 = time;
+        }
+    }
+
+    fn machine() -> StateMachine {
+        StateMachine::new()
+            .with_state("idle", Box::new(Ramp(0.0, 0.0)))
+            .with_state("run", Box::new(Ramp(100.0, 0.0)))
+            .with_state("jump", Box::new(Ramp(200.0, 0.0)))
+    }
+
+    fn x(machine: &StateMachine) -> f32 {
+        match machine.pose().get(Property::Position) {
+            Some(PropertyValue::Position(position)) => position.x,
+            value => panic!("no position: {:?}", value),
+        }
+    }
+
+    #[test]
+    fn bool_and_number_conditions_must_all_hold() {
+        let mut machine = machine()
+            .with_transition(
+                Transition::new("idle", "run")
+                    .when("moving", true)
+                    .with_condition(Condition::Greater("speed".to_string(), 1.0)),
+            )
+            .with_transition(Transition::new("run", "idle").with_condition(Condition::Less("speed".to_string(), 1.0)));
+        machine.set_bool("moving", true);
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("idle"));
+
+        machine.set_number("speed", 2.0);
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("run"));
+
+        machine.set_number("speed", 0.5);
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("idle"));
+    }
+
+    #[test]
+    fn triggers_reset_once_taken() {
+        let mut machine = machine()
+            .with_transition(Transition::new("idle", "jump").on_trigger("jump"))
+            .with_transition(Transition::new("jump", "idle").on_trigger("jump"));
+        machine.set_trigger("jump");
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("jump"));
+        assert_eq!(machine.parameter("jump"), Some(Parameter::Trigger(false)));
+
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("jump"));
+
+        // 通过 Animator 设置的触发器也一样
+        machine.set_parameter("jump", Parameter::Trigger(true));
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("idle"));
+        assert_eq!(machine.parameter("jump"), Some(Parameter::Trigger(false)));
+    }
+
+    #[test]
+    fn exit_time_holds_the_source_state() {
+        let mut machine = machine().with_transition(Transition::new("idle", "run").with_exit_time(1.0));
+        machine.update(0.5);
+        machine.update(0.5);
+        assert_eq!(machine.current_state(), Some("idle"));
+        assert_eq!(machine.state_time(), 1.0);
+
+        // 转换在推进时间之前检查
+        machine.update(0.25);
+        assert_eq!(machine.current_state(), Some("run"));
+        assert_eq!(machine.state_time(), 0.25);
+        assert_eq!(x(&machine), 100.25);
+    }
+
+    #[test]
+    fn any_state_transitions_skip_their_own_target() {
+        let mut machine = machine()
+            .with_transition(Transition::new("idle", "run").when("go", true))
+            .with_transition(Transition::from_any("jump").on_trigger("hit"));
+        machine.set_bool("go", true);
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("run"));
+
+        machine.set_trigger("hit");
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("jump"));
+
+        machine.update(0.5);
+        machine.set_trigger("hit");
+        machine.update(0.5);
+        assert_eq!(machine.current_state(), Some("jump"));
+        assert_eq!(machine.state_time(), 1.1);
+        let reentered = AnimationEvent::StateChanged { from: "jump".to_string(), to: "jump".to_string(), fade: 0.0 };
+        assert!(!machine.drain_events().contains(&reentered));
+    }
+
+    #[test]
+    fn one_transition_per_update() {
+        let mut machine = machine()
+            .with_transition(Transition::new("idle", "run").when("go", true))
+            .with_transition(Transition::new("run", "jump").when("go", true));
+        machine.set_bool("go", true);
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("run"));
+        machine.update(0.1);
+        assert_eq!(machine.current_state(), Some("jump"));
+    }
+
+    #[test]
+    fn state_changed_names_both_states() {
+        let mut machine = machine().with_transition(Transition::new("idle", "run").when("go", true));
+        machine.update(0.1);
+        assert!(machine.drain_events().is_empty());
+        machine.set_bool("go", true);
+        machine.update(0.1);
+        assert_eq!(
+            machine.drain_events(),
+            vec![AnimationEvent::StateChanged { from: "idle".to_string(), to: "run".to_string(), fade: 0.0 }]
+        );
+    }
+
+    #[test]
+    fn cross_fade_plays_the_source_state_and_freezes_when_interrupted() {
+        let mut machine = machine()
+            .with_transition(Transition::new("idle", "run").when("run", true).with_fade(1.0))
+            .with_transition(Transition::new("run", "jump").when("jump", true).with_fade(1.0));
+        machine.set_bool("run", true);
+        machine.update(0.5);
+        assert!(machine.is_fading());
+        // idle 还在播放: 0.5 和 100.5 各占一半
+        assert!((x(&machine) - 50.5).abs() < 1e-4);
+        machine.update(0.25);
+        assert!((x(&machine) - 75.75).abs() < 1e-4);
+
+        // 淡入中被打断, 从冻结的姿态开始淡入
+        machine.set_bool("jump", true);
+        machine.update(0.25);
+        assert_eq!(machine.current_state(), Some("jump"));
+        assert!((x(&machine) - (75.75 + (200.25 - 75.75) * 0.25)).abs() < 1e-3);
+        machine.update(0.25);
+        assert!((x(&machine) - (75.75 + (200.5 - 75.75) * 0.5)).abs() < 1e-3);
+
+        machine.update(0.5);
+        assert!(!machine.is_fading());
+        assert_eq!(x(&machine), 201.0);
+    }
+}
@@ -0,0 +1,440 @@
+use crate::shapes::Shape;
+use super::Animator;
+use super::event::{AnimationEvent, Marker};
+use super::property::PropertySnapshot;
+use super::state_machine::Parameter;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Repeat {
+    /// Total number of play-throughs, so `Count(1)` plays once.
+    Count(u32),
+    Infinite,
+}
+
+type EventListener = Box<dyn FnMut(&AnimationEvent)>;
+
+struct TimelineChild {
+    animator: Box<dyn Animator>,
+    start: f32,
+}
+
+/// Composes animators on a shared clock. A timeline is an `Animator` itself, so
+/// groups nest: a sequence of parallel groups, a staggered group of sequences, etc.
+///
+/// Children are driven through `Animator::seek`, and a child is applied once the
+/// playhead has reached its start so later children don't override earlier ones
+/// before they begin.
+///
+/// Every seek reports what the playhead passed over, however far it jumped: markers
+/// fire in the order they were crossed, once per repetition, followed by loop and
+/// completion events. Events go to the `on_event` callbacks and into a queue read by
+/// `drain_events`.
+pub struct Timeline {
+    children: Vec<TimelineChild>,
+    delay: f32,
+    repeat: Repeat,
+    yoyo: bool,
+    time: f32,
+    local_time: f32,
+    markers: Vec<Marker>,
+    started: bool,
+    events: Vec<AnimationEvent>,
+    listeners: Vec<EventListener>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Timeline {
+            children: vec![],
+            delay: 0.0,
+            repeat: Repeat::Count(1),
+            yoyo: false,
+            time: 0.0,
+            local_time: 0.0,
+            markers: vec![],
+            started: false,
+            events: vec![],
+            listeners: vec![],
+        }
+    }
+
+    /// Children play one after another.
+    pub fn sequence(children: Vec<Box<dyn Animator>>) -> Self {
+        let mut timeline = Self::new();
+        for child in children {
+            timeline.then(child);
+        }
+        timeline
+    }
+
+    /// Children all start together.
+    pub fn parallel(children: Vec<Box<dyn Animator>>) -> Self {
+        let mut timeline = Self::new();
+        for child in children {
+            timeline.add_at(child, 0.0);
+        }
+        timeline
+    }
+
+    /// Child `i` starts `i * offset` seconds after the first.
+    pub fn stagger(children: Vec<Box<dyn Animator>>, offset: f32) -> Self {
+        let mut timeline = Self::new();
+        for (i, child) in children.into_iter().enumerate() {
+            timeline.add_at(child, i as f32 * offset);
+        }
+        timeline
+    }
+
+    pub fn add_at(&mut self, animator: Box<dyn Animator>, start: f32) {
+        self.children.push(TimelineChild { animator, start: start.max(0.0) });
+    }
+
+    /// Appends `animator` after everything currently on the timeline.
+    pub fn then(&mut self, animator: Box<dyn Animator>) {
+        let start = self.iteration_duration();
+        self.add_at(animator, start);
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay.max(0.0);
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Every other repetition plays backwards.
+    pub fn with_yoyo(mut self, yoyo: bool) -> Self {
+        self.yoyo = yoyo;
+        self
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Places a named marker at `time` inside each repetition.
+    pub fn add_marker(&mut self, name: &str, time: f32) {
+        let marker = Marker { name: name.to_string(), time: time.max(0.0) };
+        let index = self.markers.partition_point(|m| m.time <= marker.time);
+        self.markers.insert(index, marker);
+    }
+
+    pub fn with_marker(mut self, name: &str, time: f32) -> Self {
+        self.add_marker(name, time);
+        self
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    pub fn on_event(&mut self, listener: impl FnMut(&AnimationEvent) + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    fn emit(&mut self, event: AnimationEvent) {
+        for listener in &mut self.listeners {
+            listener(&event);
+        }
+        self.events.push(event);
+    }
+
+    fn emit_markers(&mut self, lo: f32, hi: f32, include_lo: bool, include_hi: bool, descending: bool) {
+        let crossed = self
+            .markers
+            .iter()
+            .filter(|m| (m.time > lo || include_lo && m.time == lo) && (m.time < hi || include_hi && m.time == hi))
+            .map(|m| AnimationEvent::Marker { name: m.name.clone(), time: m.time })
+            .collect::<Vec<_>>();
+        if descending {
+            crossed.into_iter().rev().for_each(|event| self.emit(event));
+        } else {
+            crossed.into_iter().for_each(|event| self.emit(event));
+        }
+    }
+
+    /// Emits the markers and loop events between two timeline times.
+    ///
+    /// Elapsed time is split per repetition. An instant where one repetition ends
+    /// and the next begins belongs to the ending one, so a marker sitting on a
+    /// repetition boundary fires exactly once per pass over it.
+    fn emit_crossings(&mut self, from: f32, to: f32, first: bool) {
+        let length = self.iteration_duration();
+        let total = Animator::duration(self) - self.delay;
+        let a = (from - self.delay).max(0.0).min(total);
+        let b = (to - self.delay).max(0.0).min(total);
+        if a == b && !first {
+            return;
+        }
+
+        if !length.is_finite() || length <= 0.0 {
+            if b >= a {
+                self.emit_markers(a, b, first, true, false);
+            } else {
+                self.emit_markers(b, a, true, false, true);
+            }
+            return;
+        }
+
+        let last = match self.repeat {
+            Repeat::Count(count) => count.max(1) - 1,
+            Repeat::Infinite => u32::MAX,
+        };
+        let ending_in = |e: f32| ((e / length).ceil() as u32).saturating_sub(1).min(last);
+        let starting_in = |e: f32| ((e / length).floor() as u32).min(last);
+
+        if b >= a {
+            for i in starting_in(a)..=ending_in(b) {
+                let base = i as f32 * length;
+                let o0 = (a - base).max(0.0);
+                let o1 = (b - base).min(length);
+                if o0 == 0.0 && i > 0 {
+                    self.emit(AnimationEvent::Looped { iteration: i });
+                }
+                let include_start = o0 == 0.0 && if self.yoyo { i == 0 && first } else { i > 0 || first };
+                if self.yoyo && i % 2 == 1 {
+                    self.emit_markers(length - o1, length - o0, true, include_start, true);
+                } else {
+                    self.emit_markers(o0, o1, include_start, true, false);
+                }
+            }
+        } else {
+            let top = ending_in(a);
+            for i in (starting_in(b)..=top).rev() {
+                let base = i as f32 * length;
+                let o0 = (b - base).max(0.0);
+                let o1 = (a - base).min(length);
+                let include_end = o1 == length && i < top && !self.yoyo;
+                if self.yoyo && i % 2 == 1 {
+                    self.emit_markers(length - o1, length - o0, include_end, true, false);
+                } else {
+                    self.emit_markers(o0, o1, true, include_end, true);
+                }
+            }
+        }
+    }
+
+    /// Length of a single play-through, without delay or repeats.
+    pub fn iteration_duration(&self) -> f32 {
+        self.children
+            .iter()
+            .map(|child| child.start + child.animator.duration())
+            .fold(0.0, f32::max)
+    }
+
+    /// Maps timeline time to (iteration index, time inside that iteration).
+    fn resolve(&self, time: f32) -> (u32, f32) {
+        let elapsed = (time - self.delay).max(0.0);
+        let length = self.iteration_duration();
+        if !length.is_finite() || length <= 0.0 {
+            return (0, elapsed.min(length));
+        }
+
+        let (iteration, offset) = match self.repeat {
+            Repeat::Count(count) if elapsed >= length * count.max(1) as f32 => {
+                (count.max(1) - 1, length)
+            }
+            _ => {
+                let iteration = (elapsed / length).floor();
+                (iteration as u32, elapsed - iteration * length)
+            }
+        };
+
+        if self.yoyo && iteration % 2 == 1 {
+            (iteration, length - offset)
+        } else {
+            (iteration, offset)
+        }
+    }
+}
+
+impl Animator for Timeline {
+    fn update(&mut self, delta_time: f32) {
+        self.seek(self.time + delta_time);
+    }
+
+    fn apply(&self, shape: &mut dyn Shape) {
+        self.apply_over(&PropertySnapshot::new(), shape);
+    }
+
+    fn apply_over(&self, below: &PropertySnapshot, shape: &mut dyn Shape) {
+        for child in &self.children {
+            if self.local_time >= child.start {
+                child.animator.apply_over(below, shape);
+            }
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        let length = self.iteration_duration();
+        match self.repeat {
+            _ if length <= 0.0 => self.delay,
+            Repeat::Count(count) => self.delay + length * count.max(1) as f32,
+            Repeat::Infinite => f32::INFINITY,
+        }
+    }
+
+    fn seek(&mut self, time: f32) {
+        let from = self.time;
+        let total = Animator::duration(self);
+        let first = !self.started && time > from && time >= self.delay;
+        if first {
+            self.started = true;
+            self.emit(AnimationEvent::Started);
+        }
+        self.emit_crossings(from, time, first);
+
+        self.time = time;
+        self.local_time = self.resolve(time).1;
+        for child in &mut self.children {
+            // clamp 在上界为 NaN 时会 panic
+            let child_time = (self.local_time - child.start).max(0.0).min(child.animator.duration());
+            child.animator.seek(child_time);
+        }
+        let child_events = self
+            .children
+            .iter_mut()
+            .flat_map(|child| child.animator.drain_events())
+            .collect::<Vec<_>>();
+        for event in child_events {
+            self.emit(event);
+        }
+
+        if time > from && time >= total && (from < total || first) {
+            self.emit(AnimationEvent::Completed);
+        }
+        // 回到起点之前, 下次播放重新触发 Started
+        if time < from && time <= self.delay {
+            self.started = false;
+        }
+    }
+
+    fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn set_parameter(&mut self, name: &str, value: Parameter) {
+        for child in &mut self.children {
+            child.animator.set_parameter(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Does nothing for `0` seconds.
+    struct Hold(f32);
+
+    impl Animator for Hold {
+        fn update(&mut self, _delta_time: f32) {}
+
+        fn apply(&self, _shape: &mut dyn Shape) {}
+
+        fn duration(&self) -> f32 {
+            self.0
+        }
+
+        fn seek(&mut self, _time: f32) {}
+    }
+
+    fn timeline(repeat: Repeat) -> Timeline {
+        Timeline::sequence(vec![Box::new(Hold(1.0))]).with_repeat(repeat)
+    }
+
+    fn marker(name: &str, time: f32) -> AnimationEvent {
+        AnimationEvent::Marker { name: name.to_string(), time }
+    }
+
+    fn play(timeline: &mut Timeline, until: f32, step: f32) -> Vec<AnimationEvent> {
+        let mut events = vec![];
+        while timeline.time() < until {
+            timeline.update(step.min(until - timeline.time()));
+            events.extend(timeline.drain_events());
+        }
+        events
+    }
+
+    #[test]
+    fn markers_fire_once_per_repetition_in_small_steps() {
+        let mut timeline = timeline(Repeat::Count(3)).with_marker("mid", 0.5);
+        let events = play(&mut timeline, 3.5, 0.1);
+        assert_eq!(
+            events,
+            vec![
+                AnimationEvent::Started,
+                marker("mid", 0.5),
+                AnimationEvent::Looped { iteration: 1 },
+                marker("mid", 0.5),
+                AnimationEvent::Looped { iteration: 2 },
+                marker("mid", 0.5),
+                AnimationEvent::Completed,
+            ]
+        );
+    }
+
+    #[test]
+    fn one_big_jump_reports_everything_it_passed() {
+        let mut timeline = timeline(Repeat::Count(3)).with_marker("mid", 0.5);
+        timeline.seek(2.7);
+        let events = timeline.drain_events();
+        assert_eq!(events.iter().filter(|e| **e == marker("mid", 0.5)).count(), 3);
+        assert!(events.contains(&AnimationEvent::Looped { iteration: 2 }));
+        assert!(!events.contains(&AnimationEvent::Completed));
+    }
+
+    #[test]
+    fn boundary_markers_fire_once_per_pass() {
+        let mut timeline = timeline(Repeat::Count(2)).with_marker("start", 0.0).with_marker("end", 1.0);
+        let events = play(&mut timeline, 2.0, 0.25);
+        let count = |name: &str| events.iter().filter(|e| matches!(e, AnimationEvent::Marker { name: n, .. } if n == name)).count();
+        assert_eq!(count("start"), 2);
+        assert_eq!(count("end"), 2);
+        // 边界时刻属于结束的那一轮: 先报 end, 再报 Looped 和下一轮的 start
+        let end = events.iter().position(|e| *e == marker("end", 1.0)).unwrap();
+        let looped = events.iter().position(|e| *e == AnimationEvent::Looped { iteration: 1 }).unwrap();
+        assert!(end < looped);
+    }
+
+    #[test]
+    fn yoyo_repetitions_cross_markers_backwards() {
+        let mut timeline = timeline(Repeat::Count(2)).with_yoyo(true).with_marker("a", 0.25).with_marker("b", 0.75);
+        timeline.seek(0.0);
+        timeline.seek(2.0);
+        let markers = timeline
+            .drain_events()
+            .into_iter()
+            .filter_map(|e| match e {
+                AnimationEvent::Marker { name, .. } => Some(name),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(markers, ["a", "b", "b", "a"]);
+    }
+
+    #[test]
+    fn seeking_backwards_reports_markers_in_descending_order() {
+        let mut timeline = timeline(Repeat::Count(1)).with_marker("a", 0.25).with_marker("b", 0.75);
+        timeline.seek(1.0);
+        timeline.drain_events();
+        timeline.seek(0.0);
+        assert_eq!(timeline.drain_events(), vec![marker("b", 0.75), marker("a", 0.25)]);
+    }
+
+    #[test]
+    fn nan_child_duration_does_not_panic() {
+        let mut timeline = Timeline::parallel(vec![Box::new(Hold(1.0)), Box::new(Hold(f32::NAN))]);
+        timeline.update(0.5);
+        timeline.drain_events();
+    }
+}
@@ -0,0 +1,351 @@
+use std::mem;
+use glam::Vec2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use wgpu::util::DeviceExt;
+use crate::animation::particles::{EmissionClock, EmitterShape, ParticleEmitter};
+use crate::shapes::{Color, Shadow, Shape};
+
+/// Threads per workgroup in `particle_compute.wgsl`.
+pub const WORKGROUP_SIZE: u32 = 64;
+/// Samples taken of each over-life curve.
+const CURVE_SAMPLES: usize = 16;
+/// Points a path emitter is resampled to.
+const PATH_SAMPLES: usize = 64;
+
+/// One particle as stored on the GPU. The compute shader writes it and the
+/// render pipeline reads it straight back as per-instance vertex data.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuParticle {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+    pub rotation: f32,
+    pub spin: f32,
+    pub age: f32,
+    /// Zero for a free slot.
+    pub lifetime: f32,
+    pub base_size: f32,
+    pub size: f32,
+    pub _pad: [f32; 2],
+    /// Current color, alpha is opacity.
+    pub color: [f32; 4],
+}
+
+impl GpuParticle {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<GpuParticle>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                // age, lifetime
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// The emitter and this step's work, laid out like `SimParams` in `particle_compute.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimParams {
+    position: [f32; 2],
+    gravity: [f32; 2],
+    line_from: [f32; 2],
+    line_to: [f32; 2],
+    lifetime: [f32; 2],
+    speed: [f32; 2],
+    size: [f32; 2],
+    angle: [f32; 2],
+    spin: [f32; 2],
+    rotation: f32,
+    direction: f32,
+    spread: f32,
+    drag: f32,
+    radius: f32,
+    dt: f32,
+    /// 0 point, 1 line, 2 circle, 3 path.
+    shape: u32,
+    edge: u32,
+    path_len: u32,
+    spawn: u32,
+    capacity: u32,
+    seed: u32,
+    has_color_curve: u32,
+    _pad: u32,
+    color: [f32; 4],
+    /// Color curve in rgb, opacity curve in a.
+    life_color: [[f32; 4]; CURVE_SAMPLES],
+    /// Size curve in x, speed curve in y.
+    life_scale: [[f32; 4]; CURVE_SAMPLES],
+}
+
+struct GpuState {
+    params_buffer: wgpu::Buffer,
+    particle_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Particles that live entirely on the GPU: a compute pass integrates them and
+/// recycles dead slots through an atomic free list, and the particle buffer is
+/// drawn directly as instance data, so nothing is read back. Suited to far
+/// more particles than `ParticleSystem`, at the cost of not being able to
+/// inspect them from the CPU.
+///
+/// Over-life curves are baked into 16 samples, and path emitters into 64 points.
+pub struct GpuParticleSystem {
+    pub emitter: ParticleEmitter,
+    capacity: u32,
+    clock: EmissionClock,
+    rng: StdRng,
+    /// Time and particles owed to the next dispatch.
+    dt: f32,
+    spawn: u32,
+    gpu: Option<GpuState>,
+}
+
+impl GpuParticleSystem {
+    /// Room for `emitter.max_particles` particles; new ones are dropped while every slot is in use.
+    pub fn new(mut emitter: ParticleEmitter, seed: u64) -> Self {
+        emitter.bursts.sort_by(|a, b| a.time.total_cmp(&b.time));
+        GpuParticleSystem {
+            capacity: emitter.max_particles.clamp(1, u32::MAX as usize) as u32,
+            emitter,
+            clock: EmissionClock::default(),
+            rng: StdRng::seed_from_u64(seed),
+            dt: 0.0,
+            spawn: 0,
+            gpu: None,
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn time(&self) -> f32 {
+        self.clock.time()
+    }
+
+    /// Spawns `count` particles on the next dispatch, on top of the rate and bursts.
+    pub fn emit(&mut self, count: u32) {
+        self.spawn = self.spawn.saturating_add(count);
+    }
+
+    /// Advances the emission clock; the simulation itself catches up on the
+    /// next `dispatch`.
+    pub fn update(&mut self, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+        self.dt += dt;
+        let born = self.clock.advance(&self.emitter, dt, &mut self.rng).len() as u32;
+        self.spawn = self.spawn.saturating_add(born);
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Particle Compute Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1, false),
+                storage(2, false),
+                storage(3, true),
+            ],
+        })
+    }
+
+    fn params(&self, seed: u32) -> SimParams {
+        let e = &self.emitter;
+        let range = |r: crate::animation::particles::RandomRange| [r.min, r.max.max(r.min)];
+        let (shape, line_from, line_to, radius, edge) = match &e.shape {
+            EmitterShape::Point => (0, Vec2::ZERO, Vec2::ZERO, 0.0, false),
+            EmitterShape::Line { from, to } => (1, *from, *to, 0.0, false),
+            EmitterShape::Circle { radius, edge } => (2, Vec2::ZERO, Vec2::ZERO, *radius, *edge),
+            EmitterShape::Path(_) => (3, Vec2::ZERO, Vec2::ZERO, 0.0, false),
+        };
+        let color = e.color.to_array();
+        let sample = |i: usize| i as f32 / (CURVE_SAMPLES - 1) as f32;
+        SimParams {
+            position: e.position.to_array(),
+            gravity: e.gravity.to_array(),
+            line_from: line_from.to_array(),
+            line_to: line_to.to_array(),
+            lifetime: range(e.lifetime),
+            speed: range(e.speed),
+            size: range(e.size),
+            angle: range(e.angle),
+            spin: range(e.spin),
+            rotation: e.rotation,
+            direction: e.direction,
+            spread: e.spread,
+            drag: e.drag,
+            radius,
+            dt: self.dt,
+            shape,
+            edge: edge as u32,
+            path_len: PATH_SAMPLES as u32,
+            spawn: self.spawn.min(self.capacity),
+            capacity: self.capacity,
+            seed,
+            has_color_curve: !e.color_over_life.keyframes().is_empty() as u32,
+            _pad: 0,
+            color: [color[0], color[1], color[2], 1.0],
+            life_color: std::array::from_fn(|i| {
+                let t = sample(i);
+                let c = e.color_over_life.sample(t).unwrap_or(Color::WHITE).to_array();
+                [c[0], c[1], c[2], e.opacity_over_life.sample(t).unwrap_or(1.0)]
+            }),
+            life_scale: std::array::from_fn(|i| {
+                let t = sample(i);
+                [e.size_over_life.sample(t).unwrap_or(1.0), e.speed_over_life.sample(t).unwrap_or(1.0), 0.0, 0.0]
+            }),
+        }
+    }
+
+    fn create_gpu(&self, device: &wgpu::Device, params: &SimParams) -> GpuState {
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Params Buffer"),
+            contents: bytemuck::cast_slice(&[*params]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let particles = vec![<GpuParticle as bytemuck::Zeroable>::zeroed(); self.capacity as usize];
+        let particle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Buffer"),
+            contents: bytemuck::cast_slice(&particles),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+        });
+        // 空闲链表: 计数在前, 一开始所有槽位都空闲
+        let free_list: Vec<u32> = std::iter::once(self.capacity).chain(0..self.capacity).collect();
+        let free_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Free List Buffer"),
+            contents: bytemuck::cast_slice(&free_list),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let path: Vec<[f32; 2]> = match &self.emitter.shape {
+            EmitterShape::Path(table) if !table.is_empty() => {
+                table.resample(PATH_SAMPLES, false).iter().map(|p| p.to_array()).collect()
+            }
+            _ => vec![[0.0, 0.0]; PATH_SAMPLES],
+        };
+        let path_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Path Buffer"),
+            contents: bytemuck::cast_slice(&path),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Particle Compute Bind Group"),
+            layout: &Self::bind_group_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: params_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: particle_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: free_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: path_buffer.as_entire_binding() },
+            ],
+        });
+        GpuState { params_buffer, particle_buffer, bind_group }
+    }
+
+    /// Records the simulation and spawning for the time passed since the last
+    /// dispatch. `simulate` and `emit` are the entry points of `particle_compute.wgsl`.
+    pub fn dispatch(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        compute_pass: &mut wgpu::ComputePass,
+        simulate: &wgpu::ComputePipeline,
+        emit: &wgpu::ComputePipeline,
+    ) {
+        let seed = self.rng.gen();
+        let params = self.params(seed);
+        match &self.gpu {
+            Some(gpu) => queue.write_buffer(&gpu.params_buffer, 0, bytemuck::cast_slice(&[params])),
+            None => self.gpu = Some(self.create_gpu(device, &params)),
+        }
+        let Some(gpu) = &self.gpu else {
+            return;
+        };
+        compute_pass.set_bind_group(0, &gpu.bind_group, &[]);
+        if params.dt > 0.0 {
+            compute_pass.set_pipeline(simulate);
+            compute_pass.dispatch_workgroups(self.capacity.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        if params.spawn > 0 {
+            compute_pass.set_pipeline(emit);
+            compute_pass.dispatch_workgroups(params.spawn.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        self.dt = 0.0;
+        self.spawn = 0;
+    }
+
+    /// Draws every slot; free ones collapse in the vertex shader. Expects the
+    /// GPU particle pipeline and the particle quad to be bound.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, quad_indices: u32) {
+        if let Some(gpu) = &self.gpu {
+            render_pass.set_vertex_buffer(1, gpu.particle_buffer.slice(..));
+            render_pass.draw_indexed(0..quad_indices, 0, 0..self.capacity);
+        }
+    }
+}
+
+// 动画只移动和旋转发射器
+impl Shape for GpuParticleSystem {
+    fn set_color(&mut self, color: Color) {
+        self.emitter.color = color;
+    }
+
+    fn set_shadow(&mut self, _shadow: Shadow) {}
+
+    fn set_position(&mut self, position: Vec2) {
+        self.emitter.position = position;
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.emitter.rotation = rotation;
+    }
+
+    fn draw(&self, _render_pass: &mut wgpu::RenderPass) {}
+}
@@ -1,6 +1,6 @@
 use std::mem;
-use rand::Rng;
 use wgpu::util::DeviceExt;
+use crate::shapes::{Color, Shadow, Shape};
 
 pub struct Instance {
     pub position: glam::Vec3,
@@ -8,7 +8,9 @@ pub struct Instance {
     pub transform: glam::Mat4,
     pub theta: f32,
     pub speed: f32,
-    pub scale: f32,
+    pub scale: glam::Vec2,
+    /// Multiplies the mesh's vertex colors; alpha scales its opacity.
+    pub color: glam::Vec4,
 }
 
 #[repr(C)]
@@ -16,6 +18,7 @@ pub struct Instance {
 pub struct InstanceRaw {
     pub model: [[f32;4];4],
     pub origin3d: [f32;3],
+    pub color: [f32;4],
 }
 
 
@@ -39,22 +42,6 @@ impl InstanceManager {
         }
     }
 
-    pub fn make_up_instances(&mut self) {
-        let mut rng = rand::thread_rng();
-        let points = vec![
-            glam::Vec3::new(0.0, 1.0, 0.0),
-            // glam::Vec3::new(-0.6, 0.6, 0.0),
-            // glam::Vec3::new(-0.6, -0.6, 0.0),
-            // glam::Vec3::new(0.6, -0.6, 0.0),
-            // glam::Vec3::new(0.6, 0.6, 0.0),
-        ];
-        for i in 0..points.len() {
-            let position = points[i];
-            let rotation = glam::Quat::from_rotation_z(rng.gen_range(0.0..std::f32::consts::PI));
-            self.add_instance(Instance::new(position, rotation));
-        }
-    }
-
     pub fn add_instance(&mut self, instance: Instance) {
         self.instances.push(instance);
     }
@@ -64,12 +51,39 @@ impl InstanceManager {
         let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Instance Buffer"),
             contents: bytemuck::cast_slice(&instances_data),
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
 
         });
         self.instance_buffer = Some(instance_buffer);
     }
 
+    /// Re-uploads every instance after animators have changed them.
+    pub fn update_buffer(&self, queue: &wgpu::Queue) {
+        if let Some(buffer) = &self.instance_buffer {
+            let instances_data = self.instances.iter().map(|instance| instance.to_raw()).collect::<Vec<InstanceRaw>>();
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&instances_data));
+        }
+    }
+
+    /// Uploads every instance like `update_buffer`, first growing the buffer if the
+    /// instances no longer fit, for sets whose size changes such as particles.
+    pub fn sync_buffer(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let instances_data = self.instances.iter().map(|instance| instance.to_raw()).collect::<Vec<InstanceRaw>>();
+        let size = (instances_data.len() * mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+        let fits = self.instance_buffer.as_ref().is_some_and(|buffer| buffer.size() >= size);
+        if !fits {
+            // 按 2 的幂扩容, 避免每帧重建
+            let capacity = instances_data.len().max(1).next_power_of_two();
+            self.instance_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Instance Buffer"),
+                size: (capacity * mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        queue.write_buffer(self.get_buffer(), 0, bytemuck::cast_slice(&instances_data));
+    }
+
     pub fn get_buffer(&self) -> &wgpu::Buffer {
         self.instance_buffer.as_ref().unwrap()
     }
@@ -84,19 +98,50 @@ impl Instance {
             transform,
             theta: 0.0,
             speed: 0.0,
-            scale: 0.0,
+            scale: glam::Vec2::ONE,
+            color: glam::Vec4::ONE,
         }
     }
 
     pub fn to_raw(&self) -> InstanceRaw {
-        let coll = (glam::Mat4::from_translation(self.position) * glam::Mat4::from_quat(self.rotation)).to_cols_array_2d();
+        let scale = glam::Vec3::new(self.scale.x, self.scale.y, 1.0);
+        let coll = glam::Mat4::from_scale_rotation_translation(scale, self.rotation, self.position).to_cols_array_2d();
         InstanceRaw {
             model: coll,
             origin3d: self.position.to_array(),
+            color: self.color.to_array(),
         }
     }
 }
 
+// 实例由 InstanceManager 统一绘制, 这里只接收动画写入的变换
+impl Shape for Instance {
+    fn set_color(&mut self, color: Color) {
+        self.color = color.to_vec3().extend(self.color.w);
+    }
+
+    fn set_shadow(&mut self, _shadow: Shadow) {}
+
+    fn set_position(&mut self, position: glam::Vec2) {
+        self.position = position.extend(self.position.z);
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.theta = rotation;
+        self.rotation = glam::Quat::from_rotation_z(rotation);
+    }
+
+    fn set_scale(&mut self, scale: glam::Vec2) {
+        self.scale = scale;
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.color.w = opacity;
+    }
+
+    fn draw(&self, _render_pass: &mut wgpu::RenderPass) {}
+}
+
 impl InstanceRaw{
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
@@ -127,6 +172,11 @@ impl InstanceRaw{
                     format: wgpu::VertexFormat::Float32x3,
                     offset: mem::size_of::<[f32;16]>() as wgpu::BufferAddress,
                     shader_location: 9,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: mem::size_of::<[f32;19]>() as wgpu::BufferAddress,
+                    shader_location: 10,
                 }
             ],
         }
@@ -3,6 +3,9 @@ pub mod structure;
 pub(crate) mod resources;
 pub mod shapes;
 pub mod action;
+pub mod gpu_particles;
+pub mod motion_blur;
+pub mod shadow;
 mod texture;
 mod projection;
 mod instance;
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+
+/// Sub-frames are averaged in this format so eight or more of them don't band.
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// How `Renderer::render_frame` smears motion across a frame.
+///
+/// `shutter` is measured in fixed steps: the shutter opens that many steps
+/// before the presented pose and closes on it, so with 60 steps a second 0.5 is
+/// a 1/120 s exposure, the film look of a 180° shutter. It is clamped to
+/// [0, 1], the poses the renderer keeps, so sub-frames are only ever blended
+/// between recorded steps and never extrapolated.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MotionBlur {
+    /// Sub-frames rendered and averaged per frame; 1 turns the blur off.
+    pub samples: u32,
+    pub shutter: f32,
+}
+
+impl Default for MotionBlur {
+    fn default() -> Self {
+        MotionBlur { samples: 8, shutter: 0.5 }
+    }
+}
+
+impl MotionBlur {
+    pub fn new(samples: u32, shutter: f32) -> Self {
+        MotionBlur { samples, shutter }
+    }
+
+    /// The interpolation alpha of each sub-frame for a frame posed at `alpha`,
+    /// oldest first. The last is always `alpha` itself; the others reach back
+    /// at most one step, to `alpha - 1`.
+    pub fn sample_times(&self, alpha: f32) -> Vec<f32> {
+        let samples = self.samples.max(1);
+        if samples == 1 {
+            return vec![alpha];
+        }
+        let shutter = self.shutter.clamp(0.0, 1.0);
+        (0..samples)
+            .map(|k| alpha - shutter * (samples - 1 - k) as f32 / (samples - 1) as f32)
+            .collect()
+    }
+}
+
+/// Offscreen targets for motion blur: each sub-frame is resolved into
+/// `sub_frame`, added into a float accumulation texture with its share of the
+/// weight, and the sum is copied to the surface at the end of the frame.
+pub struct Accumulator {
+    size: (u32, u32),
+    sub_frame: wgpu::TextureView,
+    accumulate_pipeline: wgpu::RenderPipeline,
+    present_pipeline: wgpu::RenderPipeline,
+    sub_frame_bg: wgpu::BindGroup,
+    accumulation_bg: wgpu::BindGroup,
+    accumulation: wgpu::TextureView,
+}
+
+impl Accumulator {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let texture = |label, format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: config.width,
+                        height: config.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let sub_frame = texture("Motion Blur Sub-frame", config.format);
+        let accumulation = texture("Motion Blur Accumulation", ACCUMULATION_FORMAT);
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Accumulate Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = |label, view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: &layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                }],
+            })
+        };
+        let sub_frame_bg = bind_group("Sub-frame Bind Group", &sub_frame);
+        let accumulation_bg = bind_group("Accumulation Bind Group", &accumulation);
+
+        let shader_str = std::fs::read_to_string("./src/res/accumulate_shader.wgsl")
+            .expect("failed to read shader file");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Accumulate Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Accumulate Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |label, format, blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        // 子帧乘以混合常量 (权重) 后累加
+        let weighted_sum = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let accumulate_pipeline = pipeline(
+            "Accumulate Pipeline",
+            ACCUMULATION_FORMAT,
+            Some(wgpu::BlendState { color: weighted_sum, alpha: weighted_sum }),
+        );
+        let present_pipeline = pipeline("Accumulation Present Pipeline", config.format, None);
+
+        Accumulator {
+            size: (config.width, config.height),
+            sub_frame,
+            accumulate_pipeline,
+            present_pipeline,
+            sub_frame_bg,
+            accumulation_bg,
+            accumulation,
+        }
+    }
+
+    /// Width and height of the targets; they must match the surface.
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Where each sub-frame is rendered to.
+    pub fn sub_frame(&self) -> &wgpu::TextureView {
+        &self.sub_frame
+    }
+
+    /// Adds the current sub-frame, scaled by `weight`. The `first` sub-frame of
+    /// a frame clears what the last frame left behind.
+    pub fn accumulate(&self, encoder: &mut wgpu::CommandEncoder, first: bool, weight: f32) {
+        let load = if first {
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)
+        } else {
+            wgpu::LoadOp::Load
+        };
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Accumulate Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.accumulation,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        let weight = weight as f64;
+        pass.set_pipeline(&self.accumulate_pipeline);
+        pass.set_blend_constant(wgpu::Color { r: weight, g: weight, b: weight, a: weight });
+        pass.set_bind_group(0, &self.sub_frame_bg, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Copies the accumulated frame to `view`.
+    pub fn present(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Accumulation Present Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.present_pipeline);
+        pass.set_bind_group(0, &self.accumulation_bg, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_frames_end_on_alpha() {
+        let times = MotionBlur::new(5, 0.5).sample_times(0.25);
+        assert_eq!(times, vec![-0.25, -0.125, 0.0, 0.125, 0.25]);
+        assert_eq!(MotionBlur::new(1, 0.5).sample_times(0.7), vec![0.7]);
+    }
+
+    #[test]
+    fn shutter_reaches_back_at_most_one_step() {
+        let times = MotionBlur::new(3, 4.0).sample_times(0.0);
+        assert_eq!(times, vec![-1.0, -0.5, 0.0]);
+        let times = MotionBlur::new(3, -1.0).sample_times(0.5);
+        assert_eq!(times, vec![0.5, 0.5, 0.5]);
+    }
+}
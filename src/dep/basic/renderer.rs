@@ -1,7 +1,21 @@
 use super::*;
-use std::f32::consts::PI;
 use std::iter;
 use crate::animation::physics::Physics;
+use crate::animation::Animator;
+use crate::animation::event::AnimationEvent;
+use crate::animation::particles::ParticleSystem;
+use crate::animation::property::PropertySnapshot;
+use crate::animation::state_machine::Parameter;
+use crate::import::lottie::Composition;
+use crate::import::scene::{InstanceMesh, Scene};
+use crate::import::svg::SvgDocument;
+use crate::import::ImportError;
+use crate::shapes::{Shape, ShapeLayouts};
+use crate::shapes::circle::IVertex;
+use crate::shapes::mesh::ShapeRaw;
+use crate::shapes::primitive::{Primitive, RegularPolygon};
+use crate::shapes::skin::{SkinVertex, SkinnedMesh};
+use crate::shapes::sprite::{Sprite, SpriteRaw, TextureAtlas};
 
 use winit::{
     event::*,
@@ -9,7 +23,10 @@ use winit::{
     window::Window,
 };
 
-use crate::dep::basic::instance::InstanceManager;
+use crate::dep::basic::gpu_particles::{GpuParticle, GpuParticleSystem};
+use crate::dep::basic::instance::{Instance, InstanceManager, InstanceRaw};
+use crate::dep::basic::motion_blur::{Accumulator, MotionBlur};
+use crate::dep::basic::shadow::{ShadowPipelines, ShadowRegion, ShadowTarget};
 use crate::dep::basic::projection::create_ortho_project_matrix;
 use wgpu::util::DeviceExt;
 use wgpu::{
@@ -33,6 +50,140 @@ pub struct GPUBuffers {
     pub mat_layout: wgpu::BindGroupLayout,
 }
 
+/// What an animation event returned from `Renderer::update` came from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimationTarget {
+    Instance(usize),
+    Shape(usize),
+    Sprite(usize),
+    Skin(usize),
+    Bone { skin: usize, bone: usize },
+    /// The emitter of a particle system.
+    Particles(usize),
+    GpuParticles(usize),
+    /// A composition's placement; also tags the `Looped` events its own
+    /// playback raises.
+    Composition(usize),
+}
+
+/// An animator and the poses it produced on the last three fixed updates, which
+/// `Renderer::interpolate` blends between. After a loop wraps, a seek or a state
+/// machine cutting to another state all poses are the new one, so the blend
+/// never sweeps back across the jump.
+struct AnimatedTarget {
+    target: AnimationTarget,
+    animator: Box<dyn Animator>,
+    /// Only read by motion blur sub-frames that open before `previous`.
+    before: PropertySnapshot,
+    previous: PropertySnapshot,
+    current: PropertySnapshot,
+}
+
+impl AnimatedTarget {
+    fn new(target: AnimationTarget, animator: Box<dyn Animator>) -> Self {
+        let current = PropertySnapshot::capture(&*animator);
+        AnimatedTarget { target, animator, before: current.clone(), previous: current.clone(), current }
+    }
+
+    fn update(&mut self, dt: f32) -> Vec<AnimationEvent> {
+        self.animator.update(dt);
+        let events = self.animator.drain_events();
+        let current = PropertySnapshot::capture(&*self.animator);
+        let jumped = events.iter().any(|event| match event {
+            AnimationEvent::Looped { .. } => true,
+            // 有淡入淡出的转换是连续的, 可以照常插值
+            AnimationEvent::StateChanged { fade, .. } => *fade <= 0.0,
+            _ => false,
+        });
+        if jumped {
+            self.reset(current);
+        } else {
+            self.before = std::mem::replace(&mut self.previous, std::mem::replace(&mut self.current, current));
+        }
+        events
+    }
+
+    fn seek(&mut self, time: f32) {
+        self.animator.seek(time);
+        self.animator.drain_events();
+        self.reset(PropertySnapshot::capture(&*self.animator));
+    }
+
+    fn reset(&mut self, current: PropertySnapshot) {
+        self.before = current.clone();
+        self.previous = current.clone();
+        self.current = current;
+    }
+
+    /// The pose `alpha` of the way from the previous update to the latest;
+    /// from -1 to 0 it goes from the update before that to the previous one.
+    fn pose(&self, alpha: f32) -> PropertySnapshot {
+        if alpha < 0.0 {
+            self.before.blend(&self.previous, (alpha + 1.0).max(0.0))
+        } else {
+            self.previous.blend(&self.current, alpha.min(1.0))
+        }
+    }
+}
+
+/// The instance mesh as a triangle fan around its centre.
+fn instance_fan(mesh: &InstanceMesh) -> (Vec<super::structure::Vertex>, Vec<u16>) {
+    // 三角扇: 圆心是绿色, 圆周按角度分成红, 绿, 蓝, 灰四段
+    let ring = RegularPolygon::new(mesh.segments.min(u16::MAX as u32 - 1), mesh.radius)
+        .path()
+        .iter()
+        .filter_map(|event| match event {
+            lyon::path::PathEvent::Begin { at } => Some(at),
+            lyon::path::PathEvent::Line { to, .. } => Some(to),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut vertices = vec![super::structure::Vertex::new(&[0.0, 0.0, 0.0], &[0.0, 1.0, 0.0])];
+    vertices.extend(ring.iter().map(|p| {
+        let turn = (p.y.atan2(p.x) / std::f32::consts::TAU).rem_euclid(1.0);
+        let color = match turn {
+            t if t < 0.3 => [1.0, 0.0, 0.0],
+            t if t < 0.6 => [0.0, 1.0, 0.0],
+            t if t < 0.9 => [0.0, 0.0, 1.0],
+            _ => [0.5, 0.5, 0.5],
+        };
+        super::structure::Vertex::new(&[p.x, p.y, 0.0], &color)
+    }));
+    let n = ring.len() as u16;
+    let indices = (0..n).flat_map(|i| [0, i + 1, (i + 1) % n + 1]).collect::<Vec<u16>>();
+    (vertices, indices)
+}
+
+/// A particle system and the instances its particles are drawn as.
+struct ParticleLayer {
+    system: ParticleSystem,
+    instances: InstanceManager,
+}
+
+impl ParticleLayer {
+    fn new(system: ParticleSystem) -> Self {
+        ParticleLayer { system, instances: InstanceManager::new() }
+    }
+
+    fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.instances.instances = self
+            .system
+            .particles()
+            .iter()
+            .map(|particle| {
+                let mut instance = Instance::new(
+                    particle.position.extend(0.0),
+                    glam::Quat::from_rotation_z(particle.rotation),
+                );
+                instance.scale = glam::Vec2::splat(particle.size);
+                instance.color = particle.color.to_vec3().extend(particle.opacity);
+                instance
+            })
+            .collect();
+        self.instances.sync_buffer(device, queue);
+    }
+}
+
 pub struct Renderer<'a> {
     #[allow(dead_code)]
     instance: wgpu::Instance,
@@ -44,21 +195,48 @@ pub struct Renderer<'a> {
     config: wgpu::SurfaceConfiguration,
     pub size: winit::dpi::PhysicalSize<u32>,
     clear_color: wgpu::Color,
+    /// Last cursor position in physical pixels.
+    cursor: glam::Vec2,
     window: &'a Window,
 
     instance_manager: InstanceManager,
 
+    shapes: Vec<Box<dyn Shape>>,
+    /// Offscreen shadow of each shape in `shapes` that has one.
+    shadow_targets: Vec<Option<ShadowTarget>>,
+    sprites: Vec<Sprite>,
+    skins: Vec<SkinnedMesh>,
+    particles: Vec<ParticleLayer>,
+    gpu_particles: Vec<GpuParticleSystem>,
+    animators: Vec<AnimatedTarget>,
+    compositions: Vec<Composition>,
+
     render_pipeline: wgpu::RenderPipeline,
+    shape_pipeline: wgpu::RenderPipeline,
+    sprite_pipeline: wgpu::RenderPipeline,
+    skin_pipeline: wgpu::RenderPipeline,
+    particle_pipeline: wgpu::RenderPipeline,
+    gpu_particle_pipeline: wgpu::RenderPipeline,
+    shadow_pipelines: ShadowPipelines,
+    shape_layouts: ShapeLayouts,
+    /// `simulate` and `emit` from `particle_compute.wgsl`.
+    particle_compute: (wgpu::ComputePipeline, wgpu::ComputePipeline),
+    /// Unit quad every particle is drawn with.
+    particle_quad: (wgpu::Buffer, wgpu::Buffer),
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     texture_view: wgpu::TextureView,
     depth_view: TextureView,
     index_size: usize,
     buffers: GPUBuffers,
-    theta: f32,
 
     full_quad: FullQuad,
     physics: Option<Physics>,
+    motion_blur: Option<MotionBlur>,
+    /// Length of the latest fixed update, for posing compositions in between.
+    step: f32,
+    /// Sub-frame targets for `motion_blur`, made on first use at the surface size.
+    accumulator: Option<Accumulator>,
 }
 
 impl<'a> Renderer<'a> {
@@ -69,7 +247,7 @@ impl<'a> Renderer<'a> {
                 ..
             } => match ke.physical_key {
                 PhysicalKey::Code(KeyCode::KeyR) => {
-                    self.update_rotate();
+                    self.set_parameter("rotate", Parameter::Trigger(true));
                     return true;
                 }
                 _ => {}
@@ -78,19 +256,12 @@ impl<'a> Renderer<'a> {
         }
         false
     }
-
-    fn update_rotate(&mut self) {
-        let f = PI / 10.0;
-        self.theta += f;
-        let fmat = create_rotation_matrix(self.theta);
-        self.queue
-            .write_buffer(&self.buffers.mat_buffer, 0, bytemuck::cast_slice(&[fmat]));
-        println!("you should update here");
-    }
 }
 
 impl<'a> Renderer<'a> {
-    pub async fn new(window: &'a Window) -> Renderer<'a> {
+    /// Opens a surface on `window` and shows `scene`, or the default scene if
+    /// a file `scene` refers to fails to load.
+    pub async fn new(window: &'a Window, scene: &Scene) -> Renderer<'a> {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -158,62 +329,304 @@ impl<'a> Renderer<'a> {
 
         let multisampled_view = Self::create_texture_view(&device, &config);
 
-        let clear_color = wgpu::Color::BLACK;
         let render_pipeline = Self::create_pipeline(&device, &config);
-        let circle = super::structure::Circle::new([0.0, 0.0], 0.7, 100);
-        //let circle = crate::shapes::circle::generate_circle(0.5);
-        //let vert = super::structure::generate_circle_vertices([0.0, 0.0], 0.5, 100);
-        //let ind = super::structure::generate_circle_indices(vert.len());
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            //contents: bytemuck::cast_slice(super::structure::VERTICES),
-            contents: bytemuck::cast_slice(circle.vertices()),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            //contents: bytemuck::cast_slice(super::structure::INDICES),
-            contents: bytemuck::cast_slice(circle.indices()),
-            usage: wgpu::BufferUsages::INDEX,
-        });
+        let shape_layouts = ShapeLayouts::new(&device);
+        let shape_pipeline = Self::create_shape_pipeline(&device, &config, &render_pipeline.1, &shape_layouts.paint);
+        let sprite_pipeline = Self::create_sprite_pipeline(&device, &config, &render_pipeline.1);
+        let skin_pipeline = Self::create_skin_pipeline(&device, &config, &render_pipeline.1);
+        let particle_pipeline = Self::create_particle_pipeline(&device, &config, &render_pipeline.1);
+        let gpu_particle_pipeline = Self::create_gpu_particle_pipeline(&device, &config, &render_pipeline.1);
+        let particle_compute = Self::create_particle_compute(&device);
+        let shadow_pipelines = ShadowPipelines::new(&device, &config, &render_pipeline.1, &shape_layouts.paint, SAMPLE_COUNT);
+        let particle_quad = Self::create_particle_quad(&device);
+        let (vertex_buffer, index_buffer, index_size) =
+            Self::create_instance_mesh(&device, &scene.instance_mesh);
 
         let mut instance_manager = InstanceManager::new();
-        instance_manager.make_up_instances();
         instance_manager.init_buffer(&device);
 
         let depth_view = Self::init_depth_stencil(&device, &config);
 
         let full_quad = FullQuad::new(&device, &config);
 
-        Self {
+        let mut renderer = Self {
             instance,
             adapter,
             surface,
             device,
             queue,
             config,
-            clear_color,
+            clear_color: scene.clear_color(),
+            cursor: glam::Vec2::ZERO,
             size,
             window,
             render_pipeline: render_pipeline.0,
+            shape_pipeline,
+            sprite_pipeline,
+            skin_pipeline,
+            particle_pipeline,
+            gpu_particle_pipeline,
+            shadow_pipelines,
+            shape_layouts,
+            particle_compute,
+            particle_quad,
             vertex_buffer,
             index_buffer,
-            index_size: circle.indices.len(),
+            index_size,
             texture_view: multisampled_view,
             buffers: render_pipeline.1,
-            theta: 0.0,
             depth_view,
             instance_manager,
+            shapes: vec![],
+            shadow_targets: vec![],
+            sprites: vec![],
+            skins: vec![],
+            particles: vec![],
+            gpu_particles: vec![],
+            animators: vec![],
+            compositions: vec![],
             full_quad,
             physics: None,
+            motion_blur: None,
+            step: 0.0,
+            accumulator: None,
+        };
+        if let Err(e) = renderer.load_scene(scene) {
+            log::error!("failed to load scene: {}", e);
+            renderer
+                .load_scene(&Scene::default())
+                .expect("the default scene loads no files");
         }
+        renderer
+    }
+
+    /// Replaces everything on screen with the contents of `scene`. Nothing changes
+    /// if a file the scene refers to fails to load.
+    pub fn load_scene(&mut self, scene: &Scene) -> Result<(), ImportError> {
+        let mut instance_manager = InstanceManager::new();
+        let mut animators = vec![];
+        for desc in &scene.instances {
+            for _ in 0..desc.count {
+                let index = instance_manager.instances.len();
+                let mut instance = Instance::new(
+                    glam::Vec3::from(desc.position),
+                    glam::Quat::from_rotation_z(desc.rotation),
+                );
+                instance.theta = desc.rotation;
+                instance.scale = glam::Vec2::from(desc.scale);
+                instance_manager.add_instance(instance);
+                if let Some(animator) = desc.animator(index)? {
+                    animators.push(AnimatedTarget::new(AnimationTarget::Instance(index), animator));
+                }
+            }
+        }
+
+        let atlases = scene.atlases()?;
+        let mut shapes: Vec<Box<dyn Shape>> = vec![];
+        for (shape_index, desc) in scene.shapes.iter().enumerate() {
+            // 填充和描边是两个网格, 各自挂一份动画
+            for mesh in desc.meshes(&scene.gradients)? {
+                let index = shapes.len();
+                shapes.push(Box::new(mesh));
+                if let Some(animator) = desc.animator(shape_index)? {
+                    animators.push(AnimatedTarget::new(AnimationTarget::Shape(index), animator));
+                }
+            }
+        }
+        let mut sprites = vec![];
+        for (index, desc) in scene.sprites.iter().enumerate() {
+            sprites.push(desc.sprite(&atlases)?);
+            if let Some(animator) = desc.animator(index)? {
+                animators.push(AnimatedTarget::new(AnimationTarget::Sprite(index), animator));
+            }
+        }
+        let mut skins = vec![];
+        for (skin_index, desc) in scene.skins.iter().enumerate() {
+            skins.push(desc.mesh(&scene.gradients)?);
+            if let Some(animator) = desc.animator(skin_index)? {
+                animators.push(AnimatedTarget::new(AnimationTarget::Skin(skin_index), animator));
+            }
+            for (bone, bone_desc) in desc.bones.iter().enumerate() {
+                if let Some(animator) = bone_desc.animator(bone)? {
+                    let target = AnimationTarget::Bone { skin: skin_index, bone };
+                    animators.push(AnimatedTarget::new(target, animator));
+                }
+            }
+        }
+        let mut particles = vec![];
+        let mut gpu_particles = vec![];
+        for (index, desc) in scene.particles.iter().enumerate() {
+            let target = if desc.gpu {
+                gpu_particles.push(GpuParticleSystem::new(desc.emitter()?, desc.seed));
+                AnimationTarget::GpuParticles(gpu_particles.len() - 1)
+            } else {
+                particles.push(ParticleLayer::new(desc.system()?));
+                AnimationTarget::Particles(particles.len() - 1)
+            };
+            if let Some(animator) = desc.animator(index)? {
+                animators.push(AnimatedTarget::new(target, animator));
+            }
+        }
+        for file in &scene.svgs {
+            let document = SvgDocument::load(scene.resolve(&file.path))?;
+            let placement = file.placement();
+            for mut mesh in document.meshes() {
+                mesh.set_parent_transform(placement * mesh.model_matrix());
+                shapes.push(Box::new(mesh));
+            }
+        }
+
+        let mut compositions = vec![];
+        for file in &scene.compositions {
+            let mut composition = Composition::load(scene.resolve(&file.path))?;
+            file.place(&mut composition);
+            compositions.push(composition);
+        }
+
+        instance_manager.init_buffer(&self.device);
+        let (vertex_buffer, index_buffer, index_size) =
+            Self::create_instance_mesh(&self.device, &scene.instance_mesh);
+        self.vertex_buffer = vertex_buffer;
+        self.index_buffer = index_buffer;
+        self.index_size = index_size;
+        self.instance_manager = instance_manager;
+        self.shapes = shapes;
+        self.shadow_targets.clear();
+        self.sprites = sprites;
+        self.skins = skins;
+        self.particles = particles;
+        self.gpu_particles = gpu_particles;
+        self.animators = animators;
+        self.compositions = compositions;
+        self.clear_color = scene.clear_color();
+        self.motion_blur = scene.motion_blur;
+        Ok(())
+    }
+
+    fn create_instance_mesh(
+        device: &wgpu::Device,
+        mesh: &InstanceMesh,
+    ) -> (wgpu::Buffer, wgpu::Buffer, usize) {
+        let (vertices, mut indices) = instance_fan(mesh);
+        let index_count = indices.len();
+        if index_count % 2 == 1 {
+            indices.push(0);
+        }
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        (vertex_buffer, index_buffer, index_count)
+    }
+
+    /// Turns motion blur in `render_frame` on, or off with `None`.
+    pub fn set_motion_blur(&mut self, motion_blur: Option<MotionBlur>) {
+        self.motion_blur = motion_blur;
     }
 
     pub fn set_physics(&mut self, physics: Physics) {
         self.physics = Some(physics);
     }
 
+    /// Drives the instance at `index` with `animator` on every update.
+    pub fn animate_instance(&mut self, index: usize, animator: Box<dyn Animator>) {
+        self.animators.push(AnimatedTarget::new(AnimationTarget::Instance(index), animator));
+    }
+
+    /// Adds a shape drawn on top of the instances and returns its index.
+    pub fn add_shape(&mut self, shape: Box<dyn Shape>) -> usize {
+        self.shapes.push(shape);
+        self.shapes.len() - 1
+    }
+
+    /// Drives the shape at `index` with `animator` on every update.
+    pub fn animate_shape(&mut self, index: usize, animator: Box<dyn Animator>) {
+        self.animators.push(AnimatedTarget::new(AnimationTarget::Shape(index), animator));
+    }
+
+    /// Adds a sprite drawn above the shapes and returns its index.
+    pub fn add_sprite(&mut self, sprite: Sprite) -> usize {
+        self.sprites.push(sprite);
+        self.sprites.len() - 1
+    }
+
+    /// Drives the sprite at `index` with `animator` on every update, e.g. a
+    /// `FlipbookAnimator` stepping through its atlas frames.
+    pub fn animate_sprite(&mut self, index: usize, animator: Box<dyn Animator>) {
+        self.animators.push(AnimatedTarget::new(AnimationTarget::Sprite(index), animator));
+    }
+
+    /// Adds a skinned mesh drawn above the shapes and sprites and returns its index.
+    pub fn add_skin(&mut self, skin: SkinnedMesh) -> usize {
+        self.skins.push(skin);
+        self.skins.len() - 1
+    }
+
+    /// Drives the skin at `index` as a whole with `animator` on every update.
+    pub fn animate_skin(&mut self, index: usize, animator: Box<dyn Animator>) {
+        self.animators.push(AnimatedTarget::new(AnimationTarget::Skin(index), animator));
+    }
+
+    /// Poses one bone of the skin at `skin` with `animator` on every update.
+    pub fn animate_bone(&mut self, skin: usize, bone: usize, animator: Box<dyn Animator>) {
+        self.animators.push(AnimatedTarget::new(AnimationTarget::Bone { skin, bone }, animator));
+    }
+
+    /// Adds a particle system drawn above the skins and returns its index.
+    pub fn add_particles(&mut self, system: ParticleSystem) -> usize {
+        self.particles.push(ParticleLayer::new(system));
+        self.particles.len() - 1
+    }
+
+    /// Moves the emitter of the particle system at `index` with `animator`.
+    pub fn animate_particles(&mut self, index: usize, animator: Box<dyn Animator>) {
+        self.animators.push(AnimatedTarget::new(AnimationTarget::Particles(index), animator));
+    }
+
+    /// For emitting bursts or changing the emitter while the system runs.
+    pub fn particles_mut(&mut self, index: usize) -> Option<&mut ParticleSystem> {
+        self.particles.get_mut(index).map(|layer| &mut layer.system)
+    }
+
+    /// Adds a particle system simulated on the GPU, drawn above the CPU
+    /// particles, and returns its index.
+    pub fn add_gpu_particles(&mut self, system: GpuParticleSystem) -> usize {
+        self.gpu_particles.push(system);
+        self.gpu_particles.len() - 1
+    }
+
+    /// Moves the emitter of the GPU particle system at `index` with `animator`.
+    pub fn animate_gpu_particles(&mut self, index: usize, animator: Box<dyn Animator>) {
+        self.animators.push(AnimatedTarget::new(AnimationTarget::GpuParticles(index), animator));
+    }
+
+    pub fn gpu_particles_mut(&mut self, index: usize) -> Option<&mut GpuParticleSystem> {
+        self.gpu_particles.get_mut(index)
+    }
+
+    /// Adds a Lottie composition that plays in a loop, drawn above the shapes.
+    pub fn add_composition(&mut self, composition: Composition) -> usize {
+        self.compositions.push(composition);
+        self.compositions.len() - 1
+    }
+
+    pub fn composition_mut(&mut self, index: usize) -> Option<&mut Composition> {
+        self.compositions.get_mut(index)
+    }
+
+    /// Sends an input parameter to every animator, e.g. to drive state machines.
+    pub fn set_parameter(&mut self, name: &str, value: Parameter) {
+        for target in &mut self.animators {
+            target.animator.set_parameter(name, value);
+        }
+    }
+
     pub fn create_texture_view(device: &Device, config: &SurfaceConfiguration) -> TextureView {
         let multisampled_texture_extent = wgpu::Extent3d {
             width: config.width,
@@ -331,6 +744,389 @@ impl<'a> Renderer<'a> {
         (render_pipeline, buffers)
     }
 
+    /// Pipeline for `MeshShape`s: one draw per shape, transform and tint passed as a
+    /// single instance, paint at group 1. Shapes are drawn in order, so depth testing is off.
+    pub fn create_shape_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        buffers: &GPUBuffers,
+        paint_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        let shader_str = std::fs::read_to_string("./src/res/mesh_shader.wgsl")
+            .expect("failed to read mesh shader file");
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mesh Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shape Pipeline Layout"),
+            bind_group_layouts: &[&buffers.mvp_layout, paint_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shape Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[IVertex::desc(), ShapeRaw::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // 镜像变换会翻转三角形方向, 不做剔除
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Pipeline for `Sprite`s: a textured quad per sprite with its atlas at group 1.
+    pub fn create_sprite_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        buffers: &GPUBuffers,
+    ) -> wgpu::RenderPipeline {
+        let shader_str = std::fs::read_to_string("./src/res/sprite_shader.wgsl")
+            .expect("failed to read sprite shader file");
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sprite Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        let atlas_layout = TextureAtlas::bind_group_layout(device);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sprite Pipeline Layout"),
+            bind_group_layouts: &[&buffers.mvp_layout, &atlas_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sprite Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[super::structure::Vertex::desc(), SpriteRaw::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // 镜像变换会翻转三角形方向, 不做剔除
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Pipeline for particles: the unit quad instanced once per particle with `InstanceRaw`.
+    pub fn create_particle_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        buffers: &GPUBuffers,
+    ) -> wgpu::RenderPipeline {
+        let shader_str = std::fs::read_to_string("./src/res/particle_shader.wgsl")
+            .expect("failed to read particle shader file");
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Pipeline Layout"),
+            bind_group_layouts: &[&buffers.mvp_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particle Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[super::structure::Vertex::desc(), InstanceRaw::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // 镜像变换会翻转三角形方向, 不做剔除
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Pipeline for `GpuParticleSystem`s: the unit quad instanced once per particle slot,
+    /// reading the particle storage buffer as instance data.
+    pub fn create_gpu_particle_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        buffers: &GPUBuffers,
+    ) -> wgpu::RenderPipeline {
+        let shader_str = std::fs::read_to_string("./src/res/gpu_particle_shader.wgsl")
+            .expect("failed to read gpu particle shader file");
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("GPU Particle Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("GPU Particle Pipeline Layout"),
+            bind_group_layouts: &[&buffers.mvp_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("GPU Particle Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[super::structure::Vertex::desc(), GpuParticle::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // 镜像变换会翻转三角形方向, 不做剔除
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// The `simulate` and `emit` compute pipelines shared by every `GpuParticleSystem`.
+    pub fn create_particle_compute(device: &wgpu::Device) -> (wgpu::ComputePipeline, wgpu::ComputePipeline) {
+        let shader_str = std::fs::read_to_string("./src/res/particle_compute.wgsl")
+            .expect("failed to read particle compute shader file");
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle Compute Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        let bind_group_layout = GpuParticleSystem::bind_group_layout(device);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Compute Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+        (pipeline("simulate"), pipeline("emit"))
+    }
+
+    /// Quad from (-1, -1) to (1, 1), so a particle's scale is its radius.
+    fn create_particle_quad(device: &wgpu::Device) -> (wgpu::Buffer, wgpu::Buffer) {
+        let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+        let vertices = corners.map(|[x, y]| super::structure::Vertex::new(&[x, y, 0.0], &[1.0, 1.0, 1.0]));
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        (vertex_buffer, index_buffer)
+    }
+
+    /// Pipeline for `SkinnedMesh`es: the shape pipeline plus bone matrices at group 1.
+    pub fn create_skin_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        buffers: &GPUBuffers,
+    ) -> wgpu::RenderPipeline {
+        let shader_str = std::fs::read_to_string("./src/res/skin_shader.wgsl")
+            .expect("failed to read skin shader file");
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Skin Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        let bone_layout = SkinnedMesh::bone_layout(device);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skin Pipeline Layout"),
+            bind_group_layouts: &[&buffers.mvp_layout, &bone_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skin Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[SkinVertex::desc(), ShapeRaw::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // 镜像变换会翻转三角形方向, 不做剔除
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
     pub fn init_depth_stencil(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
@@ -484,27 +1280,107 @@ impl<'a> Renderer<'a> {
             let view = Self::create_texture_view(&self.device, &self.config);
             self.texture_view = view;
             self.depth_view = Self::init_depth_stencil(&self.device, &self.config);
+            self.accumulator = None;
+            self.shadow_targets.clear();
         }
     }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::CursorMoved { position, .. } => {
-                self.clear_color = wgpu::Color {
-                    r: position.x as f64 / self.size.width as f64,
-                    g: position.y as f64 / self.size.height as f64,
-                    b: 1.0,
-                    a: 1.0,
-                };
+                self.cursor = glam::Vec2::new(position.x as f32, position.y as f32);
+                // 转换到正交投影的视图坐标: y 范围 [-1, 1], x 按宽高比缩放
+                let half = glam::Vec2::new(self.size.width as f32, self.size.height as f32) / 2.0;
+                let mouse = (self.cursor - half) / half.y.max(1.0);
+                self.set_parameter("mouse.x", Parameter::Number(mouse.x));
+                self.set_parameter("mouse.y", Parameter::Number(-mouse.y));
+                true
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                self.set_parameter("pressed", Parameter::Bool(*state == ElementState::Pressed));
                 true
             }
             _ => false,
         }
     }
 
-    pub fn update(&mut self, dt: f32) {
+    /// Advances physics, animators and compositions by one fixed step of `dt`
+    /// seconds and returns the animation events raised during the step, tagged
+    /// with what they animate. Animated poses reach the screen through `interpolate`.
+    pub fn update(&mut self, dt: f32) -> Vec<(AnimationTarget, AnimationEvent)> {
         if let Some(physics) = &mut self.physics {
-            physics.step();
+            physics.step(dt);
+        }
+        self.step = dt;
+
+        let mut events = vec![];
+        for target in &mut self.animators {
+            let tag = target.target;
+            events.extend(target.update(dt).into_iter().map(|event| (tag, event)));
+        }
+        for layer in &mut self.particles {
+            layer.system.update(dt);
+        }
+        for system in &mut self.gpu_particles {
+            system.update(dt);
+        }
+        for (i, composition) in self.compositions.iter_mut().enumerate() {
+            composition.update(dt);
+            events.extend(composition.drain_events().into_iter().map(|event| (AnimationTarget::Composition(i), event)));
+        }
+        events
+    }
+
+    /// Jumps every animator and composition to `time` seconds from its start.
+    /// Events the jump would raise are dropped.
+    pub fn seek(&mut self, time: f32) {
+        for target in &mut self.animators {
+            target.seek(time);
+        }
+        for composition in &mut self.compositions {
+            composition.seek(time);
+        }
+    }
+
+    /// Poses every animated target `alpha` of the way from the previous fixed
+    /// update to the latest one, ready for `render`.
+    ///
+    /// `alpha` may go down to -1, a step before the previous update; motion blur
+    /// uses that. Compositions are posed at the matching time. Physics and
+    /// particle systems are not interpolated and keep the state of the latest
+    /// fixed update.
+    pub fn interpolate(&mut self, alpha: f32) {
+        let mut instances_moved = false;
+        for animated in &self.animators {
+            let shape: Option<&mut dyn Shape> = match animated.target {
+                AnimationTarget::Instance(index) => {
+                    instances_moved = true;
+                    self.instance_manager.instances.get_mut(index).map(|i| i as &mut dyn Shape)
+                }
+                AnimationTarget::Shape(index) => self.shapes.get_mut(index).map(|s| s.as_mut()),
+                AnimationTarget::Sprite(index) => self.sprites.get_mut(index).map(|s| s as &mut dyn Shape),
+                AnimationTarget::Skin(index) => self.skins.get_mut(index).map(|s| s as &mut dyn Shape),
+                AnimationTarget::Bone { skin, bone } => self
+                    .skins
+                    .get_mut(skin)
+                    .and_then(|s| s.skeleton_mut().bone_mut(bone))
+                    .map(|b| b as &mut dyn Shape),
+                AnimationTarget::Particles(index) => self.particles.get_mut(index).map(|p| &mut p.system as &mut dyn Shape),
+                AnimationTarget::GpuParticles(index) => self.gpu_particles.get_mut(index).map(|p| p as &mut dyn Shape),
+                AnimationTarget::Composition(index) => self.compositions.get_mut(index).map(|c| c as &mut dyn Shape),
+            };
+            if let Some(shape) = shape {
+                animated.pose(alpha).apply(shape);
+            }
+        }
+        if instances_moved {
+            self.instance_manager.update_buffer(&self.queue);
+        }
+        for composition in &mut self.compositions {
+            composition.pose_at(composition.time() + (alpha - 1.0) * self.step);
+        }
+        for layer in &mut self.particles {
+            layer.upload(&self.device, &self.queue);
         }
     }
 
@@ -522,6 +1398,64 @@ impl<'a> Renderer<'a> {
         Ok(())
     }
 
+    /// Uploads whatever changed since the last frame for shapes and their
+    /// shadows, sprites, skins and compositions.
+    fn prepare(&mut self) {
+        for shape in &mut self.shapes {
+            shape.prepare(&self.device, &self.queue, &self.shape_layouts);
+        }
+        let screen = (self.config.width, self.config.height);
+        let view = create_ortho_project_matrix(screen);
+        // 不知道范围的图形按整个屏幕算
+        let inverse = view.inverse();
+        let whole_screen = (
+            inverse.project_point3(glam::Vec3::new(-1.0, -1.0, 0.0)).truncate(),
+            inverse.project_point3(glam::Vec3::new(1.0, 1.0, 0.0)).truncate(),
+        );
+        self.shadow_targets.resize_with(self.shapes.len(), || None);
+        for (shape, target) in self.shapes.iter().zip(&mut self.shadow_targets) {
+            let Some(shadow) = shape.shadow() else {
+                *target = None;
+                continue;
+            };
+            let bounds = shape.bounds().unwrap_or(whole_screen);
+            let region = ShadowRegion::new(&shadow, bounds, view, screen);
+            if let Some(region) = region {
+                if target.as_ref().is_none_or(|t| !t.fits(region.size)) {
+                    *target = Some(ShadowTarget::new(&self.device, &self.shadow_pipelines, &self.buffers, region.size));
+                }
+            }
+            if let Some(target) = target {
+                target.update(&self.queue, &shadow, region, view, screen);
+            }
+        }
+        for sprite in &mut self.sprites {
+            sprite.prepare(&self.device, &self.queue, &self.shape_layouts);
+        }
+        for skin in &mut self.skins {
+            skin.prepare(&self.device, &self.queue, &self.shape_layouts);
+        }
+        for composition in &mut self.compositions {
+            composition.prepare(&self.device, &self.queue, &self.shape_layouts);
+        }
+    }
+
+    /// Runs this frame's GPU particle simulation; once per frame, however many
+    /// times the scene is drawn.
+    fn dispatch_particles(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.gpu_particles.is_empty() {
+            return;
+        }
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Particle Compute Pass"),
+            timestamp_writes: None,
+        });
+        let (simulate, emit) = &self.particle_compute;
+        for system in &mut self.gpu_particles {
+            system.dispatch(&self.device, &self.queue, &mut compute_pass, simulate, emit);
+        }
+    }
+
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
         let view = output
@@ -534,15 +1468,80 @@ impl<'a> Renderer<'a> {
                 label: Some("Render Encoder"),
             });
 
+        self.prepare();
+        self.dispatch_particles(&mut encoder);
+        self.draw_scene(&mut encoder, &view);
+
+        self.queue.submit(iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+
+    /// Poses the scene at `alpha` and draws it. With motion blur on, draws one
+    /// sub-frame per shutter sample instead and presents their average; the
+    /// scene is left posed at `alpha` either way. Particles look the same in
+    /// every sub-frame, so they are not blurred.
+    pub fn render_frame(&mut self, alpha: f32) -> Result<(), wgpu::SurfaceError> {
+        let blur = match self.motion_blur {
+            Some(blur) if blur.samples > 1 => blur,
+            _ => {
+                self.interpolate(alpha);
+                return self.render();
+            }
+        };
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let size = (self.config.width, self.config.height);
+        if self.accumulator.as_ref().is_none_or(|a| a.size() != size) {
+            self.accumulator = Some(Accumulator::new(&self.device, &self.config));
+        }
+        let times = blur.sample_times(alpha);
+        let weight = 1.0 / times.len() as f32;
+        for (k, &time) in times.iter().enumerate() {
+            self.interpolate(time);
+            self.prepare();
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Motion Blur Encoder"),
+                });
+            if k == 0 {
+                self.dispatch_particles(&mut encoder);
+            }
+            let accumulator = self.accumulator.as_ref().expect("created above");
+            self.draw_scene(&mut encoder, accumulator.sub_frame());
+            accumulator.accumulate(&mut encoder, k == 0, weight);
+            if k + 1 == times.len() {
+                accumulator.present(&mut encoder, &view);
+            }
+            // 每个子帧单独提交, 这样 write_buffer 写入的姿态按顺序生效
+            self.queue.submit(iter::once(encoder.finish()));
+        }
+        output.present();
+
+        Ok(())
+    }
+
+    /// Records the shadow passes and the scene pass, resolved into `target`.
+    fn draw_scene(&self, encoder: &mut wgpu::CommandEncoder, target: &TextureView) {
+        for (shape, shadow) in self.shapes.iter().zip(&self.shadow_targets) {
+            if let Some(shadow) = shadow {
+                shadow.render(encoder, &self.shadow_pipelines, shape.as_ref());
+            }
+        }
+
         {
             let mut _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &self.texture_view,
-                    resolve_target: Some(&view),
+                    resolve_target: Some(target),
                     ops: wgpu::Operations {
-                        //load: wgpu::LoadOp::Clear(wgpu::Color{r: 0.0, g:.0, b:.0,a:0.0}),
-                        load: wgpu::LoadOp::Load,
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: wgpu::StoreOp::Discard,
                     },
                 })],
@@ -561,23 +1560,199 @@ impl<'a> Renderer<'a> {
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
-            _render_pass.set_pipeline(&self.render_pipeline);
-            _render_pass.set_bind_group(0, &self.buffers.feather_bg, &[]);
-            _render_pass.set_bind_group(1, &self.buffers.mat_bg, &[]);
-            _render_pass.set_bind_group(2, &self.buffers.mvp_bg, &[]);
-            _render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            _render_pass.set_vertex_buffer(1, self.instance_manager.get_buffer().slice(..));
-            _render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            _render_pass.draw_indexed(
-                0..self.index_size as u32,
-                0,
-                0..self.instance_manager.instances.len() as u32,
-            );
+            // 空缓冲区不能绑定, 场景里没有实例时跳过
+            if !self.instance_manager.instances.is_empty() {
+                _render_pass.set_pipeline(&self.render_pipeline);
+                _render_pass.set_bind_group(0, &self.buffers.feather_bg, &[]);
+                _render_pass.set_bind_group(1, &self.buffers.mat_bg, &[]);
+                _render_pass.set_bind_group(2, &self.buffers.mvp_bg, &[]);
+                _render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                _render_pass.set_vertex_buffer(1, self.instance_manager.get_buffer().slice(..));
+                _render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                _render_pass.draw_indexed(
+                    0..self.index_size as u32,
+                    0,
+                    0..self.instance_manager.instances.len() as u32,
+                );
+            }
+
+            _render_pass.set_pipeline(&self.shape_pipeline);
+            _render_pass.set_bind_group(0, &self.buffers.mvp_bg, &[]);
+            for (i, shape) in self.shapes.iter().enumerate() {
+                // 阴影先画在图形下面, 然后换回图形管线
+                if let Some(Some(shadow)) = self.shadow_targets.get(i) {
+                    shadow.composite(&mut _render_pass, &self.shadow_pipelines, &self.buffers);
+                    _render_pass.set_pipeline(&self.shape_pipeline);
+                }
+                shape.draw(&mut _render_pass);
+            }
+            if !self.sprites.is_empty() {
+                _render_pass.set_pipeline(&self.sprite_pipeline);
+                _render_pass.set_bind_group(0, &self.buffers.mvp_bg, &[]);
+                for sprite in &self.sprites {
+                    sprite.draw(&mut _render_pass);
+                }
+            }
+            if !self.skins.is_empty() {
+                _render_pass.set_pipeline(&self.skin_pipeline);
+                _render_pass.set_bind_group(0, &self.buffers.mvp_bg, &[]);
+                for skin in &self.skins {
+                    skin.draw(&mut _render_pass);
+                }
+            }
+            let has_particles = self.particles.iter().any(|layer| !layer.instances.instances.is_empty());
+            let has_gpu_particles = !self.gpu_particles.is_empty();
+            if has_particles {
+                _render_pass.set_pipeline(&self.particle_pipeline);
+                _render_pass.set_bind_group(0, &self.buffers.mvp_bg, &[]);
+                _render_pass.set_vertex_buffer(0, self.particle_quad.0.slice(..));
+                _render_pass.set_index_buffer(self.particle_quad.1.slice(..), wgpu::IndexFormat::Uint16);
+                for layer in &self.particles {
+                    let count = layer.instances.instances.len() as u32;
+                    if count > 0 {
+                        _render_pass.set_vertex_buffer(1, layer.instances.get_buffer().slice(..));
+                        _render_pass.draw_indexed(0..6, 0, 0..count);
+                    }
+                }
+            }
+            if has_gpu_particles {
+                _render_pass.set_pipeline(&self.gpu_particle_pipeline);
+                _render_pass.set_bind_group(0, &self.buffers.mvp_bg, &[]);
+                _render_pass.set_vertex_buffer(0, self.particle_quad.0.slice(..));
+                _render_pass.set_index_buffer(self.particle_quad.1.slice(..), wgpu::IndexFormat::Uint16);
+                for system in &self.gpu_particles {
+                    system.draw(&mut _render_pass, 6);
+                }
+            }
+            if !self.sprites.is_empty() || !self.skins.is_empty() || has_particles || has_gpu_particles {
+                // Lottie 合成仍然使用图形管线
+                _render_pass.set_pipeline(&self.shape_pipeline);
+            }
+            for composition in &self.compositions {
+                composition.draw(&mut _render_pass);
+            }
         }
+    }
+}
 
-        self.queue.submit(iter::once(encoder.finish()));
-        output.present();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::property::{Property, PropertyValue};
+    use crate::animation::state_machine::{StateMachine, Transition};
+    use crate::animation::timeline::{Repeat, Timeline};
 
-        Ok(())
+    /// Rotation equal to its own time, over one second.
+    struct Ramp(f32);
+
+    impl Animator for Ramp {
+        fn update(&mut self, delta_time: f32) {
+            self.0 += delta_time;
+        }
+
+        fn apply(&self, shape: &mut dyn Shape) {
+            shape.set_rotation(self.0);
+        }
+
+        fn duration(&self) -> f32 {
+            1.0
+        }
+
+        fn seek(&mut self, time: f32) {
+            self.0 = time;
+        }
+    }
+
+    fn looping_ramp() -> AnimatedTarget {
+        let timeline = Timeline::sequence(vec![Box::new(Ramp(0.0))]).with_repeat(Repeat::Infinite);
+        AnimatedTarget::new(AnimationTarget::Shape(0), Box::new(timeline))
+    }
+
+    fn rotation(target: &AnimatedTarget, alpha: f32) -> f32 {
+        match target.pose(alpha).get(Property::Rotation) {
+            Some(PropertyValue::Rotation(r)) => r,
+            other => panic!("expected a rotation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn instance_mesh_is_a_fan_around_a_green_centre() {
+        let (vertices, indices) = instance_fan(&InstanceMesh { radius: 0.5, segments: 8 });
+        assert_eq!(vertices.len(), 9);
+        assert_eq!(vertices[0].position, [0.0, 0.0, 0.0]);
+        assert_eq!(vertices[0].color, [0.0, 1.0, 0.0]);
+        assert_eq!(indices.len(), 24);
+        assert_eq!(&indices[21..], &[0, 8, 1]);
+        for v in &vertices[1..] {
+            let r = (v.position[0].powi(2) + v.position[1].powi(2)).sqrt();
+            assert!((r - 0.5).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn blends_between_fixed_updates() {
+        let mut target = looping_ramp();
+        target.update(0.5);
+        target.update(0.25);
+        assert!((rotation(&target, 0.5) - 0.625).abs() < 1e-6);
+    }
+
+    #[test]
+    fn does_not_blend_across_a_loop() {
+        let mut target = looping_ramp();
+        target.update(0.75);
+        let events = target.update(0.5);
+        assert!(events.contains(&AnimationEvent::Looped { iteration: 1 }));
+        assert!((rotation(&target, 0.0) - 0.25).abs() < 1e-6);
+        assert!((rotation(&target, 0.5) - 0.25).abs() < 1e-6);
+        target.update(0.25);
+        assert!((rotation(&target, 0.5) - 0.375).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reaches_back_one_more_update() {
+        let mut target = looping_ramp();
+        target.update(0.25);
+        target.update(0.25);
+        target.update(0.25);
+        assert!((rotation(&target, -0.5) - 0.375).abs() < 1e-6);
+        assert!((rotation(&target, -3.0) - 0.25).abs() < 1e-6);
+        assert!((rotation(&target, 2.0) - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn does_not_blend_across_a_state_cut() {
+        let machine = |fade: f32| {
+            let machine = StateMachine::new()
+                .with_state("idle", Box::new(Ramp(0.0)))
+                .with_state("run", Box::new(Ramp(0.0)))
+                .with_transition(Transition::new("idle", "run").when("run", true).with_fade(fade));
+            AnimatedTarget::new(AnimationTarget::Shape(0), Box::new(machine))
+        };
+
+        let mut cut = machine(0.0);
+        cut.update(0.75);
+        cut.animator.set_parameter("run", Parameter::Bool(true));
+        cut.update(0.25);
+        assert!((rotation(&cut, 0.0) - 0.25).abs() < 1e-6);
+        assert!((rotation(&cut, 0.5) - 0.25).abs() < 1e-6);
+
+        // 淡入淡出本身是连续的, 照常在两次更新之间插值
+        let mut faded = machine(1.0);
+        faded.update(0.75);
+        faded.animator.set_parameter("run", Parameter::Bool(true));
+        faded.update(0.25);
+        assert!((rotation(&faded, 0.0) - 0.75).abs() < 1e-6);
+        // idle 走到 1.0, run 在 0.25, 淡入四分之一
+        assert!((rotation(&faded, 1.0) - 0.8125).abs() < 1e-6);
+    }
+
+    #[test]
+    fn does_not_blend_across_a_seek() {
+        let mut target = looping_ramp();
+        target.update(0.75);
+        target.seek(0.25);
+        assert!((rotation(&target, 0.0) - 0.25).abs() < 1e-6);
+        assert!((rotation(&target, 1.0) - 0.25).abs() < 1e-6);
     }
 }
@@ -0,0 +1,516 @@
+use glam::{Mat4, Vec2};
+use wgpu::util::DeviceExt;
+use crate::shapes::circle::IVertex;
+use crate::shapes::mesh::ShapeRaw;
+use crate::shapes::{Shadow, Shape};
+use super::renderer::GPUBuffers;
+
+/// Coverage is all a silhouette needs.
+const SILHOUETTE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// Targets grow in steps of this many pixels so a shape that changes size
+/// every frame doesn't reallocate every frame.
+const TARGET_STEP: u32 = 128;
+
+/// Laid out like `ShadowParams` in `shadow_shader.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowParams {
+    color: [f32; 4],
+    rect: [f32; 4],
+    screen: [f32; 2],
+    sigma: f32,
+    _pad: f32,
+}
+
+/// Clears the whole of `view` but draws only into its top-left `size`.
+fn clear_pass<'e>(
+    encoder: &'e mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+    resolve_target: Option<&wgpu::TextureView>,
+    size: (u32, u32),
+) -> wgpu::RenderPass<'e> {
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: if resolve_target.is_some() { wgpu::StoreOp::Discard } else { wgpu::StoreOp::Store },
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+    pass.set_viewport(0.0, 0.0, size.0 as f32, size.1 as f32, 0.0, 1.0);
+    pass
+}
+
+/// The part of the screen one shadow covers: the shape's bounds grown by the
+/// blur's reach, cut to what can still land on screen after the offset.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowRegion {
+    /// Top-left corner in pixels, before the offset.
+    pub origin: Vec2,
+    pub size: (u32, u32),
+    /// Pixels, y down.
+    pub offset: Vec2,
+    /// Gaussian sigma in pixels.
+    pub sigma: f32,
+}
+
+impl ShadowRegion {
+    /// `bounds` are in view space; `view` maps them to clip space. Returns
+    /// `None` when none of the shadow would be visible.
+    pub fn new(shadow: &Shadow, bounds: (Vec2, Vec2), view: Mat4, screen: (u32, u32)) -> Option<Self> {
+        let screen = Vec2::new(screen.0 as f32, screen.1 as f32);
+        let to_pixels = |p: Vec2| {
+            let ndc = view.project_point3(p.extend(0.0));
+            Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * screen
+        };
+        let (lo, hi) = bounds;
+        let corners = [lo, Vec2::new(hi.x, lo.y), hi, Vec2::new(lo.x, hi.y)].map(to_pixels);
+        let min = corners.iter().fold(Vec2::INFINITY, |a, &c| a.min(c));
+        let max = corners.iter().fold(Vec2::NEG_INFINITY, |a, &c| a.max(c));
+        // 模糊半径以视图单位计, 视图高度为 2; 和 CSS 一样标准差取半径的一半.
+        // 再大也没有意义, 限制在屏幕长边的 1/6 以内
+        let sigma = (shadow.blur_radius.max(0.0) * 0.25 * screen.y).min(screen.max_element() / 6.0);
+        let pad = (sigma * 3.0).ceil() + 1.0;
+        let offset = to_pixels(shadow.offset) - to_pixels(Vec2::ZERO);
+        // 只保留偏移之后还可能落在屏幕上的部分
+        let min = (min - pad).max(-offset - pad).floor();
+        let max = (max + pad).min(screen - offset + pad).ceil();
+        if !(min.x < max.x && min.y < max.y) {
+            return None;
+        }
+        let size = max - min;
+        Some(ShadowRegion { origin: min, size: (size.x as u32, size.y as u32), offset, sigma })
+    }
+
+    /// `view` followed by the move and scale that make the region fill clip
+    /// space.
+    pub fn projection(&self, view: Mat4, screen: (u32, u32)) -> Mat4 {
+        let screen = Vec2::new(screen.0 as f32, screen.1 as f32);
+        let size = Vec2::new(self.size.0 as f32, self.size.1 as f32);
+        let shift = Vec2::new(
+            (screen.x - 2.0 * self.origin.x) / size.x - 1.0,
+            1.0 - (screen.y - 2.0 * self.origin.y) / size.y,
+        );
+        Mat4::from_translation(shift.extend(0.0)) * Mat4::from_scale((screen / size).extend(1.0)) * view
+    }
+}
+
+/// Pipelines shared by every shadow: the silhouette, the two blur directions
+/// and the composite into the scene pass.
+pub struct ShadowPipelines {
+    silhouette: wgpu::RenderPipeline,
+    blur_x: wgpu::RenderPipeline,
+    blur_y: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    sample_count: u32,
+}
+
+impl ShadowPipelines {
+    /// `sample_count` and the depth-stencil state match the scene pass the
+    /// composite pipeline draws into. Silhouettes are drawn with the mesh
+    /// shader, so they need its `paint_layout`.
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        buffers: &GPUBuffers,
+        paint_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
+    ) -> Self {
+        let mesh_shader_str = std::fs::read_to_string("./src/res/mesh_shader.wgsl")
+            .expect("failed to read mesh shader file");
+        let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Silhouette Shader"),
+            source: wgpu::ShaderSource::Wgsl(mesh_shader_str.into()),
+        });
+        let shader_str = std::fs::read_to_string("./src/res/shadow_shader.wgsl")
+            .expect("failed to read shadow shader file");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shadow Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_str.into()),
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let silhouette_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Silhouette Pipeline Layout"),
+            bind_group_layouts: &[&buffers.mvp_layout, paint_layout],
+            push_constant_ranges: &[],
+        });
+        let shadow_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&buffers.mvp_layout, &layout],
+            push_constant_ranges: &[],
+        });
+
+        let silhouette = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Silhouette Pipeline"),
+            layout: Some(&silhouette_layout),
+            vertex: wgpu::VertexState {
+                module: &mesh_shader,
+                entry_point: "vs_main",
+                buffers: &[IVertex::desc(), ShapeRaw::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &mesh_shader,
+                entry_point: "fs_silhouette",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: SILHOUETTE_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState { count: sample_count, ..Default::default() },
+            multiview: None,
+            cache: None,
+        });
+
+        let fullscreen = |label, vertex_entry, entry_point, format, blend, depth_stencil, count| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&shadow_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vertex_entry,
+                    buffers: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil,
+                multisample: wgpu::MultisampleState { count, ..Default::default() },
+                multiview: None,
+                cache: None,
+            })
+        };
+        let blur_x = fullscreen("Shadow Blur X Pipeline", "vs_fullscreen", "fs_blur_x", SILHOUETTE_FORMAT, None, None, 1);
+        let blur_y = fullscreen("Shadow Blur Y Pipeline", "vs_fullscreen", "fs_blur_y", SILHOUETTE_FORMAT, None, None, 1);
+        let composite = fullscreen(
+            "Shadow Composite Pipeline",
+            "vs_composite",
+            "fs_composite",
+            config.format,
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+            Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            sample_count,
+        );
+
+        ShadowPipelines { silhouette, blur_x, blur_y, composite, layout, sampler, sample_count }
+    }
+}
+
+/// One shape's shadow: its silhouette, blurred across and then down through
+/// `scratch`, ends up back in `silhouette` ready to composite. The textures
+/// only cover the shadow's `ShadowRegion`, drawn into their top-left corner.
+pub struct ShadowTarget {
+    capacity: (u32, u32),
+    /// Where the silhouette is drawn before resolving into `silhouette`;
+    /// `None` without multisampling.
+    multisampled: Option<wgpu::TextureView>,
+    silhouette: wgpu::TextureView,
+    scratch: wgpu::TextureView,
+    params: wgpu::Buffer,
+    /// The scene's view matrix moved onto the region.
+    view: wgpu::Buffer,
+    view_bg: wgpu::BindGroup,
+    /// Reads the silhouette; used by the horizontal blur and the composite.
+    silhouette_bg: wgpu::BindGroup,
+    scratch_bg: wgpu::BindGroup,
+    region: Option<ShadowRegion>,
+}
+
+impl ShadowTarget {
+    /// Makes a target that fits regions up to `size`, rounded up.
+    pub fn new(device: &wgpu::Device, pipelines: &ShadowPipelines, buffers: &GPUBuffers, size: (u32, u32)) -> Self {
+        let round_up = |n: u32| n.max(1).div_ceil(TARGET_STEP) * TARGET_STEP;
+        let capacity = (round_up(size.0), round_up(size.1));
+        let texture = |label, sample_count| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: capacity.0,
+                        height: capacity.1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: SILHOUETTE_FORMAT,
+                    usage: if sample_count > 1 {
+                        wgpu::TextureUsages::RENDER_ATTACHMENT
+                    } else {
+                        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+                    },
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let multisampled = (pipelines.sample_count > 1)
+            .then(|| texture("Shadow Multisampled Silhouette", pipelines.sample_count));
+        let silhouette = texture("Shadow Silhouette", 1);
+        let scratch = texture("Shadow Scratch", 1);
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shadow Params Buffer"),
+            contents: bytemuck::cast_slice(&[<ShadowParams as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let view = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shadow View Buffer"),
+            contents: bytemuck::cast_slice(&Mat4::IDENTITY.to_cols_array_2d()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let view_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow View Bind Group"),
+            layout: &buffers.mvp_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: view.as_entire_binding(),
+            }],
+        });
+        let bind_group = |label, texture_view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: &pipelines.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(texture_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&pipelines.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: params.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let silhouette_bg = bind_group("Shadow Silhouette Bind Group", &silhouette);
+        let scratch_bg = bind_group("Shadow Scratch Bind Group", &scratch);
+        ShadowTarget {
+            capacity,
+            multisampled,
+            silhouette,
+            scratch,
+            params,
+            view,
+            view_bg,
+            silhouette_bg,
+            scratch_bg,
+            region: None,
+        }
+    }
+
+    /// Whether a region of `size` fits without wasting more than half of
+    /// the target.
+    pub fn fits(&self, size: (u32, u32)) -> bool {
+        let fits = |needed: u32, capacity: u32| needed <= capacity && capacity <= needed * 2 + TARGET_STEP;
+        fits(size.0, self.capacity.0) && fits(size.1, self.capacity.1)
+    }
+
+    /// Uploads `shadow` over `region`, or hides the shadow when there is no
+    /// region. `view` is the scene's view matrix.
+    pub fn update(&mut self, queue: &wgpu::Queue, shadow: &Shadow, region: Option<ShadowRegion>, view: Mat4, screen: (u32, u32)) {
+        self.region = region;
+        let Some(region) = region else {
+            return;
+        };
+        let color = shadow.color.to_array();
+        let corner = region.origin + region.offset;
+        let params = ShadowParams {
+            color: [color[0], color[1], color[2], shadow.opacity.clamp(0.0, 1.0)],
+            rect: [corner.x, corner.y, region.size.0 as f32, region.size.1 as f32],
+            screen: [screen.0 as f32, screen.1 as f32],
+            sigma: region.sigma,
+            _pad: 0.0,
+        };
+        queue.write_buffer(&self.params, 0, bytemuck::cast_slice(&[params]));
+        let projection = region.projection(view, screen);
+        queue.write_buffer(&self.view, 0, bytemuck::cast_slice(&projection.to_cols_array_2d()));
+    }
+
+    /// Draws `shape`'s silhouette and blurs it. Call before the scene pass.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, pipelines: &ShadowPipelines, shape: &dyn Shape) {
+        let Some(region) = self.region else {
+            return;
+        };
+        {
+            let mut silhouette = match &self.multisampled {
+                Some(multisampled) => clear_pass(encoder, "Shadow Silhouette Pass", multisampled, Some(&self.silhouette), region.size),
+                None => clear_pass(encoder, "Shadow Silhouette Pass", &self.silhouette, None, region.size),
+            };
+            silhouette.set_pipeline(&pipelines.silhouette);
+            silhouette.set_bind_group(0, &self.view_bg, &[]);
+            shape.draw(&mut silhouette);
+        }
+        {
+            let mut blur = clear_pass(encoder, "Shadow Blur X Pass", &self.scratch, None, region.size);
+            blur.set_pipeline(&pipelines.blur_x);
+            blur.set_bind_group(0, &self.view_bg, &[]);
+            blur.set_bind_group(1, &self.silhouette_bg, &[]);
+            blur.draw(0..3, 0..1);
+        }
+        let mut blur = clear_pass(encoder, "Shadow Blur Y Pass", &self.silhouette, None, region.size);
+        blur.set_pipeline(&pipelines.blur_y);
+        blur.set_bind_group(0, &self.view_bg, &[]);
+        blur.set_bind_group(1, &self.scratch_bg, &[]);
+        blur.draw(0..3, 0..1);
+    }
+
+    /// Draws the blurred shadow into the scene pass as a quad over its
+    /// region. Leaves the composite pipeline bound.
+    pub fn composite(&self, render_pass: &mut wgpu::RenderPass, pipelines: &ShadowPipelines, buffers: &GPUBuffers) {
+        if self.region.is_none() {
+            return;
+        }
+        render_pass.set_pipeline(&pipelines.composite);
+        render_pass.set_bind_group(0, &buffers.mvp_bg, &[]);
+        render_pass.set_bind_group(1, &self.silhouette_bg, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dep::basic::projection::create_ortho_project_matrix;
+
+    const SCREEN: (u32, u32) = (800, 600);
+
+    /// The region is rounded out to whole pixels, so allow one either way.
+    fn assert_near(actual: (f32, f32), expected: (f32, f32)) {
+        assert!((actual.0 - expected.0).abs() <= 1.0 && (actual.1 - expected.1).abs() <= 1.0, "{:?} != {:?}", actual, expected);
+    }
+
+    fn size(region: &ShadowRegion) -> (f32, f32) {
+        (region.size.0 as f32, region.size.1 as f32)
+    }
+
+    fn shadow(offset: Vec2, blur_radius: f32) -> Shadow {
+        Shadow { offset, blur_radius, ..Shadow::default() }
+    }
+
+    fn view() -> Mat4 {
+        create_ortho_project_matrix(SCREEN)
+    }
+
+    #[test]
+    fn region_pads_the_bounds_by_the_blur() {
+        // 视图高度为 2, 屏幕高 600: 0.1 个单位是 30 像素
+        let bounds = (Vec2::new(-0.1, -0.1), Vec2::new(0.1, 0.1));
+        let region = ShadowRegion::new(&shadow(Vec2::ZERO, 0.04), bounds, view(), SCREEN).unwrap();
+        assert!((region.sigma - 6.0).abs() < 1e-4);
+        // 3 倍标准差再多一个像素
+        let pad = 19.0;
+        assert_near(region.origin.into(), (400.0 - 30.0 - pad, 300.0 - 30.0 - pad));
+        assert_near(size(&region), (60.0 + 2.0 * pad, 60.0 + 2.0 * pad));
+        assert_eq!(region.offset, Vec2::ZERO);
+    }
+
+    #[test]
+    fn region_offset_is_in_pixels_with_y_down() {
+        let bounds = (Vec2::new(-0.1, -0.1), Vec2::new(0.1, 0.1));
+        let region = ShadowRegion::new(&shadow(Vec2::new(0.1, -0.1), 0.0), bounds, view(), SCREEN).unwrap();
+        assert!((region.offset - Vec2::new(30.0, 30.0)).length() < 1e-3);
+        assert_near(size(&region), (62.0, 62.0));
+    }
+
+    #[test]
+    fn region_is_cut_to_the_screen() {
+        let aspect = 800.0 / 600.0;
+        let bounds = (Vec2::new(-aspect - 1.0, -0.5), Vec2::new(0.0, 0.5));
+        let region = ShadowRegion::new(&shadow(Vec2::ZERO, 0.0), bounds, view(), SCREEN).unwrap();
+        assert_near((region.origin.x, region.size.0 as f32), (-1.0, 402.0));
+        // 完全在屏幕外的阴影不需要画
+        let outside = (Vec2::new(3.0, 0.0), Vec2::new(4.0, 1.0));
+        assert_eq!(ShadowRegion::new(&shadow(Vec2::ZERO, 0.01), outside, view(), SCREEN), None);
+        // 偏移能把屏幕外的图形的阴影移进来
+        let shifted = shadow(Vec2::new(-2.5, 0.0), 0.0);
+        assert!(ShadowRegion::new(&shifted, outside, view(), SCREEN).is_some());
+    }
+
+    #[test]
+    fn projection_maps_the_region_to_clip_space() {
+        let bounds = (Vec2::new(-0.3, 0.1), Vec2::new(0.2, 0.4));
+        let region = ShadowRegion::new(&shadow(Vec2::ZERO, 0.02), bounds, view(), SCREEN).unwrap();
+        let projection = region.projection(view(), SCREEN);
+        let screen = Vec2::new(SCREEN.0 as f32, SCREEN.1 as f32);
+        let size = Vec2::new(region.size.0 as f32, region.size.1 as f32);
+        for corner in [bounds.0, bounds.1] {
+            let pixel = {
+                let ndc = view().project_point3(corner.extend(0.0));
+                Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * screen
+            };
+            let ndc = projection.project_point3(corner.extend(0.0));
+            let in_region = Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * size;
+            assert!((in_region - (pixel - region.origin)).length() < 1e-3);
+        }
+    }
+}
@@ -62,7 +62,7 @@ impl Vertex {
                     format: wgpu::VertexFormat::Float32x3,
                 },
                 wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x2,
                 },
@@ -71,134 +71,6 @@ impl Vertex {
     }
 }
 
-
-
-pub struct Circle {
-    pub vertices: Vec<Vertex>,
-    pub indices: Vec<u16>,
-}
-
-impl Circle {
-    pub fn new(center: [f32; 2], radius: f32, segments: u32) -> Self {
-        let vertices = Self::generate_circle_vertices(center, radius, segments);
-        let indices = Self::generate_circle_indices(vertices.len());
-
-        Circle { vertices, indices }
-    }
-
-    pub fn vertices(&self) -> &[Vertex] {
-        &self.vertices
-    }
-
-    pub fn indices(&self) -> &[u16] {
-        &self.indices
-    }
-
-    fn generate_circle_vertices(center: [f32; 2], radius: f32, segments: u32) -> Vec<Vertex> {
-        let mut vertices = Vec::with_capacity(segments as usize);
-        let aspect_ratio = 1.0;
-
-        let step = std::f32::consts::PI * 2.0 / segments as f32;
-        vertices.push(Vertex {
-            position: [center[0], center[1], 0.0],
-            color: [0.0, 1.0, 0.0],
-            uv: [0.0, 0.0],
-        });
-
-        for i in 0..segments + 1 {
-            let angle = step * i as f32;
-            let x = center[0] + radius * angle.cos();
-            let y = center[1] + radius * angle.sin();
-            match i {
-                0 .. 30 => {
-                    vertices.push(Vertex {
-                        position: [x, y, 0.0],
-                        color: [1.0, 0.0, 0.0],
-                        uv: [0.0, 0.0],
-                    });
-                }
-                30 .. 60 => {
-                    vertices.push(Vertex {
-                        position: [x, y, 0.0],
-                        color: [0.0, 1.0, 0.0],
-                        uv: [0.0, 0.0],
-                    });
-                }
-                60 .. 90 => {
-                    vertices.push(Vertex {
-                        position: [x, y, 0.0],
-                        color: [0.0, 0.0, 1.0],
-                        uv: [0.0, 0.0],
-                    });
-                }
-                _ => {
-                    vertices.push(Vertex {
-                        position: [x, y, 0.0],
-                        color: [0.5, 0.5, 0.5],
-                        uv: [0.0, 0.0],
-                    });
-                }
-            }
-        }
-
-        vertices
-    }
-
-    fn generate_circle_indices(num_vertices: usize) -> Vec<u16> {
-        let mut indices = Vec::with_capacity(num_vertices * 3);
-
-        for i in 0..num_vertices {
-            indices.push(0);
-            indices.push(i as u16 + 1);
-            indices.push((i + 1) as u16 % num_vertices as u16 + 1);
-        }
-
-        indices
-    }
-}
-
-// pub fn generate_circle_vertices(center: [f32; 2], radius: f32, segments: u32) -> Vec<Vertex> {
-//     let mut vertices = Vec::with_capacity(segments as usize);
-//     let aspect_ratio = WIDTH / HEIGHT;
-//
-//     // vertices.push(Vertex {
-//     //     position: [center[0] / aspect_ratio, center[1], 0.0],
-//     //     color: [1.0, 0.0, 0.0], // 默认白色，可以根据需要修改
-//     // });
-//
-//     let step = std::f32::consts::PI * 2.0 / segments as f32;
-//     vertices.push(Vertex {
-//         position: [center[0] / aspect_ratio, center[1], 0.0],
-//         color: [0.0, 0.0, 0.5], // 默认白色,您可以根据需要修改
-//     });
-//
-//     for i in 0..segments + 1 {
-//         let angle = step * i as f32;
-//         let x = (center[0] + radius * angle.cos()) / aspect_ratio;
-//         let y = center[1] + radius * angle.sin();
-//
-//
-//         vertices.push(Vertex {
-//             position: [x, y, 0.0],
-//             color: [1.0, 0.0, 0.5], // 默认白色,您可以根据需要修改
-//         });
-//     }
-//
-//     vertices
-// }
-//
-// pub fn generate_circle_indices(num_vertices: usize) -> Vec<u16> {
-//     let mut indices = Vec::with_capacity(num_vertices * 3);
-//
-//     for i in 0..num_vertices {
-//         indices.push(0); // 圆心顶点
-//         indices.push(i as u16 + 1); // 当前圆周顶点
-//         indices.push((i + 1) as u16 % num_vertices as u16 + 1); // 下一个圆周顶点
-//     }
-//
-//     indices
-// }
-
 pub const INDICES: &[u16] = &[0, 1, 4,
     1, 2, 4,
     2, 3, 4, /* padding */ 0];
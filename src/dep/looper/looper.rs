@@ -4,9 +4,11 @@ use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::event::*;
 use winit::window::{Window, WindowBuilder};
-use crate::dep::basic;
-use crate::dep::basic::renderer::Renderer;
+use crate::dep::basic::renderer::{AnimationTarget, Renderer};
 use crate::animation::physics::Physics;
+use crate::animation::clock::FixedClock;
+use crate::animation::event::AnimationEvent;
+use crate::import::scene::Scene;
 pub struct Looper<'a> {
     window: &'a Window,
     state: Renderer<'a>,
@@ -16,9 +18,9 @@ pub struct Looper<'a> {
 
 
 impl<'a> Looper<'a> {
-    pub async fn new(window: &'a Window) -> Self {
+    pub async fn new(window: &'a Window, scene: &Scene) -> Self {
         let physics = Physics::new();
-        let mut state = Renderer::new(&window).await;
+        let mut state = Renderer::new(&window, scene).await;
         state.set_physics(physics);
 
         Self {
@@ -29,14 +31,16 @@ impl<'a> Looper<'a> {
     }
 
 
-    pub fn update(&mut self, dt: f32) {
+    /// Steps the scene and reports what the animations did, per animated target.
+    pub fn update(&mut self, dt: f32) -> Vec<(AnimationTarget, AnimationEvent)> {
         self.state.update(dt)
     }
 
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+    /// Draws the scene `alpha` of the way from the previous update to the latest.
+    pub fn render(&mut self, alpha: f32) -> Result<(), wgpu::SurfaceError>{
         //self.state.render_quad()
-        self.state.render()
+        self.state.render_frame(alpha)
     }
 
     pub fn handler(&mut self, event: &Event<()>) -> bool {
@@ -97,15 +101,25 @@ impl<'a> Looper<'a> {
 }
 
 pub async fn run() {
-    let n : u32 = 60;
-    let frame_duration = Duration::from_secs_f64(1.0 / n as f64);
+    // 动画和物理以固定 60Hz 步进, 渲染按显示器刷新率插值
+    let mut clock = FixedClock::from_rate(60.0);
     let event_loop = EventLoop::new().unwrap();
 
+    // 命令行第一个参数可以指定场景文件, 也可以直接给一个 Lottie 或 SVG 文件
+    let scene = match std::env::args().nth(1) {
+        Some(path) => Scene::open(&path).unwrap_or_else(|e| {
+            log::error!("failed to load {}: {}", path, e);
+            Scene::default()
+        }),
+        None => Scene::default(),
+    };
+
     let window = WindowBuilder::new().
-        with_inner_size(winit::dpi::LogicalSize::new(basic::structure::WIDTH, basic::structure::HEIGHT)).
+        with_title(&scene.window.title).
+        with_inner_size(winit::dpi::LogicalSize::new(scene.window.width, scene.window.height)).
         build(&event_loop).unwrap();
 
-    let mut looper = Looper::new(&window).await;
+    let mut looper = Looper::new(&window, &scene).await;
 
     let mut last_update = Instant::now();
     let mut last_fps_update = Instant::now();
@@ -155,30 +169,28 @@ pub async fn run() {
                             last_fps_update = now;
                             frame_count = 0;
                         }
-                        if now.duration_since(last_update) >= frame_duration {
-                            let dt = last_update.elapsed().as_secs_f32();
-                            looper.update(dt);
-                            // 渲染逻辑放在这里
-                            looper.window.request_redraw();
-                            last_update = now;
-                            frame_count += 1;
-
-                            match looper.render() {
-                                Ok(_) => {}
-                                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                                    looper.state.resize(looper.state.size);
-                                }
-                                Err(wgpu::SurfaceError::OutOfMemory) => {
-                                    log::error!("OutOfMemory");
-                                    control_flow.exit();
-                                }
-                                Err(wgpu::SurfaceError::Timeout) => {
-                                    log::warn!("Surface timeout")
-                                }
+                        let frame_time = now.duration_since(last_update).as_secs_f32();
+                        last_update = now;
+                        frame_count += 1;
+                        for _ in 0..clock.advance(frame_time) {
+                            for (target, event) in looper.update(clock.step()) {
+                                log::debug!("{:?} animation event: {:?}", target, event);
                             }
                         }
 
-
+                        match looper.render(clock.alpha()) {
+                            Ok(_) => {}
+                            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                                looper.state.resize(looper.state.size);
+                            }
+                            Err(wgpu::SurfaceError::OutOfMemory) => {
+                                log::error!("OutOfMemory");
+                                control_flow.exit();
+                            }
+                            Err(wgpu::SurfaceError::Timeout) => {
+                                log::warn!("Surface timeout")
+                            }
+                        }
                     }
                     _ => {}
                 }
@@ -0,0 +1,947 @@
+use glam::{Mat4, Vec2, Vec3};
+use lyon::math::{point, vector, Angle, Box2D, Transform};
+use lyon::path::builder::BorderRadii;
+use lyon::path::{FillRule, Path, Winding};
+use serde_json::Value;
+use crate::animation::keyframe::{Animatable, Interpolation, Keyframe, KeyframeAnimator, PropertyTrack, Track};
+use crate::animation::easing::Easing;
+use crate::animation::event::AnimationEvent;
+use crate::animation::Animator;
+use crate::shapes::mesh::{MeshShape, ShapeTransform};
+use crate::shapes::stroke::{LineCap, LineJoin, StrokeStyle};
+use crate::shapes::{Color, Shadow, Shape, ShapeLayouts};
+use super::{fit_to_view, ImportError};
+
+// 嵌套过深的父子关系视为循环引用
+const MAX_PARENT_DEPTH: usize = 32;
+
+/// Every number in a Lottie value, which may be a scalar or an array.
+fn numbers(value: &Value) -> Vec<f32> {
+    match value {
+        Value::Number(n) => vec![n.as_f64().unwrap_or(0.0) as f32],
+        Value::Array(items) => items.iter().filter_map(Value::as_f64).map(|n| n as f32).collect(),
+        _ => vec![],
+    }
+}
+
+fn first_number(value: Option<&Value>) -> Option<f32> {
+    value.map(numbers).and_then(|n| n.first().copied())
+}
+
+fn item_type(item: &Value) -> &str {
+    item.get("ty").and_then(Value::as_str).unwrap_or("")
+}
+
+fn is_hidden(item: &Value) -> bool {
+    item.get("hd").and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn scalar(n: &[f32]) -> f32 {
+    n[0]
+}
+
+fn percent(n: &[f32]) -> f32 {
+    n[0] / 100.0
+}
+
+fn vec2(n: &[f32]) -> Vec2 {
+    Vec2::new(n[0], n.get(1).copied().unwrap_or(n[0]))
+}
+
+fn scale(n: &[f32]) -> Vec2 {
+    vec2(n) / 100.0
+}
+
+fn color(n: &[f32]) -> Color {
+    let channel = |i: usize| n.get(i).copied().unwrap_or(0.0);
+    let rgb = Vec3::new(channel(0), channel(1), channel(2));
+    // 早期版本导出的颜色是 0-255
+    if rgb.max_element() > 1.0 {
+        Color::from_vec3(rgb / 255.0)
+    } else {
+        Color::from_vec3(rgb)
+    }
+}
+
+fn is_animated(prop: &Value) -> bool {
+    match prop.get("a").and_then(Value::as_i64) {
+        Some(a) => a == 1,
+        // 旧版导出没有 "a" 字段, 看 k 是不是关键帧数组
+        None => prop
+            .get("k")
+            .and_then(Value::as_array)
+            .and_then(|k| k.first())
+            .is_some_and(|k| k.get("t").is_some()),
+    }
+}
+
+/// Easing of the segment that starts at `key`, from its `o` (out) and `i` (in)
+/// handles. Multi-dimensional handles use their first component.
+fn key_interpolation(key: &Value) -> Interpolation {
+    if key.get("h").and_then(Value::as_i64) == Some(1) {
+        return Interpolation::Hold;
+    }
+    let handle = |name: &str, axis: &str| key.get(name).and_then(|h| first_number(h.get(axis)));
+    match (handle("o", "x"), handle("o", "y"), handle("i", "x"), handle("i", "y")) {
+        (Some(x1), Some(y1), Some(x2), Some(y2)) => Interpolation::Eased(Easing::cubic_bezier(x1, y1, x2, y2)),
+        _ => Interpolation::Linear,
+    }
+}
+
+/// Reads an animatable property (`{"a": 0|1, "k": ...}`) into a track, converting
+/// keyframe times from frames to seconds. Static properties become a single keyframe.
+fn parse_track<T: Animatable>(prop: Option<&Value>, frame_rate: f32, default: T, convert: impl Fn(&[f32]) -> T) -> Track<T> {
+    let mut track = Track::new();
+    let Some(k) = prop.and_then(|prop| prop.get("k")) else {
+        return track.with_keyframe(Keyframe::hold(0.0, default));
+    };
+
+    if prop.is_some_and(is_animated) {
+        let mut previous_end: Option<Vec<f32>> = None;
+        for key in k.as_array().into_iter().flatten() {
+            let Some(frame) = key.get("t").and_then(Value::as_f64) else {
+                continue;
+            };
+            // 旧格式里最后一帧只有 t, 值取上一帧的 e
+            let start = key.get("s").map(numbers).filter(|n| !n.is_empty()).or(previous_end.take());
+            previous_end = key.get("e").map(numbers).filter(|n| !n.is_empty());
+            if let Some(start) = start {
+                track.add_keyframe(Keyframe::new(frame as f32 / frame_rate, convert(&start), key_interpolation(key)));
+            }
+        }
+    } else {
+        let value = numbers(k);
+        if !value.is_empty() {
+            track.add_keyframe(Keyframe::hold(0.0, convert(&value)));
+        }
+    }
+
+    if track.keyframes().is_empty() {
+        track.add_keyframe(Keyframe::hold(0.0, default));
+    }
+    track
+}
+
+fn is_static<T: Animatable>(track: &Track<T>) -> bool {
+    track.keyframes().len() <= 1
+}
+
+fn initial<T: Animatable>(track: &Track<T>) -> T {
+    track.keyframes()[0].value
+}
+
+#[derive(Clone, Debug)]
+enum PositionTrack {
+    Joined(Track<Vec2>),
+    /// `"s": true` positions animate x and y with separate keyframes.
+    Split(Track<f32>, Track<f32>),
+}
+
+/// A layer transform (`ks`) or group transform (`tr`).
+#[derive(Clone, Debug)]
+struct LottieTransform {
+    anchor: Track<Vec2>,
+    position: PositionTrack,
+    scale: Track<Vec2>,
+    /// Radians. Lottie's y axis points down, so positive angles turn clockwise on screen.
+    rotation: Track<f32>,
+    opacity: Track<f32>,
+}
+
+impl LottieTransform {
+    fn parse(transform: Option<&Value>, frame_rate: f32) -> Self {
+        let get = |key: &str| transform.and_then(|t| t.get(key));
+        let position = match get("p") {
+            Some(p) if p.get("s").and_then(Value::as_bool) == Some(true) => PositionTrack::Split(
+                parse_track(p.get("x"), frame_rate, 0.0, scalar),
+                parse_track(p.get("y"), frame_rate, 0.0, scalar),
+            ),
+            p => PositionTrack::Joined(parse_track(p, frame_rate, Vec2::ZERO, vec2)),
+        };
+        LottieTransform {
+            anchor: parse_track(get("a"), frame_rate, Vec2::ZERO, vec2),
+            position,
+            scale: parse_track(get("s"), frame_rate, Vec2::ONE, scale),
+            rotation: parse_track(get("r").or(get("rz")), frame_rate, 0.0, |n| n[0].to_radians()),
+            opacity: parse_track(get("o"), frame_rate, 1.0, percent),
+        }
+    }
+
+    fn matrix(&self, time: f32) -> Mat4 {
+        let position = match &self.position {
+            PositionTrack::Joined(track) => track.sample(time).unwrap_or(Vec2::ZERO),
+            PositionTrack::Split(x, y) => Vec2::new(x.sample(time).unwrap_or(0.0), y.sample(time).unwrap_or(0.0)),
+        };
+        ShapeTransform {
+            position,
+            rotation: self.rotation.sample(time).unwrap_or(0.0),
+            scale: self.scale.sample(time).unwrap_or(Vec2::ONE),
+            anchor: self.anchor.sample(time).unwrap_or(Vec2::ZERO),
+        }
+        .matrix()
+    }
+
+    fn opacity(&self, time: f32) -> f32 {
+        self.opacity.sample(time).unwrap_or(1.0)
+    }
+
+    /// Whether the matrix is the same at every frame; opacity doesn't count.
+    fn is_static(&self) -> bool {
+        let position = match &self.position {
+            PositionTrack::Joined(track) => is_static(track),
+            PositionTrack::Split(x, y) => is_static(x) && is_static(y),
+        };
+        position && is_static(&self.anchor) && is_static(&self.scale) && is_static(&self.rotation)
+    }
+}
+
+fn to_lyon(matrix: Mat4) -> Transform {
+    let (x, y, w) = (matrix.x_axis, matrix.y_axis, matrix.w_axis);
+    Transform::new(x.x, x.y, y.x, y.y, w.x, w.y)
+}
+
+fn bezier_path(shape: &Value) -> Option<Path> {
+    let points = |key: &str| -> Vec<Vec2> {
+        shape
+            .get(key)
+            .and_then(Value::as_array)
+            .map(|items| items.iter().map(numbers).filter(|n| !n.is_empty()).map(|n| vec2(&n)).collect())
+            .unwrap_or_default()
+    };
+    let (vertices, in_tangents, out_tangents) = (points("v"), points("i"), points("o"));
+    if vertices.is_empty() {
+        return None;
+    }
+    let closed = shape.get("c").and_then(Value::as_bool).unwrap_or(false);
+    let tangent = |tangents: &[Vec2], j: usize| tangents.get(j).copied().unwrap_or(Vec2::ZERO);
+
+    let mut builder = Path::builder();
+    let at = |v: Vec2| point(v.x, v.y);
+    builder.begin(at(vertices[0]));
+    let count = vertices.len();
+    let edges = if closed { count } else { count - 1 };
+    for j in 0..edges {
+        let (from, to) = (j, (j + 1) % count);
+        builder.cubic_bezier_to(
+            at(vertices[from] + tangent(&out_tangents, from)),
+            at(vertices[to] + tangent(&in_tangents, to)),
+            at(vertices[to]),
+        );
+    }
+    builder.end(closed);
+    Some(builder.build())
+}
+
+fn unsupported<T>(what: &str) -> Result<T, ImportError> {
+    Err(ImportError::Format(format!("lottie: {} are not supported", what)))
+}
+
+/// Outline of a path item (`sh`, `rc`, `el`). Paths are tessellated once, so
+/// animated ones are an error rather than frozen at their first frame.
+fn item_path(item: &Value, frame_rate: f32) -> Result<Option<Path>, ImportError> {
+    let animated = |key: &str| item.get(key).is_some_and(is_animated);
+    let static_vec2 = |key: &str, default: Vec2| initial(&parse_track(item.get(key), frame_rate, default, vec2));
+    let path = match item_type(item) {
+        "sh" if animated("ks") => return unsupported("animated paths"),
+        "sh" => item.get("ks").and_then(|ks| ks.get("k")).and_then(bezier_path),
+        "rc" | "el" if animated("p") || animated("s") || animated("r") => {
+            return unsupported("animated rectangles and ellipses");
+        }
+        "rc" => {
+            let center = static_vec2("p", Vec2::ZERO);
+            let half = static_vec2("s", Vec2::ZERO) / 2.0;
+            let radius = initial(&parse_track(item.get("r"), frame_rate, 0.0, scalar)).min(half.min_element());
+            let rect = Box2D::new(point(center.x - half.x, center.y - half.y), point(center.x + half.x, center.y + half.y));
+            let mut builder = Path::builder();
+            builder.add_rounded_rectangle(&rect, &BorderRadii::new(radius.max(0.0)), Winding::Positive);
+            Some(builder.build())
+        }
+        "el" => {
+            let center = static_vec2("p", Vec2::ZERO);
+            let radii = static_vec2("s", Vec2::ZERO) / 2.0;
+            let mut builder = Path::builder();
+            builder.add_ellipse(point(center.x, center.y), vector(radii.x, radii.y), Angle::zero(), Winding::Positive);
+            Some(builder.build())
+        }
+        _ => None,
+    };
+    Ok(path)
+}
+
+/// All outlines a style paints, given the items before it: their path items
+/// plus those of nested groups, baked through the groups' transforms. A baked
+/// transform can't move, so animated ones are an error.
+fn collect_paths(items: &[&Value], frame_rate: f32) -> Result<Vec<Path>, ImportError> {
+    let mut paths = vec![];
+    for item in items {
+        if item_type(item) == "gr" {
+            let children = visible_items(item);
+            let transform = LottieTransform::parse(children.iter().find(|c| item_type(c) == "tr").copied(), frame_rate);
+            if !transform.is_static() {
+                return unsupported("animated groups painted by a style outside them");
+            }
+            let matrix = to_lyon(transform.matrix(0.0));
+            paths.extend(collect_paths(&children, frame_rate)?.into_iter().map(|p| p.transformed(&matrix)));
+        } else if let Some(path) = item_path(item, frame_rate)? {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+fn visible_items(group: &Value) -> Vec<&Value> {
+    group
+        .get("it")
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter(|item| !is_hidden(item)).collect())
+        .unwrap_or_default()
+}
+
+/// One painted shape of a layer.
+struct Drawable {
+    shape: MeshShape,
+    paint: KeyframeAnimator,
+    opacity: Track<f32>,
+    /// Indices into the layer's group transforms, outermost first.
+    groups: Vec<usize>,
+}
+
+struct Layer {
+    index: Option<i64>,
+    parent: Option<i64>,
+    transform: LottieTransform,
+    /// Layer time zero, in composition seconds.
+    start_time: f32,
+    in_point: f32,
+    out_point: f32,
+    groups: Vec<LottieTransform>,
+    /// Bottom to top.
+    drawables: Vec<Drawable>,
+}
+
+impl Layer {
+    fn parse(layer: &Value, frame_rate: f32) -> Result<Self, ImportError> {
+        let frame = |key: &str, default: f32| layer.get(key).and_then(Value::as_f64).map_or(default, |f| f as f32 / frame_rate);
+        let mut parsed = Layer {
+            index: layer.get("ind").and_then(Value::as_i64),
+            parent: layer.get("parent").and_then(Value::as_i64),
+            transform: LottieTransform::parse(layer.get("ks"), frame_rate),
+            start_time: frame("st", 0.0),
+            in_point: frame("ip", f32::NEG_INFINITY),
+            out_point: frame("op", f32::INFINITY),
+            groups: vec![],
+            drawables: vec![],
+        };
+
+        match layer.get("ty").and_then(Value::as_i64) {
+            // 形状图层
+            Some(4) => {
+                let items = layer
+                    .get("shapes")
+                    .and_then(Value::as_array)
+                    .map(|items| items.iter().filter(|item| !is_hidden(item)).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                parsed.parse_items(&items, &[], None, frame_rate)?;
+            }
+            // 纯色图层
+            Some(1) => parsed.parse_solid(layer),
+            // 空图层只用来做父级
+            Some(3) => {}
+            ty => log::warn!("lottie layer type {:?} is not supported, only its transform is kept", ty),
+        }
+        Ok(parsed)
+    }
+
+    fn parse_solid(&mut self, layer: &Value) {
+        let size = |key: &str| layer.get(key).and_then(Value::as_f64).unwrap_or(0.0) as f32;
+        let hex = layer.get("sc").and_then(Value::as_str).unwrap_or("#000000").trim_start_matches('#');
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok()).unwrap_or(0) as f32 / 255.0;
+
+        let mut builder = Path::builder();
+        builder.add_rectangle(&Box2D::new(point(0.0, 0.0), point(size("sw"), size("sh"))), Winding::Positive);
+        self.drawables.push(Drawable {
+            shape: MeshShape::fill(&builder.build(), FillRule::NonZero, Color::new(channel(0), channel(2), channel(4))),
+            paint: KeyframeAnimator::new(),
+            opacity: Track::new().with_keyframe(Keyframe::hold(0.0, 1.0)),
+            groups: vec![],
+        });
+    }
+
+    /// A style (`fl`, `st`) paints the paths before it in its list, including
+    /// those in nested groups. Earlier items sit on top, so the list is walked
+    /// back to front.
+    fn parse_items(&mut self, items: &[&Value], groups: &[usize], trim: Option<&Value>, frame_rate: f32) -> Result<(), ImportError> {
+        let trim = items.iter().find(|item| item_type(item) == "tm").copied().or(trim);
+        let outline = |end: usize| -> Result<Option<Path>, ImportError> {
+            let paths = collect_paths(&items[..end], frame_rate)?;
+            let mut builder = Path::builder();
+            builder.extend_from_paths(&paths.iter().map(Path::as_slice).collect::<Vec<_>>());
+            let path = builder.build();
+            Ok(path.iter().next().is_some().then_some(path))
+        };
+
+        for (i, item) in items.iter().enumerate().rev() {
+            match item_type(item) {
+                "gr" => {
+                    let children = visible_items(item);
+                    let transform = children.iter().find(|c| item_type(c) == "tr").copied();
+                    self.groups.push(LottieTransform::parse(transform, frame_rate));
+                    let mut nested = groups.to_vec();
+                    nested.push(self.groups.len() - 1);
+                    self.parse_items(&children, &nested, trim, frame_rate)?;
+                }
+                "fl" => {
+                    if let Some(path) = outline(i)? {
+                        let drawable = fill(item, &path, frame_rate);
+                        self.drawables.push(Drawable { groups: groups.to_vec(), ..drawable });
+                    }
+                }
+                "st" => {
+                    if let Some(path) = outline(i)? {
+                        let drawable = stroke(item, path, trim, frame_rate);
+                        self.drawables.push(Drawable { groups: groups.to_vec(), ..drawable });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn fill(item: &Value, path: &Path, frame_rate: f32) -> Drawable {
+    let color = parse_track(item.get("c"), frame_rate, Color::WHITE, color);
+    let mut paint = KeyframeAnimator::new();
+    let fill_rule = match item.get("r").and_then(Value::as_i64) {
+        Some(2) => FillRule::EvenOdd,
+        _ => FillRule::NonZero,
+    };
+    let shape = MeshShape::fill(path, fill_rule, initial(&color));
+    if !is_static(&color) {
+        paint.add_track(PropertyTrack::Color(color));
+    }
+    Drawable {
+        shape,
+        paint,
+        opacity: parse_track(item.get("o"), frame_rate, 1.0, percent),
+        groups: vec![],
+    }
+}
+
+/// A stroke with its dashes and the trim (`tm`) in scope. The trim offset is not supported.
+fn stroke(item: &Value, path: Path, trim: Option<&Value>, frame_rate: f32) -> Drawable {
+    let color = parse_track(item.get("c"), frame_rate, Color::WHITE, color);
+    let width = parse_track(item.get("w"), frame_rate, 1.0, scalar);
+    let mut style = StrokeStyle::new(initial(&width));
+    style.cap = match item.get("lc").and_then(Value::as_i64) {
+        Some(2) => LineCap::Round,
+        Some(3) => LineCap::Square,
+        _ => LineCap::Butt,
+    };
+    style.join = match item.get("lj").and_then(Value::as_i64) {
+        Some(2) => LineJoin::Round,
+        Some(3) => LineJoin::Bevel,
+        _ => LineJoin::Miter,
+    };
+    if let Some(limit) = item.get("ml").and_then(Value::as_f64) {
+        style.miter_limit = limit as f32;
+    }
+
+    let mut paint = KeyframeAnimator::new();
+    for dash in item.get("d").and_then(Value::as_array).into_iter().flatten() {
+        let value = parse_track(dash.get("v"), frame_rate, 0.0, scalar);
+        match dash.get("n").and_then(Value::as_str) {
+            Some("d") | Some("g") => style.dashes.push(initial(&value)),
+            Some("o") => {
+                style.dash_offset = initial(&value);
+                if !is_static(&value) {
+                    paint.add_track(PropertyTrack::DashOffset(value));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(trim) = trim {
+        let start = parse_track(trim.get("s"), frame_rate, 0.0, percent);
+        let end = parse_track(trim.get("e"), frame_rate, 1.0, percent);
+        style.trim_start = initial(&start);
+        style.trim_end = initial(&end);
+        if !is_static(&start) {
+            paint.add_track(PropertyTrack::TrimStart(start));
+        }
+        if !is_static(&end) {
+            paint.add_track(PropertyTrack::TrimEnd(end));
+        }
+    }
+
+    let shape = MeshShape::stroke(path, style, initial(&color));
+    if !is_static(&color) {
+        paint.add_track(PropertyTrack::Color(color));
+    }
+    if !is_static(&width) {
+        paint.add_track(PropertyTrack::StrokeWidth(width));
+    }
+    Drawable {
+        shape,
+        paint,
+        opacity: parse_track(item.get("o"), frame_rate, 1.0, percent),
+        groups: vec![],
+    }
+}
+
+/// A Lottie (Bodymovin) animation: shape, solid and null layers with parenting,
+/// groups, fills, strokes, trim paths and keyframed transforms.
+///
+/// The composition is scaled so its height spans the renderer's [-1, 1] range and
+/// is centred on the origin; move it around with the `Shape` setters.
+///
+/// As an `Animator` it poses its own layers: `update` plays it in a loop,
+/// raising `Looped` at each wrap, and `seek` counts from the in point. `apply`
+/// leaves the target alone, since the composition is drawn as its own shape.
+/// Paths are tessellated once, so animated paths and animated groups that a
+/// style outside them paints are rejected on import.
+pub struct Composition {
+    pub width: f32,
+    pub height: f32,
+    pub frame_rate: f32,
+    in_point: f32,
+    out_point: f32,
+    /// Top to bottom, as in the file.
+    layers: Vec<Layer>,
+    time: f32,
+    /// What the layers are posed at; `time` unless `pose_at` moved it.
+    posed: f32,
+    placement: ShapeTransform,
+    opacity: f32,
+    /// Completed loops since the last seek.
+    iteration: u32,
+    events: Vec<AnimationEvent>,
+}
+
+impl Composition {
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, ImportError> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ImportError> {
+        let root: Value = serde_json::from_str(json)?;
+        Self::from_value(&root)
+    }
+
+    pub fn from_value(root: &Value) -> Result<Self, ImportError> {
+        let number = |key: &str| {
+            root.get(key)
+                .and_then(Value::as_f64)
+                .map(|n| n as f32)
+                .ok_or_else(|| ImportError::Format(format!("missing composition field \"{}\"", key)))
+        };
+        let frame_rate = number("fr")?;
+        if frame_rate <= 0.0 {
+            return Err(ImportError::Format(format!("invalid frame rate {}", frame_rate)));
+        }
+        let layers = root
+            .get("layers")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ImportError::Format("missing \"layers\"".to_string()))?
+            .iter()
+            .filter(|layer| !is_hidden(layer))
+            .map(|layer| Layer::parse(layer, frame_rate))
+            .collect::<Result<_, _>>()?;
+
+        let in_point = number("ip")? / frame_rate;
+        let mut composition = Composition {
+            width: number("w")?,
+            height: number("h")?,
+            frame_rate,
+            in_point,
+            out_point: number("op")? / frame_rate,
+            layers,
+            time: in_point,
+            posed: in_point,
+            placement: ShapeTransform::default(),
+            opacity: 1.0,
+            iteration: 0,
+            events: vec![],
+        };
+        composition.evaluate();
+        Ok(composition)
+    }
+
+    /// Composition time in seconds, from the file's frame zero.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Poses the layers at composition time `time`, wrapped into the play
+    /// range, without moving the playhead; the next `update` carries on from
+    /// `time()`.
+    pub fn pose_at(&mut self, time: f32) {
+        self.evaluate_at(self.wrap(time));
+    }
+
+    fn wrap(&self, time: f32) -> f32 {
+        let duration = self.duration();
+        if duration > 0.0 {
+            self.in_point + (time - self.in_point).rem_euclid(duration)
+        } else {
+            self.in_point
+        }
+    }
+
+    fn root_matrix(&self) -> Mat4 {
+        self.placement.matrix() * fit_to_view(Vec2::ZERO, Vec2::new(self.width, self.height.max(1.0)))
+    }
+
+    fn layer_matrix(&self, layer: &Layer, time: f32, depth: usize) -> Mat4 {
+        let local = layer.transform.matrix(time - layer.start_time);
+        let parent = layer
+            .parent
+            .filter(|_| depth < MAX_PARENT_DEPTH)
+            .and_then(|parent| self.layers.iter().find(|l| l.index == Some(parent)));
+        match parent {
+            Some(parent) => self.layer_matrix(parent, time, depth + 1) * local,
+            None => local,
+        }
+    }
+
+    fn evaluate(&mut self) {
+        self.evaluate_at(self.posed);
+    }
+
+    fn evaluate_at(&mut self, time: f32) {
+        self.posed = time;
+        let root = self.root_matrix();
+        let worlds = self.layers.iter().map(|layer| root * self.layer_matrix(layer, time, 0)).collect::<Vec<_>>();
+        let opacity = self.opacity;
+
+        for (layer, world) in self.layers.iter_mut().zip(worlds) {
+            let local = time - layer.start_time;
+            let layer_opacity = opacity * layer.transform.opacity(local);
+            let groups = layer
+                .groups
+                .iter()
+                .map(|group| (group.matrix(local), group.opacity(local)))
+                .collect::<Vec<_>>();
+
+            for drawable in &mut layer.drawables {
+                let mut matrix = world;
+                let mut opacity = layer_opacity * drawable.opacity.sample(local).unwrap_or(1.0);
+                for &group in &drawable.groups {
+                    matrix *= groups[group].0;
+                    opacity *= groups[group].1;
+                }
+                for value in drawable.paint.evaluate(local) {
+                    value.apply(&mut drawable.shape);
+                }
+                drawable.shape.set_parent_transform(matrix);
+                drawable.shape.set_opacity(opacity);
+            }
+        }
+    }
+
+    fn is_visible(&self, layer: &Layer) -> bool {
+        self.posed >= layer.in_point && self.posed < layer.out_point
+    }
+}
+
+impl Animator for Composition {
+    fn update(&mut self, delta_time: f32) {
+        let delta_time = if delta_time.is_finite() { delta_time } else { 0.0 };
+        let duration = self.duration();
+        let elapsed = self.time - self.in_point + delta_time;
+        if duration > 0.0 && elapsed >= duration {
+            for _ in 0..(elapsed / duration) as u32 {
+                self.iteration += 1;
+                self.events.push(AnimationEvent::Looped { iteration: self.iteration });
+            }
+        }
+        self.time = self.wrap(self.time + delta_time);
+        self.evaluate_at(self.time);
+    }
+
+    fn apply(&self, _shape: &mut dyn Shape) {}
+
+    /// One pass from the in point to the out point.
+    fn duration(&self) -> f32 {
+        (self.out_point - self.in_point).max(0.0)
+    }
+
+    fn seek(&mut self, time: f32) {
+        let time = if time.is_finite() { time.max(0.0) } else { 0.0 };
+        let duration = self.duration();
+        self.iteration = if duration > 0.0 { (time / duration) as u32 } else { 0 };
+        self.time = self.wrap(self.in_point + time);
+        self.evaluate_at(self.time);
+    }
+
+    fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl Shape for Composition {
+    fn set_color(&mut self, _color: Color) {}
+
+    fn set_shadow(&mut self, _shadow: Shadow) {}
+
+    fn set_position(&mut self, position: Vec2) {
+        self.placement.position = position;
+        self.evaluate();
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.placement.rotation = rotation;
+        self.evaluate();
+    }
+
+    fn set_scale(&mut self, scale: Vec2) {
+        self.placement.scale = scale;
+        self.evaluate();
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+        self.evaluate();
+    }
+
+    fn set_anchor(&mut self, anchor: Vec2) {
+        self.placement.anchor = anchor;
+        self.evaluate();
+    }
+
+    fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, layouts: &ShapeLayouts) {
+        for layer in &mut self.layers {
+            for drawable in &mut layer.drawables {
+                drawable.shape.prepare(device, queue, layouts);
+            }
+        }
+    }
+
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        for layer in self.layers.iter().rev().filter(|layer| self.is_visible(layer)) {
+            for drawable in &layer.drawables {
+                drawable.shape.draw(render_pass);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn composition() -> Composition {
+        Composition::from_json(r#"{ "fr": 30, "ip": 30, "op": 90, "w": 100, "h": 100, "layers": [] }"#).unwrap()
+    }
+
+    /// Two seconds at 30 fps, from frame zero.
+    fn with_layers(layers: &str) -> Result<Composition, ImportError> {
+        Composition::from_json(&format!(r#"{{ "fr": 30, "ip": 0, "op": 60, "w": 100, "h": 100, "layers": [{}] }}"#, layers))
+    }
+
+    fn shape_layer(items: &str) -> Result<Composition, ImportError> {
+        with_layers(&format!(r#"{{ "ty": 4, "ind": 1, "ks": {{}}, "shapes": [{}] }}"#, items))
+    }
+
+    const RECT: &str = r#"{ "ty": "rc", "p": { "a": 0, "k": [0, 0] }, "s": { "a": 0, "k": [10, 10] }, "r": { "a": 0, "k": 0 } }"#;
+    const ELLIPSE: &str = r#"{ "ty": "el", "p": { "a": 0, "k": [50, 0] }, "s": { "a": 0, "k": [10, 10] } }"#;
+    const FILL: &str = r#"{ "ty": "fl", "c": { "a": 0, "k": [1, 0, 0, 1] }, "o": { "a": 0, "k": 50 } }"#;
+    const STROKE: &str = r#"{ "ty": "st", "c": { "a": 0, "k": [0, 0, 1, 1] }, "o": { "a": 0, "k": 100 }, "w": { "a": 0, "k": 2 } }"#;
+
+    fn x_range(shape: &MeshShape) -> (f32, f32) {
+        shape
+            .geometry()
+            .vertices
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| (min.min(v.position[0]), max.max(v.position[0])))
+    }
+
+    fn drawables(composition: &Composition, layer: usize) -> Vec<&MeshShape> {
+        composition.layers[layer].drawables.iter().map(|d| &d.shape).collect()
+    }
+
+    #[test]
+    fn playback_wraps_at_the_out_point() {
+        let mut composition = composition();
+        assert_eq!(composition.time(), 1.0);
+        assert_eq!(composition.duration(), 2.0);
+        composition.update(2.5);
+        assert!((composition.time() - 1.5).abs() < 1e-5);
+        assert_eq!(composition.drain_events(), vec![AnimationEvent::Looped { iteration: 1 }]);
+        composition.update(0.25);
+        assert!(composition.drain_events().is_empty());
+
+        // seek 从入点算起
+        composition.seek(0.5);
+        assert!((composition.time() - 1.5).abs() < 1e-5);
+        composition.seek(4.5);
+        assert!((composition.time() - 1.5).abs() < 1e-5);
+        composition.update(1.5);
+        assert_eq!(composition.drain_events(), vec![AnimationEvent::Looped { iteration: 3 }]);
+    }
+
+    #[test]
+    fn posing_leaves_the_playhead() {
+        let mut composition = composition();
+        composition.update(0.5);
+        composition.pose_at(0.0);
+        assert_eq!(composition.posed, 2.0);
+        assert_eq!(composition.time(), 1.5);
+        composition.update(0.25);
+        assert_eq!(composition.time(), 1.75);
+        assert_eq!(composition.posed, 1.75);
+    }
+
+    #[test]
+    fn parses_shape_solid_and_null_layers() {
+        let composition = with_layers(&format!(
+            r##"{{ "ty": 4, "ks": {{}}, "shapes": [{}, {}] }},
+               {{ "ty": 1, "ks": {{}}, "sw": 20, "sh": 10, "sc": "#00ff00" }},
+               {{ "ty": 3, "ks": {{}} }}"##,
+            RECT, FILL
+        ))
+        .unwrap();
+        assert_eq!(composition.layers.len(), 3);
+
+        let fill = drawables(&composition, 0);
+        assert_eq!(fill.len(), 1);
+        assert_eq!(fill[0].color(), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(fill[0].opacity(), 0.5);
+        assert!(fill[0].stroke_style().is_none());
+
+        let solid = drawables(&composition, 1);
+        assert_eq!(solid.len(), 1);
+        assert_eq!(solid[0].color(), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(x_range(solid[0]), (0.0, 20.0));
+
+        assert!(drawables(&composition, 2).is_empty());
+    }
+
+    #[test]
+    fn parents_chain_by_index() {
+        // 子图层写在父图层前面, 按 ind 找父级
+        let composition = with_layers(
+            r#"{ "ty": 3, "ind": 3, "parent": 2, "ks": { "p": { "a": 0, "k": [1, 0] } } },
+               { "ty": 3, "ind": 2, "parent": 1, "ks": { "p": { "a": 0, "k": [5, 0] } } },
+               { "ty": 3, "ind": 1, "ks": { "p": { "a": 0, "k": [10, 0] }, "s": { "a": 0, "k": [200, 200] } } }"#,
+        )
+        .unwrap();
+        let world = |layer: usize| composition.layer_matrix(&composition.layers[layer], 0.0, 0).w_axis.x;
+        assert_eq!(world(2), 10.0);
+        assert_eq!(world(1), 20.0);
+        assert_eq!(world(0), 22.0);
+    }
+
+    #[test]
+    fn nested_groups_keep_their_transform() {
+        let group = format!(r#"{{ "ty": "gr", "it": [{}, {}, {{ "ty": "tr", "p": {{ "a": 0, "k": [20, 0] }} }}] }}"#, RECT, FILL);
+        let composition = shape_layer(&group).unwrap();
+        let layer = &composition.layers[0];
+        assert_eq!(layer.groups.len(), 1);
+        assert_eq!(layer.groups[0].matrix(0.0).w_axis.x, 20.0);
+        assert_eq!(layer.drawables[0].groups, vec![0]);
+        // 组内的填充不烘焙变换, 每帧跟着组走
+        assert_eq!(x_range(&layer.drawables[0].shape), (-5.0, 5.0));
+
+        // 组外的样式把组的变换烘焙进路径
+        let group = format!(r#"{{ "ty": "gr", "it": [{}, {{ "ty": "tr", "p": {{ "a": 0, "k": [20, 0] }} }}] }}"#, RECT);
+        let composition = shape_layer(&format!("{}, {}", group, FILL)).unwrap();
+        let layer = &composition.layers[0];
+        assert!(layer.drawables[0].groups.is_empty());
+        assert_eq!(x_range(&layer.drawables[0].shape), (15.0, 25.0));
+    }
+
+    #[test]
+    fn styles_paint_only_the_paths_before_them() {
+        let composition = shape_layer(&format!("{}, {}, {}, {}, {}", FILL, RECT, FILL, ELLIPSE, STROKE)).unwrap();
+        // 从下往上: 描边 (矩形 + 椭圆), 填充 (矩形); 最前面的填充没有路径
+        let shapes = drawables(&composition, 0);
+        assert_eq!(shapes.len(), 2);
+        assert!(shapes[0].stroke_style().is_some());
+        let (min, max) = x_range(shapes[0]);
+        assert!(min < -5.0 && max > 55.0);
+        assert!(shapes[1].stroke_style().is_none());
+        assert_eq!(x_range(shapes[1]), (-5.0, 5.0));
+    }
+
+    #[test]
+    fn parses_stroke_dashes_and_trim() {
+        let stroke = r#"{ "ty": "st", "c": { "a": 0, "k": [0, 0, 1, 1] }, "o": { "a": 0, "k": 100 }, "w": { "a": 0, "k": 4 },
+            "lc": 2, "lj": 3, "ml": 2,
+            "d": [
+                { "n": "d", "v": { "a": 0, "k": 3 } },
+                { "n": "g", "v": { "a": 0, "k": 2 } },
+                { "n": "o", "v": { "a": 0, "k": 1 } }
+            ] }"#;
+        let trim = r#"{ "ty": "tm", "s": { "a": 0, "k": 10 }, "e": { "a": 0, "k": 90 }, "o": { "a": 0, "k": 0 } }"#;
+        let composition = shape_layer(&format!("{}, {}, {}", RECT, trim, stroke)).unwrap();
+        let shapes = drawables(&composition, 0);
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(shapes[0].color(), Color::new(0.0, 0.0, 1.0));
+
+        let style = shapes[0].stroke_style().unwrap();
+        assert_eq!(style.width, 4.0);
+        assert_eq!(style.cap, LineCap::Round);
+        assert_eq!(style.join, LineJoin::Bevel);
+        assert_eq!(style.miter_limit, 2.0);
+        assert_eq!(style.dashes, vec![3.0, 2.0]);
+        assert_eq!(style.dash_offset, 1.0);
+        assert!((style.trim_start - 0.1).abs() < 1e-6);
+        assert!((style.trim_end - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn hold_keys_jump_and_eased_keys_curve() {
+        let fill = r#"{ "ty": "fl", "o": { "a": 0, "k": 100 }, "c": { "a": 1, "k": [
+            { "t": 0, "s": [1, 0, 0, 1], "h": 1 },
+            { "t": 30, "s": [0, 0, 1, 1] }
+        ] } }"#;
+        let opacity = r#"{ "a": 1, "k": [
+            { "t": 0, "s": [0], "o": { "x": [0.5], "y": [0] }, "i": { "x": [0.5], "y": [1] } },
+            { "t": 30, "s": [100] }
+        ] }"#;
+        let mut composition = with_layers(&format!(
+            r#"{{ "ty": 4, "ks": {{ "o": {} }}, "shapes": [{}, {}] }}"#,
+            opacity, RECT, fill
+        ))
+        .unwrap();
+        let shape = |composition: &Composition| {
+            let shape = &composition.layers[0].drawables[0].shape;
+            (shape.color(), shape.opacity())
+        };
+
+        composition.seek(0.25);
+        let (color, opacity) = shape(&composition);
+        assert_eq!(color, Color::new(1.0, 0.0, 0.0));
+        assert!(opacity > 0.0 && opacity < 0.25);
+
+        composition.seek(0.5);
+        let (color, opacity) = shape(&composition);
+        assert_eq!(color, Color::new(1.0, 0.0, 0.0));
+        assert!((opacity - 0.5).abs() < 1e-3);
+
+        composition.seek(1.0);
+        let (color, opacity) = shape(&composition);
+        assert_eq!(color, Color::new(0.0, 0.0, 1.0));
+        assert_eq!(opacity, 1.0);
+    }
+
+    #[test]
+    fn rejects_animated_paths_and_baked_animated_groups() {
+        let path = r#"{ "ty": "sh", "ks": { "a": 1, "k": [
+            { "t": 0, "s": [{ "c": true, "v": [[0, 0], [10, 0], [10, 10]], "i": [[0, 0], [0, 0], [0, 0]], "o": [[0, 0], [0, 0], [0, 0]] }] },
+            { "t": 30, "s": [{ "c": true, "v": [[0, 0], [20, 0], [20, 20]], "i": [[0, 0], [0, 0], [0, 0]], "o": [[0, 0], [0, 0], [0, 0]] }] }
+        ] } }"#;
+        assert!(matches!(shape_layer(&format!("{}, {}", path, FILL)), Err(ImportError::Format(_))));
+
+        let moving = r#"{ "ty": "tr", "p": { "a": 1, "k": [{ "t": 0, "s": [0, 0] }, { "t": 30, "s": [20, 0] }] } }"#;
+        let group = format!(r#"{{ "ty": "gr", "it": [{}, {}] }}"#, RECT, moving);
+        assert!(matches!(shape_layer(&format!("{}, {}", group, FILL)), Err(ImportError::Format(_))));
+
+        // 组内的样式跟着动画的组走, 不需要烘焙
+        let group = format!(r#"{{ "ty": "gr", "it": [{}, {}, {}] }}"#, RECT, FILL, moving);
+        assert!(shape_layer(&group).is_ok());
+    }
+}
@@ -0,0 +1,55 @@
+pub mod lottie;
+pub mod png;
+pub mod svg;
+pub mod scene;
+
+use std::fmt;
+use glam::{Mat4, Vec2, Vec3};
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The file parsed but doesn't have the structure the format requires.
+    Format(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "failed to read file: {}", e),
+            ImportError::Json(e) => write!(f, "invalid json: {}", e),
+            ImportError::Format(message) => write!(f, "unsupported file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImportError::Io(e) => Some(e),
+            ImportError::Json(e) => Some(e),
+            ImportError::Format(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(e: std::io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(e: serde_json::Error) -> Self {
+        ImportError::Json(e)
+    }
+}
+
+/// Maps a y-down document rectangle onto the renderer's view: its height spans
+/// [-1, 1], its centre lands on the origin and y points up.
+pub(crate) fn fit_to_view(origin: Vec2, size: Vec2) -> Mat4 {
+    let fit = 2.0 / size.y.max(f32::EPSILON);
+    Mat4::from_scale(Vec3::new(fit, -fit, 1.0))
+        * Mat4::from_translation((-(origin + size / 2.0)).extend(0.0))
+}
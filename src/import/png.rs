@@ -0,0 +1,144 @@
+use std::path::Path;
+use super::ImportError;
+
+/// Largest width or height `decode_png` accepts.
+pub const MAX_IMAGE_SIZE: u32 = 16384;
+
+/// An 8-bit RGBA image, rows top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        let len = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(4));
+        assert_eq!(Some(pixels.len()), len, "pixel data doesn't match the image size");
+        Image { width, height, pixels }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        decode_png(&std::fs::read(path)?)
+    }
+}
+
+fn format_error<T>(message: impl std::fmt::Display) -> Result<T, ImportError> {
+    Err(ImportError::Format(format!("png: {}", message)))
+}
+
+/// Decodes a PNG of any color type into RGBA. 16-bit channels are cut to
+/// their high byte; for animated PNGs only the first frame is kept.
+pub fn decode_png(bytes: &[u8]) -> Result<Image, ImportError> {
+    let mut decoder = ::png::Decoder::new(bytes);
+    decoder.set_transformations(::png::Transformations::EXPAND | ::png::Transformations::STRIP_16);
+    let mut reader = match decoder.read_info() {
+        Ok(reader) => reader,
+        Err(e) => return format_error(e),
+    };
+    let (width, height) = reader.info().size();
+    if width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return format_error(format!("{}x{} is larger than {}x{}", width, height, MAX_IMAGE_SIZE, MAX_IMAGE_SIZE));
+    }
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = match reader.next_frame(&mut buffer) {
+        Ok(frame) => frame,
+        Err(e) => return format_error(e),
+    };
+    let data = &buffer[..frame.buffer_size()];
+    // EXPAND 之后只剩这四种 8 位格式
+    let pixels = match frame.color_type {
+        ::png::ColorType::Rgba => data.to_vec(),
+        ::png::ColorType::Rgb => data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        ::png::ColorType::GrayscaleAlpha => data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        ::png::ColorType::Grayscale => data.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        ::png::ColorType::Indexed => return format_error("palette was not expanded"),
+    };
+    Ok(Image::new(frame.width, frame.height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(width: u32, height: u32, color: ::png::ColorType, depth: ::png::BitDepth, data: &[u8]) -> Vec<u8> {
+        encode_with(width, height, color, depth, data, |_| {})
+    }
+
+    fn encode_with(
+        width: u32,
+        height: u32,
+        color: ::png::ColorType,
+        depth: ::png::BitDepth,
+        data: &[u8],
+        setup: impl FnOnce(&mut ::png::Encoder<&mut Vec<u8>>),
+    ) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut encoder = ::png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        setup(&mut encoder);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn decodes_rgba() {
+        let data = [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 9, 9, 9, 9];
+        let image = decode_png(&encode(2, 2, ::png::ColorType::Rgba, ::png::BitDepth::Eight, &data)).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixels, data);
+    }
+
+    #[test]
+    fn expands_rgb_and_gray() {
+        let rgb = decode_png(&encode(1, 1, ::png::ColorType::Rgb, ::png::BitDepth::Eight, &[1, 2, 3])).unwrap();
+        assert_eq!(rgb.pixels, [1, 2, 3, 255]);
+        let gray = decode_png(&encode(2, 1, ::png::ColorType::GrayscaleAlpha, ::png::BitDepth::Eight, &[7, 8, 9, 10])).unwrap();
+        assert_eq!(gray.pixels, [7, 7, 7, 8, 9, 9, 9, 10]);
+        // 1 位灰度: 10 -> 白, 黑
+        let bits = decode_png(&encode(2, 1, ::png::ColorType::Grayscale, ::png::BitDepth::One, &[0b1000_0000])).unwrap();
+        assert_eq!(bits.pixels, [255, 255, 255, 255, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn keeps_the_high_byte_of_16_bit_channels() {
+        let image = decode_png(&encode(1, 1, ::png::ColorType::Rgb, ::png::BitDepth::Sixteen, &[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc])).unwrap();
+        assert_eq!(image.pixels, [0x12, 0x56, 0x9a, 255]);
+    }
+
+    #[test]
+    fn expands_palettes_with_transparency() {
+        let bytes = encode_with(3, 1, ::png::ColorType::Indexed, ::png::BitDepth::Eight, &[1, 0, 1], |encoder| {
+            encoder.set_palette(vec![10, 20, 30, 40, 50, 60]);
+            encoder.set_trns(vec![0]);
+        });
+        let image = decode_png(&bytes).unwrap();
+        assert_eq!(image.pixels, [40, 50, 60, 255, 10, 20, 30, 0, 40, 50, 60, 255]);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(decode_png(b"not a png").is_err());
+        let mut bytes = encode(2, 2, ::png::ColorType::Rgba, ::png::BitDepth::Eight, &[0; 16]);
+        assert!(decode_png(&bytes[..bytes.len() / 2]).is_err());
+        // 改坏 IHDR 的 CRC
+        bytes[29] ^= 0xff;
+        assert!(decode_png(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_huge_images() {
+        let bytes = encode(MAX_IMAGE_SIZE + 1, 1, ::png::ColorType::Grayscale, ::png::BitDepth::One, &[0; (MAX_IMAGE_SIZE as usize + 8) / 8]);
+        assert!(decode_png(&bytes).is_err());
+    }
+
+    #[test]
+    fn loads_the_demo_sprite() {
+        let image = Image::load("src/res/sprites/blob.png").unwrap();
+        assert_eq!(image.pixels.len(), image.width as usize * image.height as usize * 4);
+    }
+}
@@ -0,0 +1,1647 @@
+use std::collections::BTreeMap;
+use std::path::{Path as FsPath, PathBuf};
+use std::rc::Rc;
+use glam::{Affine2, Mat4, Vec2};
+use lyon::math::point;
+use lyon::path::{FillRule, Path, Polygon};
+use lyon::tessellation::VertexBuffers;
+use serde::{Deserialize, Serialize};
+use crate::dep::basic::motion_blur::MotionBlur;
+use crate::animation::flipbook::{FlipbookAnimator, LoopMode};
+use crate::animation::particles::{Burst, EmitterShape, ParticleEmitter, ParticleSystem, RandomRange};
+use crate::animation::easing::{CubicBezier, EaseMode, Easing, StepPosition};
+use crate::animation::keyframe::{Animatable, Interpolation, Keyframe, KeyframeAnimator, PropertyTrack, Track};
+use crate::animation::path_follow::PathFollowAnimator;
+use crate::animation::noise::WiggleAnimator;
+use crate::animation::skeleton::{BoneTransform, Skeleton};
+use crate::animation::expression::{snapshot_variables, ExpressionAnimator};
+use crate::animation::layer::{AnimationLayer, BlendMode, LayerStack};
+use crate::animation::property::{Property, PropertySnapshot, PropertyValue};
+use crate::animation::state_machine::{Condition, Parameter, StateMachine, Transition};
+use crate::animation::timeline::{Repeat, Timeline};
+use crate::animation::{Animator, RotationAnimator};
+use crate::shapes::circle::IVertex;
+use crate::shapes::mesh::{MeshShape, ShapeTransform};
+use crate::shapes::paint::{Gradient, GradientKind, GradientStop, Paint, SpreadMode};
+use crate::shapes::primitive::{Arrow, Circle, Ellipse, Line, Primitive, Rect, RegularPolygon, RoundedRect, Star};
+use crate::shapes::skin::{subdivide, SkinnedMesh};
+use crate::shapes::sprite::{AtlasFrame, Sprite, TextureAtlas};
+use crate::shapes::stroke::{LineCap, LineJoin, StrokeStyle};
+use crate::shapes::{Color, Shadow, Shape};
+use super::png::Image;
+use super::svg::path_data;
+use super::ImportError;
+
+/// Everything the renderer shows, as loaded from a scene file.
+///
+/// Coordinates are in the renderer's view space: y points up and the window
+/// height spans [-1, 1]. Relative file paths resolve against the scene file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub window: WindowSettings,
+    /// Clear color, RGBA.
+    pub background: [f32; 4],
+    /// Mesh drawn once per entry in `instances`.
+    pub instance_mesh: InstanceMesh,
+    pub instances: Vec<SceneInstance>,
+    /// Named gradients that shape fills and strokes refer to.
+    pub gradients: BTreeMap<String, SceneGradient>,
+    pub shapes: Vec<SceneShape>,
+    /// Named PNG images cut into frames, shown by `sprites`.
+    pub atlases: BTreeMap<String, SceneAtlas>,
+    /// Drawn above the shapes.
+    pub sprites: Vec<SceneSprite>,
+    /// Skinned meshes, drawn above the shapes and sprites.
+    pub skins: Vec<SceneSkin>,
+    /// Particle emitters, drawn above the skins.
+    pub particles: Vec<SceneEmitter>,
+    pub svgs: Vec<SceneFile>,
+    /// Lottie files, played in a loop above the shapes.
+    pub compositions: Vec<SceneFile>,
+    /// Averages sub-frames across a shutter interval; off when absent.
+    pub motion_blur: Option<MotionBlur>,
+    #[serde(skip)]
+    base_dir: Option<PathBuf>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            window: WindowSettings::default(),
+            background: [0.0, 0.0, 0.0, 1.0],
+            instance_mesh: InstanceMesh::default(),
+            instances: vec![SceneInstance {
+                position: [0.0, 1.0, 0.0],
+                state_machine: Some(spin_on_trigger("rotate")),
+                ..SceneInstance::default()
+            }],
+            gradients: BTreeMap::new(),
+            shapes: vec![],
+            atlases: BTreeMap::new(),
+            sprites: vec![],
+            skins: vec![],
+            particles: vec![],
+            svgs: vec![],
+            compositions: vec![],
+            motion_blur: None,
+            base_dir: None,
+        }
+    }
+}
+
+/// Idles until `trigger` is set, then does one eased full turn.
+fn spin_on_trigger(trigger: &str) -> SceneStateMachine {
+    let turn = SceneAnimation {
+        kind: SceneAnimationKind::Keyframes {
+            tracks: vec![SceneTrack {
+                property: Property::Rotation,
+                keyframes: vec![
+                    SceneKeyframe {
+                        time: 0.0,
+                        value: SceneValue::Scalar(0.0),
+                        easing: SceneEasing::Named("ease-in-out".to_string()),
+                        hold: false,
+                    },
+                    SceneKeyframe {
+                        time: SPIN_DURATION,
+                        value: SceneValue::Scalar(std::f32::consts::TAU),
+                        easing: SceneEasing::default(),
+                        hold: false,
+                    },
+                ],
+            }],
+        },
+        blend: BlendMode::Override,
+        weight: 1.0,
+        delay: 0.0,
+        repeat: None,
+        yoyo: false,
+    };
+    SceneStateMachine {
+        states: vec![
+            SceneState { name: "idle".to_string(), animations: vec![] },
+            SceneState { name: "spin".to_string(), animations: vec![turn] },
+        ],
+        transitions: vec![
+            SceneTransition {
+                from: Some("idle".to_string()),
+                to: "spin".to_string(),
+                conditions: vec![SceneCondition::Trigger { trigger: trigger.to_string() }],
+                exit_time: None,
+                fade: 0.0,
+            },
+            SceneTransition {
+                from: Some("spin".to_string()),
+                to: "idle".to_string(),
+                conditions: vec![],
+                exit_time: Some(SPIN_DURATION),
+                fade: 0.0,
+            },
+        ],
+    }
+}
+
+const SPIN_DURATION: f32 = 0.6;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            width: 640,
+            height: 640,
+            title: "wgpu animation".to_string(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstanceMesh {
+    pub radius: f32,
+    pub segments: u32,
+}
+
+impl Default for InstanceMesh {
+    fn default() -> Self {
+        InstanceMesh { radius: 0.7, segments: 100 }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneInstance {
+    pub position: [f32; 3],
+    /// Radians around z.
+    pub rotation: f32,
+    pub scale: [f32; 2],
+    /// Copies of this instance; each gets the next `index` for expressions.
+    pub count: usize,
+    pub animations: Vec<SceneAnimation>,
+    pub state_machine: Option<SceneStateMachine>,
+}
+
+impl SceneInstance {
+    pub fn rest_pose(&self) -> PropertySnapshot {
+        let mut rest = PropertySnapshot::new();
+        rest.set(PropertyValue::Position(Vec2::new(self.position[0], self.position[1])));
+        rest.set(PropertyValue::Rotation(self.rotation));
+        rest.set(PropertyValue::Scale(Vec2::from(self.scale)));
+        rest
+    }
+
+    /// Everything that animates the `index`th instance, or `None` if it's static.
+    /// `index` is what expressions read as `index`.
+    pub fn animator(&self, index: usize) -> Result<Option<Box<dyn Animator>>, ImportError> {
+        layer_stack(self.rest_pose(), index, &self.animations, self.state_machine.as_ref())
+    }
+}
+
+impl Default for SceneInstance {
+    fn default() -> Self {
+        SceneInstance {
+            position: [0.0; 3],
+            rotation: 0.0,
+            scale: [1.0, 1.0],
+            count: 1,
+            animations: vec![],
+            state_machine: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneStop {
+    pub offset: f32,
+    pub color: [f32; 3],
+    #[serde(default = "default_one")]
+    pub opacity: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SceneGradientKind {
+    Linear {
+        start: [f32; 2],
+        end: [f32; 2],
+    },
+    Radial {
+        center: [f32; 2],
+        radius: f32,
+        #[serde(default)]
+        focal: Option<[f32; 2]>,
+    },
+    /// `angle` in radians from +x, counter-clockwise.
+    Conic {
+        center: [f32; 2],
+        #[serde(default)]
+        angle: f32,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneSpread {
+    #[default]
+    Pad,
+    Repeat,
+    Reflect,
+}
+
+/// Moves the gradient within the shape: scaled, then rotated, then translated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneGradientTransform {
+    pub position: [f32; 2],
+    pub rotation: f32,
+    pub scale: [f32; 2],
+}
+
+impl Default for SceneGradientTransform {
+    fn default() -> Self {
+        SceneGradientTransform {
+            position: [0.0, 0.0],
+            rotation: 0.0,
+            scale: default_scale(),
+        }
+    }
+}
+
+/// Gradient coordinates are in the local space of the shape being painted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneGradient {
+    #[serde(flatten)]
+    pub kind: SceneGradientKind,
+    pub stops: Vec<SceneStop>,
+    #[serde(default)]
+    pub spread: SceneSpread,
+    #[serde(default)]
+    pub transform: Option<SceneGradientTransform>,
+}
+
+impl SceneGradient {
+    pub fn to_gradient(&self) -> Gradient {
+        let mut gradient = match self.kind {
+            SceneGradientKind::Linear { start, end } => Gradient::linear(Vec2::from(start), Vec2::from(end)),
+            SceneGradientKind::Radial { center, radius, focal } => {
+                let center = Vec2::from(center);
+                let mut gradient = Gradient::radial(center, radius);
+                gradient.kind = GradientKind::Radial {
+                    center,
+                    radius,
+                    focal: focal.map_or(center, Vec2::from),
+                };
+                gradient
+            }
+            SceneGradientKind::Conic { center, angle } => Gradient::conic(Vec2::from(center), angle),
+        };
+        for stop in &self.stops {
+            gradient.add_stop(GradientStop {
+                offset: stop.offset,
+                color: color(stop.color),
+                opacity: stop.opacity,
+            });
+        }
+        gradient.spread = match self.spread {
+            SceneSpread::Pad => SpreadMode::Pad,
+            SceneSpread::Repeat => SpreadMode::Repeat,
+            SceneSpread::Reflect => SpreadMode::Reflect,
+        };
+        if let Some(transform) = &self.transform {
+            gradient.transform = Affine2::from_scale_angle_translation(
+                Vec2::from(transform.scale),
+                transform.rotation,
+                Vec2::from(transform.position),
+            );
+        }
+        gradient
+    }
+}
+
+/// A solid `[r, g, b]` or `{ "gradient": "<name>" }`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScenePaint {
+    Color([f32; 3]),
+    Gradient { gradient: String },
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneLineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneLineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneStroke {
+    pub paint: ScenePaint,
+    #[serde(default = "default_stroke_width")]
+    pub width: f32,
+    #[serde(default)]
+    pub cap: SceneLineCap,
+    #[serde(default)]
+    pub join: SceneLineJoin,
+    #[serde(default)]
+    pub dashes: Vec<f32>,
+}
+
+fn default_stroke_width() -> f32 {
+    StrokeStyle::default().width
+}
+
+impl SceneStroke {
+    pub fn style(&self) -> StrokeStyle {
+        let mut style = StrokeStyle::new(self.width);
+        style.cap = match self.cap {
+            SceneLineCap::Butt => LineCap::Butt,
+            SceneLineCap::Round => LineCap::Round,
+            SceneLineCap::Square => LineCap::Square,
+        };
+        style.join = match self.join {
+            SceneLineJoin::Miter => LineJoin::Miter,
+            SceneLineJoin::Round => LineJoin::Round,
+            SceneLineJoin::Bevel => LineJoin::Bevel,
+        };
+        style.dashes = self.dashes.clone();
+        style
+    }
+}
+
+/// Outline of a shape, centred on its local origin.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SceneGeometry {
+    Circle {
+        radius: f32,
+    },
+    Ellipse {
+        radii: [f32; 2],
+    },
+    Rect {
+        size: [f32; 2],
+        #[serde(default)]
+        corner_radius: f32,
+    },
+    Polygon {
+        points: Vec<[f32; 2]>,
+        #[serde(default = "default_true")]
+        closed: bool,
+    },
+    RegularPolygon {
+        sides: u32,
+        radius: f32,
+    },
+    Star {
+        points: u32,
+        outer_radius: f32,
+        inner_radius: f32,
+    },
+    /// Points along +x; the head defaults to 40% of the length and three times
+    /// the shaft width.
+    Arrow {
+        length: f32,
+        shaft_width: f32,
+        #[serde(default)]
+        head_length: Option<f32>,
+        #[serde(default)]
+        head_width: Option<f32>,
+        #[serde(default)]
+        corner_radius: f32,
+    },
+    /// Has no inside; give it a stroke.
+    Line {
+        from: [f32; 2],
+        to: [f32; 2],
+    },
+    /// SVG path data, in view space.
+    Path {
+        data: String,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl SceneGeometry {
+    pub fn path(&self) -> Path {
+        match self {
+            SceneGeometry::Circle { radius } => Circle::new(*radius).path(),
+            SceneGeometry::Ellipse { radii } => Ellipse::new(Vec2::from(*radii)).path(),
+            SceneGeometry::Rect { size, corner_radius } if *corner_radius > 0.0 => {
+                RoundedRect::new(Vec2::from(*size), *corner_radius).path()
+            }
+            SceneGeometry::Rect { size, .. } => Rect::new(Vec2::from(*size)).path(),
+            SceneGeometry::Polygon { points, closed } => {
+                let points = points.iter().map(|p| point(p[0], p[1])).collect::<Vec<_>>();
+                let mut builder = Path::builder();
+                builder.add_polygon(Polygon { points: &points, closed: *closed });
+                builder.build()
+            }
+            SceneGeometry::RegularPolygon { sides, radius } => RegularPolygon::new(*sides, *radius).path(),
+            SceneGeometry::Star { points, outer_radius, inner_radius } => {
+                Star::new(*points, *outer_radius, *inner_radius).path()
+            }
+            SceneGeometry::Arrow { length, shaft_width, head_length, head_width, corner_radius } => {
+                let arrow = Arrow::new(*length, *shaft_width).with_corner_radius(*corner_radius);
+                arrow
+                    .with_head(head_length.unwrap_or(arrow.head_length), head_width.unwrap_or(arrow.head_width))
+                    .path()
+            }
+            SceneGeometry::Line { from, to } => Line::new(Vec2::from(*from), Vec2::from(*to)).path(),
+            SceneGeometry::Path { data } => path_data(data),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneShape {
+    #[serde(flatten)]
+    pub geometry: SceneGeometry,
+    #[serde(default)]
+    pub fill: Option<ScenePaint>,
+    #[serde(default)]
+    pub stroke: Option<SceneStroke>,
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 2],
+    #[serde(default)]
+    pub anchor: [f32; 2],
+    #[serde(default = "default_one")]
+    pub opacity: f32,
+    /// Drawn under the fill, or under the stroke of an unfilled shape.
+    #[serde(default)]
+    pub shadow: Option<SceneShadow>,
+    #[serde(default)]
+    pub animations: Vec<SceneAnimation>,
+    #[serde(default)]
+    pub state_machine: Option<SceneStateMachine>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneShadow {
+    pub color: [f32; 3],
+    pub offset: [f32; 2],
+    pub blur: f32,
+    pub opacity: f32,
+}
+
+impl Default for SceneShadow {
+    fn default() -> Self {
+        let shadow = Shadow::default();
+        SceneShadow {
+            color: shadow.color.to_array(),
+            offset: shadow.offset.into(),
+            blur: shadow.blur_radius,
+            opacity: shadow.opacity,
+        }
+    }
+}
+
+impl SceneShadow {
+    pub fn shadow(&self) -> Shadow {
+        Shadow {
+            color: color(self.color),
+            offset: self.offset.into(),
+            blur_radius: self.blur,
+            opacity: self.opacity,
+        }
+    }
+}
+
+fn default_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+impl SceneShape {
+    pub fn transform(&self) -> ShapeTransform {
+        ShapeTransform {
+            position: Vec2::from(self.position),
+            rotation: self.rotation,
+            scale: Vec2::from(self.scale),
+            anchor: Vec2::from(self.anchor),
+        }
+    }
+
+    pub fn rest_pose(&self) -> PropertySnapshot {
+        let mut rest = PropertySnapshot::new();
+        rest.set(PropertyValue::Position(Vec2::from(self.position)));
+        rest.set(PropertyValue::Rotation(self.rotation));
+        rest.set(PropertyValue::Scale(Vec2::from(self.scale)));
+        rest.set(PropertyValue::Anchor(Vec2::from(self.anchor)));
+        rest.set(PropertyValue::Opacity(self.opacity));
+        if let Some(shadow) = &self.shadow {
+            rest.set(PropertyValue::ShadowColor(color(shadow.color)));
+            rest.set(PropertyValue::ShadowOffset(Vec2::from(shadow.offset)));
+            rest.set(PropertyValue::ShadowBlur(shadow.blur));
+            rest.set(PropertyValue::ShadowOpacity(shadow.opacity));
+        }
+        rest
+    }
+
+    /// Everything that animates this shape, or `None` if it's static. `index` is
+    /// the shape's position in the scene, which expressions read as `index`.
+    pub fn animator(&self, index: usize) -> Result<Option<Box<dyn Animator>>, ImportError> {
+        layer_stack(self.rest_pose(), index, &self.animations, self.state_machine.as_ref())
+    }
+
+    /// The fill mesh, then the stroke mesh, whichever are present.
+    pub fn meshes(&self, gradients: &BTreeMap<String, SceneGradient>) -> Result<Vec<MeshShape>, ImportError> {
+        let paint = |paint: &ScenePaint| match paint {
+            ScenePaint::Color(c) => Ok(Paint::Solid(color(*c))),
+            ScenePaint::Gradient { gradient: name } => gradients
+                .get(name)
+                .map(|gradient| Paint::Gradient(gradient.to_gradient()))
+                .ok_or_else(|| ImportError::Format(format!("unknown gradient '{}'", name))),
+        };
+        let path = self.geometry.path();
+        let mut meshes = vec![];
+        if let Some(fill) = &self.fill {
+            meshes.push(MeshShape::fill(&path, FillRule::NonZero, Color::WHITE).with_paint(paint(fill)?));
+        }
+        if let Some(stroke) = &self.stroke {
+            meshes.push(MeshShape::stroke(path.clone(), stroke.style(), Color::WHITE).with_paint(paint(&stroke.paint)?));
+        }
+        for mesh in &mut meshes {
+            mesh.transform = self.transform();
+            mesh.set_opacity(self.opacity);
+        }
+        if let (Some(shadow), Some(mesh)) = (&self.shadow, meshes.first_mut()) {
+            mesh.set_shadow(shadow.shadow());
+        }
+        Ok(meshes)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneBone {
+    pub name: String,
+    /// Name of a bone listed earlier; a root bone if absent.
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// Bind pose relative to the parent.
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 2],
+    /// Extent along the bone's x axis, used to weight vertices to it.
+    #[serde(default)]
+    pub length: f32,
+    #[serde(default)]
+    pub animations: Vec<SceneAnimation>,
+    #[serde(default)]
+    pub state_machine: Option<SceneStateMachine>,
+}
+
+impl SceneBone {
+    pub fn rest(&self) -> BoneTransform {
+        BoneTransform {
+            position: Vec2::from(self.position),
+            rotation: self.rotation,
+            scale: Vec2::from(self.scale),
+        }
+    }
+
+    pub fn rest_pose(&self) -> PropertySnapshot {
+        let mut rest = PropertySnapshot::new();
+        rest.set(PropertyValue::Position(Vec2::from(self.position)));
+        rest.set(PropertyValue::Rotation(self.rotation));
+        rest.set(PropertyValue::Scale(Vec2::from(self.scale)));
+        rest
+    }
+
+    /// Everything that poses the `index`th bone, or `None` if it stays in its bind pose.
+    pub fn animator(&self, index: usize) -> Result<Option<Box<dyn Animator>>, ImportError> {
+        layer_stack(self.rest_pose(), index, &self.animations, self.state_machine.as_ref())
+    }
+}
+
+/// A PNG image and the frames in it. Without `frames` the image is cut into a
+/// `columns` × `rows` grid, numbered row by row.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneAtlas {
+    pub image: String,
+    #[serde(default = "default_grid")]
+    pub columns: u32,
+    #[serde(default = "default_grid")]
+    pub rows: u32,
+    /// Frame rectangles in pixels, `[x, y, width, height]` from the top-left corner.
+    #[serde(default)]
+    pub frames: Vec<[u32; 4]>,
+    /// Nearest-neighbour sampling, for pixel art.
+    #[serde(default)]
+    pub pixelated: bool,
+}
+
+fn default_grid() -> u32 {
+    1
+}
+
+impl SceneAtlas {
+    pub fn atlas(&self, image: Image) -> TextureAtlas {
+        let atlas = if self.frames.is_empty() {
+            TextureAtlas::grid(image, self.columns, self.rows)
+        } else {
+            let frames = self
+                .frames
+                .iter()
+                .map(|&[x, y, width, height]| AtlasFrame { x, y, width, height })
+                .collect();
+            TextureAtlas::with_frames(image, frames).expect("frames is not empty")
+        };
+        atlas.with_pixelated(self.pixelated)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneSprite {
+    /// Name of an entry in the scene's `atlases`.
+    pub atlas: String,
+    #[serde(default)]
+    pub frame: usize,
+    /// Width and height in view units; defaults to 0.5 high with the first
+    /// frame's aspect ratio.
+    #[serde(default)]
+    pub size: Option<[f32; 2]>,
+    /// Point of the sprite placed at `position`, (0, 0) bottom-left to (1, 1) top-right.
+    #[serde(default = "default_pivot")]
+    pub pivot: [f32; 2],
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 2],
+    #[serde(default = "default_one")]
+    pub opacity: f32,
+    #[serde(default)]
+    pub animations: Vec<SceneAnimation>,
+    #[serde(default)]
+    pub state_machine: Option<SceneStateMachine>,
+}
+
+fn default_pivot() -> [f32; 2] {
+    [0.5, 0.5]
+}
+
+impl SceneSprite {
+    pub fn transform(&self) -> ShapeTransform {
+        ShapeTransform {
+            position: Vec2::from(self.position),
+            rotation: self.rotation,
+            scale: Vec2::from(self.scale),
+            anchor: Vec2::ZERO,
+        }
+    }
+
+    pub fn rest_pose(&self) -> PropertySnapshot {
+        let mut rest = PropertySnapshot::new();
+        rest.set(PropertyValue::Position(Vec2::from(self.position)));
+        rest.set(PropertyValue::Rotation(self.rotation));
+        rest.set(PropertyValue::Scale(Vec2::from(self.scale)));
+        rest.set(PropertyValue::Opacity(self.opacity));
+        rest.set(PropertyValue::Frame(self.frame as f32));
+        rest
+    }
+
+    pub fn animator(&self, index: usize) -> Result<Option<Box<dyn Animator>>, ImportError> {
+        layer_stack(self.rest_pose(), index, &self.animations, self.state_machine.as_ref())
+    }
+
+    pub fn sprite(&self, atlases: &BTreeMap<String, Rc<TextureAtlas>>) -> Result<Sprite, ImportError> {
+        let atlas = atlases
+            .get(&self.atlas)
+            .ok_or_else(|| ImportError::Format(format!("unknown atlas '{}'", self.atlas)))?;
+        let size = match self.size {
+            Some(size) => Vec2::from(size),
+            None => {
+                let frame = atlas.frame(0);
+                Vec2::new(0.5 * frame.width as f32 / frame.height.max(1) as f32, 0.5)
+            }
+        };
+        let mut sprite = Sprite::new(atlas.clone(), size);
+        sprite.pivot = Vec2::from(self.pivot);
+        sprite.transform = self.transform();
+        sprite.set_opacity(self.opacity);
+        sprite.set_frame(self.frame);
+        Ok(sprite)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SceneEmitterShape {
+    #[default]
+    Point,
+    Line { from: [f32; 2], to: [f32; 2] },
+    Circle {
+        radius: f32,
+        /// Emit only from the outline.
+        #[serde(default)]
+        edge: bool,
+    },
+    /// SVG path data relative to the emitter.
+    Path { data: String },
+}
+
+impl SceneEmitterShape {
+    pub fn to_shape(&self) -> EmitterShape {
+        match self {
+            SceneEmitterShape::Point => EmitterShape::Point,
+            SceneEmitterShape::Line { from, to } => EmitterShape::Line { from: Vec2::from(*from), to: Vec2::from(*to) },
+            SceneEmitterShape::Circle { radius, edge } => EmitterShape::Circle { radius: *radius, edge: *edge },
+            SceneEmitterShape::Path { data } => EmitterShape::path(&path_data(data)),
+        }
+    }
+}
+
+/// A fixed number, or `[min, max]` to pick from at random.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SceneRange {
+    Fixed(f32),
+    Between([f32; 2]),
+}
+
+impl SceneRange {
+    pub fn to_range(self) -> RandomRange {
+        match self {
+            SceneRange::Fixed(value) => RandomRange::constant(value),
+            SceneRange::Between([min, max]) => RandomRange::new(min, max),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneBurst {
+    #[serde(default)]
+    pub time: f32,
+    pub count: u32,
+}
+
+/// Curves over each particle's life, keyframe times running from 0 at birth to 1
+/// at death. `size` and `speed` multiply the particle's own values.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneOverLife {
+    pub size: Vec<SceneKeyframe>,
+    pub speed: Vec<SceneKeyframe>,
+    pub color: Vec<SceneKeyframe>,
+    pub opacity: Vec<SceneKeyframe>,
+}
+
+/// A particle emitter. Angles are in radians; `direction` defaults to straight up.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneEmitter {
+    pub position: [f32; 2],
+    pub rotation: f32,
+    pub shape: SceneEmitterShape,
+    /// Particles per second.
+    pub rate: f32,
+    pub bursts: Vec<SceneBurst>,
+    /// Seconds to keep emitting, forever if absent.
+    pub duration: Option<f32>,
+    pub lifetime: SceneRange,
+    pub speed: SceneRange,
+    pub direction: f32,
+    pub spread: f32,
+    /// Particle radius.
+    pub size: SceneRange,
+    pub angle: SceneRange,
+    pub spin: SceneRange,
+    pub color: [f32; 3],
+    pub over_life: SceneOverLife,
+    pub gravity: [f32; 2],
+    pub drag: f32,
+    pub max_particles: usize,
+    pub seed: u64,
+    /// Simulate on the GPU, for very large `max_particles`.
+    pub gpu: bool,
+    /// Move and turn the emitter.
+    pub animations: Vec<SceneAnimation>,
+    pub state_machine: Option<SceneStateMachine>,
+}
+
+impl Default for SceneEmitter {
+    fn default() -> Self {
+        let emitter = ParticleEmitter::default();
+        SceneEmitter {
+            position: [0.0, 0.0],
+            rotation: 0.0,
+            shape: SceneEmitterShape::Point,
+            rate: emitter.rate,
+            bursts: vec![],
+            duration: None,
+            lifetime: SceneRange::Fixed(emitter.lifetime.min),
+            speed: SceneRange::Fixed(emitter.speed.min),
+            direction: emitter.direction,
+            spread: emitter.spread,
+            size: SceneRange::Fixed(emitter.size.min),
+            angle: SceneRange::Fixed(0.0),
+            spin: SceneRange::Fixed(0.0),
+            color: [1.0, 1.0, 1.0],
+            over_life: SceneOverLife::default(),
+            gravity: [0.0, 0.0],
+            drag: 0.0,
+            max_particles: emitter.max_particles,
+            seed: 0,
+            gpu: false,
+            animations: vec![],
+            state_machine: None,
+        }
+    }
+}
+
+impl SceneEmitter {
+    pub fn rest_pose(&self) -> PropertySnapshot {
+        let mut rest = PropertySnapshot::new();
+        rest.set(PropertyValue::Position(Vec2::from(self.position)));
+        rest.set(PropertyValue::Rotation(self.rotation));
+        rest.set(PropertyValue::Color(color(self.color)));
+        rest
+    }
+
+    pub fn animator(&self, index: usize) -> Result<Option<Box<dyn Animator>>, ImportError> {
+        layer_stack(self.rest_pose(), index, &self.animations, self.state_machine.as_ref())
+    }
+
+    pub fn emitter(&self) -> Result<ParticleEmitter, ImportError> {
+        let number = |curve: &str| {
+            let message = format!("{} over life keyframes take a number", curve);
+            move |v: &SceneValue| match v {
+                SceneValue::Scalar(v) => Ok(*v),
+                SceneValue::Vector(_) => Err(ImportError::Format(message.clone())),
+            }
+        };
+        let life = &self.over_life;
+        Ok(ParticleEmitter {
+            position: Vec2::from(self.position),
+            rotation: self.rotation,
+            shape: self.shape.to_shape(),
+            rate: self.rate,
+            bursts: self.bursts.iter().map(|b| Burst { time: b.time, count: b.count }).collect(),
+            duration: self.duration.unwrap_or(f32::INFINITY),
+            lifetime: self.lifetime.to_range(),
+            speed: self.speed.to_range(),
+            direction: self.direction,
+            spread: self.spread,
+            size: self.size.to_range(),
+            angle: self.angle.to_range(),
+            spin: self.spin.to_range(),
+            color: color(self.color),
+            size_over_life: track(&life.size, number("size"))?,
+            speed_over_life: track(&life.speed, number("speed"))?,
+            color_over_life: track(&life.color, |v| v.components::<3>(Property::Color).map(color))?,
+            opacity_over_life: track(&life.opacity, number("opacity"))?,
+            gravity: Vec2::from(self.gravity),
+            drag: self.drag,
+            max_particles: self.max_particles,
+        })
+    }
+
+    pub fn system(&self) -> Result<ParticleSystem, ImportError> {
+        Ok(ParticleSystem::new(self.emitter()?, self.seed))
+    }
+}
+
+/// Shapes deformed by a bone hierarchy. Shape coordinates are in skeleton space,
+/// and each vertex is weighted to the bones nearest to it in the bind pose.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneSkin {
+    pub bones: Vec<SceneBone>,
+    pub shapes: Vec<SceneShape>,
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 2],
+    #[serde(default = "default_one")]
+    pub opacity: f32,
+    /// Longest triangle edge after subdividing; smaller bends more smoothly.
+    #[serde(default = "default_skin_resolution")]
+    pub resolution: f32,
+    /// Animate the skin as a whole; bones have their own.
+    #[serde(default)]
+    pub animations: Vec<SceneAnimation>,
+    #[serde(default)]
+    pub state_machine: Option<SceneStateMachine>,
+}
+
+fn default_skin_resolution() -> f32 {
+    0.05
+}
+
+impl SceneSkin {
+    pub fn skeleton(&self) -> Result<Skeleton, ImportError> {
+        let mut skeleton = Skeleton::new();
+        for bone in &self.bones {
+            let parent = match &bone.parent {
+                None => None,
+                Some(name) => Some(skeleton.bone_index(name).ok_or_else(|| {
+                    ImportError::Format(format!("bone '{}' has unknown parent '{}'", bone.name, name))
+                })?),
+            };
+            skeleton.add_bone(&bone.name, parent, bone.rest(), bone.length);
+        }
+        Ok(skeleton)
+    }
+
+    pub fn transform(&self) -> ShapeTransform {
+        ShapeTransform {
+            position: Vec2::from(self.position),
+            rotation: self.rotation,
+            scale: Vec2::from(self.scale),
+            anchor: Vec2::ZERO,
+        }
+    }
+
+    pub fn rest_pose(&self) -> PropertySnapshot {
+        let mut rest = PropertySnapshot::new();
+        rest.set(PropertyValue::Position(Vec2::from(self.position)));
+        rest.set(PropertyValue::Rotation(self.rotation));
+        rest.set(PropertyValue::Scale(Vec2::from(self.scale)));
+        rest.set(PropertyValue::Opacity(self.opacity));
+        rest
+    }
+
+    pub fn animator(&self, index: usize) -> Result<Option<Box<dyn Animator>>, ImportError> {
+        layer_stack(self.rest_pose(), index, &self.animations, self.state_machine.as_ref())
+    }
+
+    /// The shapes merged into one mesh and bound to the skeleton. Each shape's
+    /// transform and color are baked into its vertices; its opacity is not, use
+    /// the skin's.
+    pub fn mesh(&self, gradients: &BTreeMap<String, SceneGradient>) -> Result<SkinnedMesh, ImportError> {
+        let mut geometry = VertexBuffers::new();
+        for shape in &self.shapes {
+            for mesh in shape.meshes(gradients)? {
+                let matrix = mesh.model_matrix();
+                let tint = mesh.color().to_vec3();
+                let base = geometry.vertices.len();
+                if base + mesh.geometry().vertices.len() > u16::MAX as usize {
+                    return Err(ImportError::Format("skin has too many vertices".to_string()));
+                }
+                geometry.vertices.extend(mesh.geometry().vertices.iter().map(|v| {
+                    let position = matrix.transform_point3(glam::Vec3::from(v.position));
+                    IVertex {
+                        position: position.to_array(),
+                        color: (glam::Vec3::from(v.color) * tint).to_array(),
+                    }
+                }));
+                geometry.indices.extend(mesh.geometry().indices.iter().map(|i| i + base as u16));
+            }
+        }
+        let mut skin = SkinnedMesh::bind(&subdivide(&geometry, self.resolution), self.skeleton()?);
+        skin.transform = self.transform();
+        skin.set_opacity(self.opacity);
+        Ok(skin)
+    }
+}
+
+/// An SVG or Lottie file placed in the scene.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub path: String,
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 2],
+}
+
+impl SceneFile {
+    pub fn new(path: &str) -> Self {
+        SceneFile {
+            path: path.to_string(),
+            position: [0.0, 0.0],
+            rotation: 0.0,
+            scale: default_scale(),
+        }
+    }
+
+    pub fn placement(&self) -> Mat4 {
+        ShapeTransform {
+            position: Vec2::from(self.position),
+            rotation: self.rotation,
+            scale: Vec2::from(self.scale),
+            anchor: Vec2::ZERO,
+        }
+        .matrix()
+    }
+
+    /// Applies the placement to something that positions itself, like a composition.
+    pub fn place(&self, shape: &mut dyn Shape) {
+        shape.set_position(Vec2::from(self.position));
+        shape.set_rotation(self.rotation);
+        shape.set_scale(Vec2::from(self.scale));
+    }
+}
+
+/// An easing name such as `"ease-in-out"` or `"back-out"`, `{ "cubic_bezier": [x1, y1, x2, y2] }`
+/// or `{ "steps": n, "position": "jump-end" }`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SceneEasing {
+    Named(String),
+    CubicBezier { cubic_bezier: [f32; 4] },
+    Steps {
+        steps: u32,
+        #[serde(default)]
+        position: Option<String>,
+    },
+}
+
+impl Default for SceneEasing {
+    fn default() -> Self {
+        SceneEasing::Named("linear".to_string())
+    }
+}
+
+impl SceneEasing {
+    pub fn is_linear(&self) -> bool {
+        matches!(self, SceneEasing::Named(name) if name == "linear")
+    }
+
+    pub fn to_easing(&self) -> Result<Easing, ImportError> {
+        let unknown = |kind: &str, name: &str| ImportError::Format(format!("unknown {} '{}'", kind, name));
+        match self {
+            SceneEasing::CubicBezier { cubic_bezier: [x1, y1, x2, y2] } => Ok(Easing::cubic_bezier(*x1, *y1, *x2, *y2)),
+            SceneEasing::Steps { steps, position } => {
+                let position = match position.as_deref() {
+                    None | Some("jump-end") | Some("end") => StepPosition::JumpEnd,
+                    Some("jump-start") | Some("start") => StepPosition::JumpStart,
+                    Some("jump-none") => StepPosition::JumpNone,
+                    Some("jump-both") => StepPosition::JumpBoth,
+                    Some(other) => return Err(unknown("step position", other)),
+                };
+                Ok(Easing::steps(*steps, position))
+            }
+            SceneEasing::Named(name) => {
+                match name.as_str() {
+                    "linear" => return Ok(Easing::Linear),
+                    "ease" => return Ok(Easing::CubicBezier(CubicBezier::EASE)),
+                    "ease-in" => return Ok(Easing::CubicBezier(CubicBezier::EASE_IN)),
+                    "ease-out" => return Ok(Easing::CubicBezier(CubicBezier::EASE_OUT)),
+                    "ease-in-out" => return Ok(Easing::CubicBezier(CubicBezier::EASE_IN_OUT)),
+                    _ => {}
+                }
+                // 其余名字形如 "quad-in", "bounce-in-out"
+                let (curve, mode) = name.split_once('-').ok_or_else(|| unknown("easing", name))?;
+                let mode = match mode {
+                    "in" => EaseMode::In,
+                    "out" => EaseMode::Out,
+                    "in-out" => EaseMode::InOut,
+                    _ => return Err(unknown("easing", name)),
+                };
+                Ok(match curve {
+                    "quad" => Easing::Quad(mode),
+                    "cubic" => Easing::Cubic(mode),
+                    "quart" => Easing::Quart(mode),
+                    "expo" => Easing::Expo(mode),
+                    "back" => Easing::Back(mode),
+                    "elastic" => Easing::Elastic(mode),
+                    "bounce" => Easing::Bounce(mode),
+                    _ => return Err(unknown("easing", name)),
+                })
+            }
+        }
+    }
+}
+
+/// A number, or an array for vector and color properties.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SceneValue {
+    Scalar(f32),
+    Vector(Vec<f32>),
+}
+
+impl SceneValue {
+    fn scalar(&self, property: Property) -> Result<f32, ImportError> {
+        match self {
+            SceneValue::Scalar(v) => Ok(*v),
+            SceneValue::Vector(_) => Err(ImportError::Format(format!("{:?} keyframes take a number", property))),
+        }
+    }
+
+    fn components<const N: usize>(&self, property: Property) -> Result<[f32; N], ImportError> {
+        match self {
+            // 缩放可以只写一个数, 表示等比缩放
+            SceneValue::Scalar(v) if property == Property::Scale => Ok([*v; N]),
+            SceneValue::Vector(v) if v.len() == N => Ok(std::array::from_fn(|i| v[i])),
+            _ => Err(ImportError::Format(format!("{:?} keyframes take {} numbers", property, N))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneKeyframe {
+    pub time: f32,
+    pub value: SceneValue,
+    /// Easing of the segment that starts at this keyframe.
+    #[serde(default)]
+    pub easing: SceneEasing,
+    /// Keep the value until the next keyframe instead of interpolating.
+    #[serde(default)]
+    pub hold: bool,
+}
+
+impl SceneKeyframe {
+    fn interpolation(&self) -> Result<Interpolation, ImportError> {
+        Ok(if self.hold {
+            Interpolation::Hold
+        } else if self.easing.is_linear() {
+            Interpolation::Linear
+        } else {
+            Interpolation::Eased(self.easing.to_easing()?)
+        })
+    }
+}
+
+fn track<T: Animatable>(
+    keyframes: &[SceneKeyframe],
+    value: impl Fn(&SceneValue) -> Result<T, ImportError>,
+) -> Result<Track<T>, ImportError> {
+    let mut track = Track::new();
+    for keyframe in keyframes {
+        track.add_keyframe(Keyframe::new(keyframe.time, value(&keyframe.value)?, keyframe.interpolation()?));
+    }
+    Ok(track)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneTrack {
+    pub property: Property,
+    pub keyframes: Vec<SceneKeyframe>,
+}
+
+impl SceneTrack {
+    pub fn to_track(&self) -> Result<PropertyTrack, ImportError> {
+        let p = self.property;
+        let keys = &self.keyframes;
+        let scalar = |v: &SceneValue| v.scalar(p);
+        let vec2 = |v: &SceneValue| v.components::<2>(p).map(Vec2::from);
+        Ok(match p {
+            Property::Position => PropertyTrack::Position(track(keys, vec2)?),
+            Property::Rotation => PropertyTrack::Rotation(track(keys, scalar)?),
+            Property::Scale => PropertyTrack::Scale(track(keys, vec2)?),
+            Property::Color => PropertyTrack::Color(track(keys, |v| v.components::<3>(p).map(color))?),
+            Property::Opacity => PropertyTrack::Opacity(track(keys, scalar)?),
+            Property::Anchor => PropertyTrack::Anchor(track(keys, vec2)?),
+            Property::StrokeWidth => PropertyTrack::StrokeWidth(track(keys, scalar)?),
+            Property::DashOffset => PropertyTrack::DashOffset(track(keys, scalar)?),
+            Property::TrimStart => PropertyTrack::TrimStart(track(keys, scalar)?),
+            Property::TrimEnd => PropertyTrack::TrimEnd(track(keys, scalar)?),
+            Property::Frame => PropertyTrack::Frame(track(keys, scalar)?),
+            Property::ShadowColor => PropertyTrack::ShadowColor(track(keys, |v| v.components::<3>(p).map(color))?),
+            Property::ShadowOffset => PropertyTrack::ShadowOffset(track(keys, vec2)?),
+            Property::ShadowBlur => PropertyTrack::ShadowBlur(track(keys, scalar)?),
+            Property::ShadowOpacity => PropertyTrack::ShadowOpacity(track(keys, scalar)?),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SceneAnimationKind {
+    /// Spins forever at `speed` radians per second.
+    Rotation { speed: f32 },
+    Keyframes { tracks: Vec<SceneTrack> },
+    /// Moves along SVG path data, given in view space.
+    FollowPath {
+        data: String,
+        duration: f32,
+        #[serde(default)]
+        easing: SceneEasing,
+        /// Turns to face along the path, plus this many radians.
+        #[serde(default)]
+        auto_orient: Option<f32>,
+    },
+    /// Smooth noise on one property; use `"blend": "additive"` to jitter around
+    /// the value the layers below produce.
+    Wiggle {
+        property: Property,
+        frequency: f32,
+        amplitude: f32,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default)]
+        seed: u32,
+    },
+    /// Computes one property from expressions such as `"sin(time * 2) * 0.3"`,
+    /// one per component; see `ExpressionAnimator` for the variables available.
+    Expression {
+        property: Property,
+        value: SceneExpression,
+    },
+    /// Steps a sprite through atlas frames; `durations` overrides how long each
+    /// entry of `frames` shows, in seconds.
+    Flipbook {
+        frames: Vec<usize>,
+        #[serde(default = "default_fps")]
+        fps: f32,
+        #[serde(default)]
+        durations: Vec<f32>,
+        #[serde(default, rename = "loop")]
+        loop_mode: LoopMode,
+    },
+}
+
+/// A single expression, or one per component.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SceneExpression {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl SceneExpression {
+    pub fn sources(&self) -> Vec<&str> {
+        match self {
+            SceneExpression::One(source) => vec![source.as_str()],
+            SceneExpression::Many(sources) => sources.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+fn default_fps() -> f32 {
+    12.0
+}
+
+fn default_octaves() -> u32 {
+    1
+}
+
+/// `"infinite"` or a total number of play-throughs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SceneRepeat {
+    Count(u32),
+    Named(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneAnimation {
+    #[serde(flatten)]
+    pub kind: SceneAnimationKind,
+    /// How the animation combines with the ones listed before it on the same target.
+    #[serde(default)]
+    pub blend: BlendMode,
+    #[serde(default = "default_one")]
+    pub weight: f32,
+    #[serde(default)]
+    pub delay: f32,
+    #[serde(default)]
+    pub repeat: Option<SceneRepeat>,
+    #[serde(default)]
+    pub yoyo: bool,
+}
+
+impl SceneAnimation {
+    pub fn build(&self) -> Result<Box<dyn Animator>, ImportError> {
+        let animator: Box<dyn Animator> = match &self.kind {
+            SceneAnimationKind::Rotation { speed } => Box::new(RotationAnimator::new(*speed)),
+            SceneAnimationKind::Keyframes { tracks } => {
+                let mut animator = KeyframeAnimator::new();
+                for track in tracks {
+                    animator.add_track(track.to_track()?);
+                }
+                Box::new(animator)
+            }
+            SceneAnimationKind::FollowPath { data, duration, easing, auto_orient } => {
+                let mut animator = PathFollowAnimator::new(&path_data(data), *duration).with_easing(easing.to_easing()?);
+                if let Some(offset) = auto_orient {
+                    animator = animator.with_auto_orient(*offset);
+                }
+                Box::new(animator)
+            }
+            SceneAnimationKind::Wiggle { property, frequency, amplitude, octaves, seed } => Box::new(
+                WiggleAnimator::new(*property, *frequency, *amplitude)
+                    .with_octaves(*octaves)
+                    .with_seed(*seed),
+            ),
+            SceneAnimationKind::Expression { property, value } => Box::new(
+                ExpressionAnimator::parse(*property, &value.sources())
+                    .map_err(|e| ImportError::Format(format!("expression: {}", e)))?,
+            ),
+            SceneAnimationKind::Flipbook { frames, fps, durations, loop_mode } => Box::new(
+                FlipbookAnimator::new(frames.clone(), *fps)
+                    .with_durations(durations)
+                    .with_loop_mode(*loop_mode),
+            ),
+        };
+        let repeat = match &self.repeat {
+            None => None,
+            Some(SceneRepeat::Count(count)) => Some(Repeat::Count(*count)),
+            Some(SceneRepeat::Named(name)) if name == "infinite" => Some(Repeat::Infinite),
+            Some(SceneRepeat::Named(name)) => return Err(ImportError::Format(format!("unknown repeat '{}'", name))),
+        };
+        if self.delay == 0.0 && repeat.is_none() && !self.yoyo {
+            return Ok(animator);
+        }
+        let mut timeline = Timeline::sequence(vec![animator]).with_delay(self.delay).with_yoyo(self.yoyo);
+        if let Some(repeat) = repeat {
+            timeline = timeline.with_repeat(repeat);
+        }
+        Ok(Box::new(timeline))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneState {
+    pub name: String,
+    /// Played together while the state is active.
+    #[serde(default)]
+    pub animations: Vec<SceneAnimation>,
+}
+
+/// `{ "trigger": name }`, `{ "bool": name, "value": true }`, `{ "number": name, "greater": x }`
+/// or `{ "number": name, "less": x }`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SceneCondition {
+    Trigger { trigger: String },
+    Bool {
+        bool: String,
+        #[serde(default = "default_true")]
+        value: bool,
+    },
+    Greater { number: String, greater: f32 },
+    Less { number: String, less: f32 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneTransition {
+    /// Leave out to allow the transition from any state.
+    #[serde(default)]
+    pub from: Option<String>,
+    pub to: String,
+    #[serde(default)]
+    pub conditions: Vec<SceneCondition>,
+    #[serde(default)]
+    pub exit_time: Option<f32>,
+    #[serde(default)]
+    pub fade: f32,
+}
+
+/// The first state is the initial one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneStateMachine {
+    pub states: Vec<SceneState>,
+    #[serde(default)]
+    pub transitions: Vec<SceneTransition>,
+}
+
+impl SceneStateMachine {
+    pub fn build(&self) -> Result<StateMachine, ImportError> {
+        let mut machine = StateMachine::new();
+        for state in &self.states {
+            let animators = state
+                .animations
+                .iter()
+                .map(SceneAnimation::build)
+                .collect::<Result<Vec<_>, _>>()?;
+            machine.add_state(&state.name, Box::new(Timeline::parallel(animators)));
+        }
+        let known = |name: &str| {
+            if self.states.iter().any(|state| state.name == name) {
+                Ok(())
+            } else {
+                Err(ImportError::Format(format!("unknown state '{}'", name)))
+            }
+        };
+        for desc in &self.transitions {
+            known(&desc.to)?;
+            let mut transition = match &desc.from {
+                Some(from) => {
+                    known(from)?;
+                    Transition::new(from, &desc.to)
+                }
+                None => Transition::from_any(&desc.to),
+            };
+            for condition in &desc.conditions {
+                transition = transition.with_condition(match condition {
+                    SceneCondition::Trigger { trigger } => Condition::Trigger(trigger.clone()),
+                    SceneCondition::Bool { bool, value } => Condition::Bool(bool.clone(), *value),
+                    SceneCondition::Greater { number, greater } => Condition::Greater(number.clone(), *greater),
+                    SceneCondition::Less { number, less } => Condition::Less(number.clone(), *less),
+                });
+            }
+            transition.exit_time = desc.exit_time;
+            machine.add_transition(transition.with_fade(desc.fade));
+        }
+        Ok(machine)
+    }
+}
+
+/// Stacks a target's animations in file order with its state machine on top, so
+/// the state machine overrides whatever it drives.
+fn layer_stack(
+    rest: PropertySnapshot,
+    index: usize,
+    animations: &[SceneAnimation],
+    state_machine: Option<&SceneStateMachine>,
+) -> Result<Option<Box<dyn Animator>>, ImportError> {
+    if animations.is_empty() && state_machine.is_none() {
+        return Ok(None);
+    }
+    let variables = snapshot_variables(&rest);
+    let mut stack = LayerStack::new().with_rest_pose(rest);
+    for animation in animations {
+        let layer = AnimationLayer {
+            animator: animation.build()?,
+            mode: animation.blend,
+            weight: animation.weight,
+        };
+        stack.add_layer(layer);
+    }
+    if let Some(machine) = state_machine {
+        stack.add_layer(AnimationLayer::new(Box::new(machine.build()?)));
+    }
+    // 表达式可以读取目标的序号和静态属性
+    stack.set_parameter("index", Parameter::Number(index as f32));
+    for (name, value) in variables {
+        stack.set_parameter(name, Parameter::Number(value));
+    }
+    Ok(Some(Box::new(stack)))
+}
+
+impl Scene {
+    pub fn load(path: impl AsRef<FsPath>) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        let mut scene = Self::from_json(&std::fs::read_to_string(path)?)?;
+        scene.base_dir = path.parent().map(FsPath::to_path_buf);
+        Ok(scene)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ImportError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, ImportError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Loads a scene file, or wraps a bare `.svg` or Lottie file in the default scene.
+    pub fn open(path: impl AsRef<FsPath>) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        let name = path.to_string_lossy().into_owned();
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")) {
+            let mut scene = Scene::default();
+            scene.svgs.push(SceneFile::new(&name));
+            return Ok(scene);
+        }
+        let value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        // Lottie 文件顶层一定有 layers
+        if value.get("layers").is_some() {
+            let mut scene = Scene::default();
+            scene.compositions.push(SceneFile::new(&name));
+            return Ok(scene);
+        }
+        let mut scene: Scene = serde_json::from_value(value)?;
+        scene.base_dir = path.parent().map(FsPath::to_path_buf);
+        Ok(scene)
+    }
+
+    /// Resolves a path written in the scene file.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        match &self.base_dir {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        }
+    }
+
+    /// Loads every atlas image, keyed by atlas name.
+    pub fn atlases(&self) -> Result<BTreeMap<String, Rc<TextureAtlas>>, ImportError> {
+        let mut atlases = BTreeMap::new();
+        for (name, desc) in &self.atlases {
+            let image = Image::load(self.resolve(&desc.image))?;
+            atlases.insert(name.clone(), Rc::new(desc.atlas(image)));
+        }
+        Ok(atlases)
+    }
+
+    pub fn clear_color(&self) -> wgpu::Color {
+        let [r, g, b, a] = self.background.map(f64::from);
+        wgpu::Color { r, g, b, a }
+    }
+}
+
+fn color(c: [f32; 3]) -> Color {
+    Color::new(c[0], c[1], c[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_scene_builds() {
+        let scene = Scene::load("src/res/scenes/demo.json").unwrap();
+        for (index, desc) in scene.instances.iter().enumerate() {
+            desc.animator(index).unwrap();
+        }
+        for (index, desc) in scene.shapes.iter().enumerate() {
+            assert!(!desc.meshes(&scene.gradients).unwrap().is_empty());
+            desc.animator(index).unwrap();
+        }
+        let atlases = scene.atlases().unwrap();
+        for desc in &scene.sprites {
+            desc.sprite(&atlases).unwrap();
+        }
+        for desc in &scene.skins {
+            desc.mesh(&scene.gradients).unwrap();
+        }
+        for desc in &scene.particles {
+            desc.emitter().unwrap();
+        }
+    }
+
+    #[test]
+    fn default_scene_round_trips() {
+        let json = Scene::default().to_json().unwrap();
+        let scene = Scene::from_json(&json).unwrap();
+        assert_eq!(scene.instances.len(), 1);
+        assert_eq!(scene.instances[0].position, [0.0, 1.0, 0.0]);
+        assert!(scene.instances[0].animator(0).unwrap().is_some());
+    }
+
+    #[test]
+    fn gradient_kind_is_flattened() {
+        let json = r#"{
+            "type": "radial",
+            "center": [0.1, 0.2],
+            "radius": 0.5,
+            "spread": "reflect",
+            "stops": [{ "offset": 0.0, "color": [1.0, 0.0, 0.0] }, { "offset": 1.0, "color": [0.0, 0.0, 1.0], "opacity": 0.5 }]
+        }"#;
+        let gradient: SceneGradient = serde_json::from_str(json).unwrap();
+        let round_trip: SceneGradient = serde_json::from_str(&serde_json::to_string(&gradient).unwrap()).unwrap();
+        for desc in [gradient, round_trip] {
+            let gradient = desc.to_gradient();
+            match gradient.kind {
+                GradientKind::Radial { center, radius, focal } => {
+                    assert_eq!(center, Vec2::new(0.1, 0.2));
+                    assert_eq!(radius, 0.5);
+                    assert_eq!(focal, center);
+                }
+                _ => panic!("expected a radial gradient"),
+            }
+            assert_eq!(gradient.spread, SpreadMode::Reflect);
+            assert_eq!(gradient.stops.len(), 2);
+            assert_eq!(desc.stops[0].opacity, 1.0);
+            assert_eq!(desc.stops[1].opacity, 0.5);
+        }
+    }
+
+    #[test]
+    fn unknown_gradient_is_an_error() {
+        let shape: SceneShape =
+            serde_json::from_str(r#"{ "type": "circle", "radius": 0.5, "fill": { "gradient": "missing" } }"#).unwrap();
+        assert!(matches!(shape.meshes(&BTreeMap::new()), Err(ImportError::Format(_))));
+    }
+
+    #[test]
+    fn unknown_geometry_is_an_error() {
+        assert!(Scene::from_json(r#"{ "shapes": [{ "type": "blob" }] }"#).is_err());
+    }
+
+    #[test]
+    fn relative_paths_resolve_against_the_scene_file() {
+        let scene = Scene::load("src/res/scenes/demo.json").unwrap();
+        assert_eq!(scene.resolve("a.png"), PathBuf::from("src/res/scenes/a.png"));
+        assert_eq!(Scene::default().resolve("a.png"), PathBuf::from("a.png"));
+    }
+}
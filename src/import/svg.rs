@@ -0,0 +1,792 @@
+use std::str::FromStr;
+use glam::{Affine2, Mat4, Vec2, Vec4};
+use lyon::algorithms::aabb::bounding_box;
+use lyon::math::{point, vector, Angle, Box2D, Point};
+use lyon::path::{FillRule, Path, Polygon, Winding};
+use roxmltree::{Document, Node};
+use svgtypes::{Length, LengthUnit, PointsParser, SimplePathSegment, SimplifyingPathParser};
+use crate::shapes::mesh::MeshShape;
+use crate::shapes::paint::{Gradient, GradientKind, GradientStop, Paint, SpreadMode};
+use crate::shapes::stroke::{LineCap, LineJoin, StrokeStyle};
+use crate::shapes::{Color, Shape};
+use super::{fit_to_view, ImportError};
+
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+// 渐变 href 链的最大长度, 防止循环引用
+const MAX_HREF_DEPTH: usize = 16;
+// 圆弧的三次贝塞尔近似系数
+const KAPPA: f32 = 0.552_284_8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgFill {
+    pub paint: Paint,
+    pub opacity: f32,
+    pub rule: FillRule,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgStroke {
+    pub paint: Paint,
+    pub opacity: f32,
+    pub style: StrokeStyle,
+}
+
+/// One painted element. `path` is in the element's own coordinates and `transform`
+/// carries it, through every enclosing group, into the document's user space.
+#[derive(Clone, Debug)]
+pub struct SvgShape {
+    pub id: Option<String>,
+    pub path: Path,
+    pub transform: Affine2,
+    pub fill: Option<SvgFill>,
+    pub stroke: Option<SvgStroke>,
+    /// Product of the element's and its ancestors' `opacity`.
+    pub opacity: f32,
+}
+
+fn affine_to_mat4(affine: Affine2) -> Mat4 {
+    let (m, t) = (affine.matrix2, affine.translation);
+    Mat4::from_cols(
+        Vec4::new(m.x_axis.x, m.x_axis.y, 0.0, 0.0),
+        Vec4::new(m.y_axis.x, m.y_axis.y, 0.0, 0.0),
+        Vec4::Z,
+        Vec4::new(t.x, t.y, 0.0, 1.0),
+    )
+}
+
+fn affine_to_lyon(affine: Affine2) -> lyon::math::Transform {
+    let (m, t) = (affine.matrix2, affine.translation);
+    lyon::math::Transform::new(m.x_axis.x, m.x_axis.y, m.y_axis.x, m.y_axis.y, t.x, t.y)
+}
+
+impl SvgShape {
+    /// The outline in document user space.
+    pub fn transformed_path(&self) -> Path {
+        self.path.clone().transformed(&affine_to_lyon(self.transform))
+    }
+
+    /// Fill and stroke meshes, fill first, positioned in document user space.
+    pub fn meshes(&self) -> Vec<MeshShape> {
+        let mut meshes = vec![];
+        if let Some(fill) = &self.fill {
+            let mesh = MeshShape::fill(&self.path, fill.rule, Color::WHITE).with_paint(fill.paint.clone());
+            meshes.push((mesh, fill.opacity));
+        }
+        if let Some(stroke) = &self.stroke {
+            let mesh = MeshShape::stroke(self.path.clone(), stroke.style.clone(), Color::WHITE).with_paint(stroke.paint.clone());
+            meshes.push((mesh, stroke.opacity));
+        }
+
+        let parent = affine_to_mat4(self.transform);
+        meshes
+            .into_iter()
+            .map(|(mut mesh, opacity)| {
+                mesh.set_parent_transform(parent);
+                mesh.set_opacity(opacity * self.opacity);
+                mesh
+            })
+            .collect()
+    }
+}
+
+/// A parsed SVG file: its view box and every painted element in paint order.
+///
+/// Supports `path`, `rect`, `circle`, `ellipse`, `line`, `polyline` and `polygon`
+/// inside nested `g`/`svg` elements with transforms, solid and gradient fills and
+/// strokes given as attributes or in `style`. Text, images, `use`, clipping,
+/// masks and CSS stylesheets are skipped.
+#[derive(Clone, Debug)]
+pub struct SvgDocument {
+    pub view_box_origin: Vec2,
+    pub view_box_size: Vec2,
+    pub shapes: Vec<SvgShape>,
+}
+
+impl SvgDocument {
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, ImportError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ImportError> {
+        let document = Document::parse(text).map_err(|e| ImportError::Format(e.to_string()))?;
+        let root = document.root_element();
+        if root.tag_name().name() != "svg" {
+            return Err(ImportError::Format(format!("root element is <{}>, not <svg>", root.tag_name().name())));
+        }
+
+        let view_box = root.attribute("viewBox").and_then(|v| svgtypes::ViewBox::from_str(v).ok());
+        let size = |name: &str, fallback: f64| {
+            root.attribute(name)
+                .and_then(|v| Length::from_str(v).ok())
+                .filter(|l| l.unit != LengthUnit::Percent)
+                .map_or(fallback as f32, |l| to_user_units(l, 0.0))
+        };
+        let (view_box_origin, view_box_size) = match view_box {
+            Some(v) => (Vec2::new(v.x as f32, v.y as f32), Vec2::new(v.w as f32, v.h as f32)),
+            None => (Vec2::ZERO, Vec2::new(size("width", 100.0), size("height", 100.0))),
+        };
+
+        let mut parser = Parser {
+            document: &document,
+            viewport: view_box_size,
+            shapes: vec![],
+        };
+        let style = Style::default().inherit(root, parser.diagonal());
+        let opacity = number_attribute(root, "opacity").unwrap_or(1.0);
+        parser.walk(root, &style, Affine2::IDENTITY, opacity);
+
+        Ok(SvgDocument {
+            view_box_origin,
+            view_box_size,
+            shapes: parser.shapes,
+        })
+    }
+
+    /// Fits the view box into the renderer's view, y up.
+    pub fn fit_transform(&self) -> Mat4 {
+        fit_to_view(self.view_box_origin, self.view_box_size)
+    }
+
+    /// Every shape's meshes, in paint order, fitted into the renderer's view.
+    pub fn meshes(&self) -> Vec<MeshShape> {
+        let fit = self.fit_transform();
+        self.shapes
+            .iter()
+            .flat_map(SvgShape::meshes)
+            .map(|mut mesh| {
+                mesh.set_parent_transform(fit * mesh.model_matrix());
+                mesh
+            })
+            .collect()
+    }
+
+    /// All outlines merged into one path in document user space, e.g. to use an
+    /// icon's silhouette as a motion path or morph target.
+    pub fn outline(&self) -> Path {
+        let paths = self.shapes.iter().map(SvgShape::transformed_path).collect::<Vec<_>>();
+        let mut builder = Path::builder();
+        builder.extend_from_paths(&paths.iter().map(Path::as_slice).collect::<Vec<_>>());
+        builder.build()
+    }
+}
+
+fn to_user_units(length: Length, reference: f32) -> f32 {
+    let n = length.number as f32;
+    match length.unit {
+        LengthUnit::None | LengthUnit::Px => n,
+        LengthUnit::Percent => n * reference / 100.0,
+        LengthUnit::In => n * 96.0,
+        LengthUnit::Cm => n * 96.0 / 2.54,
+        LengthUnit::Mm => n * 96.0 / 25.4,
+        LengthUnit::Pt => n * 4.0 / 3.0,
+        LengthUnit::Pc => n * 16.0,
+        LengthUnit::Em => n * 16.0,
+        LengthUnit::Ex => n * 8.0,
+    }
+}
+
+/// A presentation property, from the `style` attribute if it's there, else from
+/// the attribute of the same name.
+fn property<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    let from_style = node.attribute("style").and_then(|style| {
+        style.split(';').find_map(|declaration| {
+            let (key, value) = declaration.split_once(':')?;
+            (key.trim() == name).then(|| value.trim())
+        })
+    });
+    from_style.or_else(|| node.attribute(name)).map(str::trim)
+}
+
+fn number_attribute(node: Node, name: &str) -> Option<f32> {
+    let value = property(node, name)?;
+    let length = Length::from_str(value).ok()?;
+    // opacity 一类的属性也可以写成百分比
+    Some(match length.unit {
+        LengthUnit::Percent => length.number as f32 / 100.0,
+        _ => length.number as f32,
+    })
+}
+
+fn href<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.attribute((XLINK_NS, "href"))
+        .or_else(|| node.attribute("href"))
+        .and_then(|href| href.strip_prefix('#'))
+}
+
+fn parse_color(value: &str) -> Option<(Color, f32)> {
+    let color = svgtypes::Color::from_str(value).ok()?;
+    let channel = |c: u8| c as f32 / 255.0;
+    Some((Color::new(channel(color.red), channel(color.green), channel(color.blue)), channel(color.alpha)))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PaintRef {
+    None,
+    Color(Color, f32),
+    CurrentColor,
+    /// A gradient id, with the color to use if it doesn't resolve.
+    Url(String, Option<(Color, f32)>),
+}
+
+impl PaintRef {
+    fn parse(value: &str) -> Option<Self> {
+        let paint = match svgtypes::Paint::from_str(value).ok()? {
+            svgtypes::Paint::None => PaintRef::None,
+            svgtypes::Paint::CurrentColor => PaintRef::CurrentColor,
+            svgtypes::Paint::Color(c) => {
+                PaintRef::Color(Color::new(c.red as f32 / 255.0, c.green as f32 / 255.0, c.blue as f32 / 255.0), c.alpha as f32 / 255.0)
+            }
+            svgtypes::Paint::FuncIRI(id, fallback) => {
+                let fallback = match fallback {
+                    Some(svgtypes::PaintFallback::Color(c)) => {
+                        Some((Color::new(c.red as f32 / 255.0, c.green as f32 / 255.0, c.blue as f32 / 255.0), c.alpha as f32 / 255.0))
+                    }
+                    _ => None,
+                };
+                PaintRef::Url(id.to_string(), fallback)
+            }
+            // inherit 由调用方处理, context-fill 等不支持
+            _ => return None,
+        };
+        Some(paint)
+    }
+}
+
+/// The inherited presentation properties in effect at an element.
+#[derive(Clone, Debug)]
+struct Style {
+    color: (Color, f32),
+    fill: PaintRef,
+    fill_opacity: f32,
+    fill_rule: FillRule,
+    stroke: PaintRef,
+    stroke_opacity: f32,
+    stroke_style: StrokeStyle,
+    visible: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            color: (Color::BLACK, 1.0),
+            fill: PaintRef::Color(Color::BLACK, 1.0),
+            fill_opacity: 1.0,
+            fill_rule: FillRule::NonZero,
+            stroke: PaintRef::None,
+            stroke_opacity: 1.0,
+            stroke_style: StrokeStyle {
+                width: 1.0,
+                miter_limit: 4.0,
+                ..Default::default()
+            },
+            visible: true,
+        }
+    }
+}
+
+impl Style {
+    /// This style with the properties `node` sets overridden. `diagonal` resolves
+    /// percentage stroke widths.
+    fn inherit(&self, node: Node, diagonal: f32) -> Style {
+        let mut style = self.clone();
+        let length = |name: &str| {
+            property(node, name)
+                .and_then(|v| Length::from_str(v).ok())
+                .map(|l| to_user_units(l, diagonal))
+        };
+
+        if let Some(color) = property(node, "color").and_then(parse_color) {
+            style.color = color;
+        }
+        if let Some(fill) = property(node, "fill").and_then(PaintRef::parse) {
+            style.fill = fill;
+        }
+        if let Some(opacity) = number_attribute(node, "fill-opacity") {
+            style.fill_opacity = opacity.clamp(0.0, 1.0);
+        }
+        match property(node, "fill-rule") {
+            Some("evenodd") => style.fill_rule = FillRule::EvenOdd,
+            Some("nonzero") => style.fill_rule = FillRule::NonZero,
+            _ => {}
+        }
+        if let Some(stroke) = property(node, "stroke").and_then(PaintRef::parse) {
+            style.stroke = stroke;
+        }
+        if let Some(opacity) = number_attribute(node, "stroke-opacity") {
+            style.stroke_opacity = opacity.clamp(0.0, 1.0);
+        }
+        if let Some(width) = length("stroke-width") {
+            style.stroke_style.width = width;
+        }
+        match property(node, "stroke-linecap") {
+            Some("butt") => style.stroke_style.cap = LineCap::Butt,
+            Some("round") => style.stroke_style.cap = LineCap::Round,
+            Some("square") => style.stroke_style.cap = LineCap::Square,
+            _ => {}
+        }
+        match property(node, "stroke-linejoin") {
+            Some("miter") | Some("miter-clip") | Some("arcs") => style.stroke_style.join = LineJoin::Miter,
+            Some("round") => style.stroke_style.join = LineJoin::Round,
+            Some("bevel") => style.stroke_style.join = LineJoin::Bevel,
+            _ => {}
+        }
+        if let Some(limit) = number_attribute(node, "stroke-miterlimit") {
+            style.stroke_style.miter_limit = limit;
+        }
+        match property(node, "stroke-dasharray") {
+            Some("none") => style.stroke_style.dashes.clear(),
+            Some(list) => {
+                let dashes = svgtypes::LengthListParser::from(list)
+                    .map(|l| l.map(|l| to_user_units(l, diagonal)))
+                    .collect::<Result<Vec<_>, _>>();
+                if let Ok(dashes) = dashes {
+                    style.stroke_style.dashes = dashes;
+                }
+            }
+            None => {}
+        }
+        if let Some(offset) = length("stroke-dashoffset") {
+            style.stroke_style.dash_offset = offset;
+        }
+        match property(node, "visibility") {
+            Some("hidden") | Some("collapse") => style.visible = false,
+            Some("visible") => style.visible = true,
+            _ => {}
+        }
+        style
+    }
+}
+
+fn parse_transform(value: Option<&str>) -> Affine2 {
+    value
+        .and_then(|t| svgtypes::Transform::from_str(t).ok())
+        .map_or(Affine2::IDENTITY, |t| {
+            Affine2::from_cols_array(&[t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32])
+        })
+}
+
+pub(crate) fn path_data(d: &str) -> Path {
+    let at = |x: f64, y: f64| point(x as f32, y as f32);
+    let mut builder = Path::builder();
+    let mut open = false;
+    let mut last = point(0.0, 0.0);
+    for segment in SimplifyingPathParser::from(d) {
+        // 按 SVG 规范, 出错时保留出错之前的部分
+        let segment = match segment {
+            Ok(segment) => segment,
+            Err(e) => {
+                log::warn!("invalid svg path data: {}", e);
+                break;
+            }
+        };
+        let to = match segment {
+            SimplePathSegment::MoveTo { x, y } => {
+                if open {
+                    builder.end(false);
+                }
+                last = at(x, y);
+                builder.begin(last);
+                open = true;
+                continue;
+            }
+            SimplePathSegment::ClosePath => {
+                if open {
+                    builder.end(true);
+                    open = false;
+                }
+                continue;
+            }
+            SimplePathSegment::LineTo { x, y } => at(x, y),
+            SimplePathSegment::CurveTo { x, y, .. } => at(x, y),
+            SimplePathSegment::Quadratic { x, y, .. } => at(x, y),
+        };
+        if !open {
+            builder.begin(last);
+            open = true;
+        }
+        match segment {
+            SimplePathSegment::CurveTo { x1, y1, x2, y2, .. } => builder.cubic_bezier_to(at(x1, y1), at(x2, y2), to),
+            SimplePathSegment::Quadratic { x1, y1, .. } => builder.quadratic_bezier_to(at(x1, y1), to),
+            _ => builder.line_to(to),
+        };
+        last = to;
+    }
+    if open {
+        builder.end(false);
+    }
+    builder.build()
+}
+
+pub(crate) fn rounded_rect(min: Point, size: Vec2, radii: Vec2) -> Path {
+    let (x, y, w, h) = (min.x, min.y, size.x, size.y);
+    let mut builder = Path::builder();
+    if radii.x <= 0.0 || radii.y <= 0.0 {
+        builder.add_rectangle(&Box2D::new(min, point(x + w, y + h)), Winding::Positive);
+        return builder.build();
+    }
+    let (rx, ry) = (radii.x, radii.y);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    builder.begin(point(x + rx, y));
+    builder.line_to(point(x + w - rx, y));
+    builder.cubic_bezier_to(point(x + w - rx + kx, y), point(x + w, y + ry - ky), point(x + w, y + ry));
+    builder.line_to(point(x + w, y + h - ry));
+    builder.cubic_bezier_to(point(x + w, y + h - ry + ky), point(x + w - rx + kx, y + h), point(x + w - rx, y + h));
+    builder.line_to(point(x + rx, y + h));
+    builder.cubic_bezier_to(point(x + rx - kx, y + h), point(x, y + h - ry + ky), point(x, y + h - ry));
+    builder.line_to(point(x, y + ry));
+    builder.cubic_bezier_to(point(x, y + ry - ky), point(x + rx - kx, y), point(x + rx, y));
+    builder.end(true);
+    builder.build()
+}
+
+struct Parser<'a, 'input> {
+    document: &'a Document<'input>,
+    viewport: Vec2,
+    shapes: Vec<SvgShape>,
+}
+
+impl<'a, 'input> Parser<'a, 'input> {
+    /// Percentage lengths that aren't horizontal or vertical resolve against this.
+    fn diagonal(&self) -> f32 {
+        (self.viewport.length_squared() / 2.0).sqrt()
+    }
+
+    fn length(&self, node: Node, name: &str, reference: f32) -> Option<f32> {
+        node.attribute(name)
+            .and_then(|v| Length::from_str(v).ok())
+            .map(|l| to_user_units(l, reference))
+    }
+
+    fn walk(&mut self, node: Node, style: &Style, transform: Affine2, opacity: f32) {
+        for child in node.children().filter(Node::is_element) {
+            if property(child, "display") == Some("none") {
+                continue;
+            }
+            let name = child.tag_name().name();
+            let container = matches!(name, "g" | "a" | "svg" | "switch");
+            let drawable = matches!(name, "path" | "rect" | "circle" | "ellipse" | "line" | "polyline" | "polygon");
+            if !container && !drawable {
+                if matches!(name, "text" | "image" | "use") {
+                    log::debug!("svg <{}> is not supported, skipped", name);
+                }
+                continue;
+            }
+
+            let style = style.inherit(child, self.diagonal());
+            let transform = transform * parse_transform(child.attribute("transform"));
+            let opacity = opacity * number_attribute(child, "opacity").unwrap_or(1.0).clamp(0.0, 1.0);
+            if container {
+                self.walk(child, &style, transform, opacity);
+            } else if let Some(path) = self.element_path(child) {
+                self.add_shape(child, path, &style, transform, opacity);
+            }
+        }
+    }
+
+    fn element_path(&self, node: Node) -> Option<Path> {
+        let (width, height, diagonal) = (self.viewport.x, self.viewport.y, self.diagonal());
+        let x = |name: &str| self.length(node, name, width).unwrap_or(0.0);
+        let y = |name: &str| self.length(node, name, height).unwrap_or(0.0);
+        let mut builder = Path::builder();
+        match node.tag_name().name() {
+            "path" => return node.attribute("d").map(path_data),
+            "rect" => {
+                let size = Vec2::new(x("width"), y("height"));
+                if size.x <= 0.0 || size.y <= 0.0 {
+                    return None;
+                }
+                // rx 和 ry 只给了一个时另一个取相同的值
+                let rx = self.length(node, "rx", width).filter(|r| *r > 0.0);
+                let ry = self.length(node, "ry", height).filter(|r| *r > 0.0);
+                let radii = match (rx, ry) {
+                    (Some(rx), Some(ry)) => Vec2::new(rx, ry),
+                    (Some(r), None) | (None, Some(r)) => Vec2::splat(r),
+                    (None, None) => Vec2::ZERO,
+                };
+                return Some(rounded_rect(point(x("x"), y("y")), size, radii.min(size / 2.0)));
+            }
+            "circle" => {
+                let r = self.length(node, "r", diagonal).unwrap_or(0.0);
+                if r <= 0.0 {
+                    return None;
+                }
+                builder.add_circle(point(x("cx"), y("cy")), r, Winding::Positive);
+            }
+            "ellipse" => {
+                let rx = self.length(node, "rx", width);
+                let ry = self.length(node, "ry", height);
+                let (rx, ry) = match (rx, ry) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(r), None) | (None, Some(r)) => (r, r),
+                    (None, None) => return None,
+                };
+                if rx <= 0.0 || ry <= 0.0 {
+                    return None;
+                }
+                builder.add_ellipse(point(x("cx"), y("cy")), vector(rx, ry), Angle::zero(), Winding::Positive);
+            }
+            "line" => {
+                builder.begin(point(x("x1"), y("y1")));
+                builder.line_to(point(x("x2"), y("y2")));
+                builder.end(false);
+            }
+            name @ ("polyline" | "polygon") => {
+                let points = PointsParser::from(node.attribute("points")?)
+                    .map(|(x, y)| point(x as f32, y as f32))
+                    .collect::<Vec<_>>();
+                if points.len() < 2 {
+                    return None;
+                }
+                builder.add_polygon(Polygon { points: &points, closed: name == "polygon" });
+            }
+            _ => return None,
+        }
+        Some(builder.build())
+    }
+
+    fn add_shape(&mut self, node: Node, path: Path, style: &Style, transform: Affine2, opacity: f32) {
+        if !style.visible || path.iter().next().is_none() {
+            return;
+        }
+        let bounds = bounding_box(path.iter());
+        // 直线没有面积, 忽略填充
+        let fill = if node.tag_name().name() == "line" {
+            None
+        } else {
+            self.resolve_paint(&style.fill, style.color, bounds).map(|(paint, alpha)| SvgFill {
+                paint,
+                opacity: alpha * style.fill_opacity,
+                rule: style.fill_rule,
+            })
+        };
+        let stroke = if style.stroke_style.width > 0.0 {
+            self.resolve_paint(&style.stroke, style.color, bounds).map(|(paint, alpha)| SvgStroke {
+                paint,
+                opacity: alpha * style.stroke_opacity,
+                style: style.stroke_style.clone(),
+            })
+        } else {
+            None
+        };
+        if fill.is_none() && stroke.is_none() {
+            return;
+        }
+        self.shapes.push(SvgShape {
+            id: node.attribute("id").map(str::to_string),
+            path,
+            transform,
+            fill,
+            stroke,
+            opacity,
+        });
+    }
+
+    fn resolve_paint(&self, paint: &PaintRef, current: (Color, f32), bounds: Box2D) -> Option<(Paint, f32)> {
+        match paint {
+            PaintRef::None => None,
+            PaintRef::Color(color, alpha) => Some((Paint::Solid(*color), *alpha)),
+            PaintRef::CurrentColor => Some((Paint::Solid(current.0), current.1)),
+            PaintRef::Url(id, fallback) => match self.gradient(id, bounds) {
+                Some(gradient) if gradient.stops.len() == 1 => {
+                    let stop = gradient.stops[0];
+                    Some((Paint::Solid(stop.color), stop.opacity))
+                }
+                Some(gradient) if gradient.stops.is_empty() => None,
+                Some(gradient) => Some((Paint::Gradient(gradient), 1.0)),
+                None => fallback.map(|(color, alpha)| (Paint::Solid(color), alpha)),
+            },
+        }
+    }
+
+    /// Resolves a `linearGradient` or `radialGradient`, following `href` for
+    /// attributes and stops the element doesn't set itself.
+    fn gradient(&self, id: &str, bounds: Box2D) -> Option<Gradient> {
+        let find = |id: &str| {
+            self.document
+                .descendants()
+                .find(|n| n.attribute("id") == Some(id))
+                .filter(|n| matches!(n.tag_name().name(), "linearGradient" | "radialGradient"))
+        };
+        let mut chain = vec![find(id)?];
+        while let Some(next) = href(chain[chain.len() - 1]).and_then(find) {
+            if chain.len() >= MAX_HREF_DEPTH || chain.contains(&next) {
+                break;
+            }
+            chain.push(next);
+        }
+        let attribute = |name: &str| chain.iter().find_map(|n| n.attribute(name));
+
+        let bounding_box_units = attribute("gradientUnits") != Some("userSpaceOnUse");
+        let (width, height, diagonal) = if bounding_box_units {
+            (1.0, 1.0, 1.0)
+        } else {
+            (self.viewport.x, self.viewport.y, self.diagonal())
+        };
+        let coordinate = |name: &str, default: &str, reference: f32| {
+            let value = attribute(name).unwrap_or(default);
+            Length::from_str(value).map_or(0.0, |l| to_user_units(l, reference))
+        };
+
+        let kind = if chain[0].tag_name().name() == "linearGradient" {
+            GradientKind::Linear {
+                start: Vec2::new(coordinate("x1", "0%", width), coordinate("y1", "0%", height)),
+                end: Vec2::new(coordinate("x2", "100%", width), coordinate("y2", "0%", height)),
+            }
+        } else {
+            let center = Vec2::new(coordinate("cx", "50%", width), coordinate("cy", "50%", height));
+            let focal = Vec2::new(
+                attribute("fx").map_or(center.x, |_| coordinate("fx", "50%", width)),
+                attribute("fy").map_or(center.y, |_| coordinate("fy", "50%", height)),
+            );
+            GradientKind::Radial { center, radius: coordinate("r", "50%", diagonal), focal }
+        };
+
+        let mut gradient_transform = parse_transform(attribute("gradientTransform"));
+        if bounding_box_units {
+            let (min, size) = (bounds.min, bounds.size());
+            gradient_transform = Affine2::from_scale_angle_translation(Vec2::new(size.width, size.height), 0.0, Vec2::new(min.x, min.y))
+                * gradient_transform;
+        }
+
+        let stops = chain
+            .iter()
+            .map(|n| gradient_stops(*n))
+            .find(|stops| !stops.is_empty())
+            .unwrap_or_default();
+        let spread = match attribute("spreadMethod") {
+            Some("repeat") => SpreadMode::Repeat,
+            Some("reflect") => SpreadMode::Reflect,
+            _ => SpreadMode::Pad,
+        };
+        Some(Gradient {
+            kind,
+            stops,
+            spread,
+            transform: gradient_transform,
+        })
+    }
+}
+
+fn gradient_stops(node: Node) -> Vec<GradientStop> {
+    let mut stops: Vec<GradientStop> = vec![];
+    for stop in node.children().filter(|n| n.tag_name().name() == "stop") {
+        // 偏移量不能比前一个 stop 小
+        let previous = stops.last().map_or(0.0, |s| s.offset);
+        let offset = number_attribute(stop, "offset").unwrap_or(0.0).clamp(0.0, 1.0).max(previous);
+        let (color, alpha) = property(stop, "stop-color").and_then(parse_color).unwrap_or((Color::BLACK, 1.0));
+        let opacity = number_attribute(stop, "stop-opacity").unwrap_or(1.0).clamp(0.0, 1.0);
+        stops.push(GradientStop {
+            offset,
+            color,
+            opacity: alpha * opacity,
+        });
+    }
+    stops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(body: &str) -> SvgDocument {
+        SvgDocument::parse(&format!(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 50">{}</svg>"#, body)).unwrap()
+    }
+
+    fn solid(paint: &Paint) -> Color {
+        match paint {
+            Paint::Solid(color) => *color,
+            Paint::Gradient(_) => panic!("expected a solid paint"),
+        }
+    }
+
+    #[test]
+    fn rejects_non_svg_roots() {
+        assert!(SvgDocument::parse("<html/>").is_err());
+        assert!(SvgDocument::parse("not xml").is_err());
+    }
+
+    #[test]
+    fn reads_view_box_and_falls_back_to_size() {
+        let document = parse("");
+        assert_eq!(document.view_box_origin, Vec2::ZERO);
+        assert_eq!(document.view_box_size, Vec2::new(100.0, 50.0));
+        let sized = SvgDocument::parse(r#"<svg width="20" height="10"/>"#).unwrap();
+        assert_eq!(sized.view_box_size, Vec2::new(20.0, 10.0));
+    }
+
+    #[test]
+    fn inherits_paint_and_multiplies_opacity_through_groups() {
+        let document = parse(
+            r##"<g fill="red" opacity="0.5" transform="translate(10 0)">
+                 <rect width="10" height="10" fill-opacity="0.5" transform="scale(2)"/>
+                 <circle r="5" fill="none" stroke="#00ff00" stroke-width="2"/>
+                 <rect width="10" height="10" display="none"/>
+               </g>"##,
+        );
+        assert_eq!(document.shapes.len(), 2);
+        let rect = &document.shapes[0];
+        let fill = rect.fill.as_ref().unwrap();
+        assert_eq!(solid(&fill.paint), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(fill.opacity, 0.5);
+        assert_eq!(rect.opacity, 0.5);
+        assert_eq!(rect.transform.transform_point2(Vec2::new(10.0, 10.0)), Vec2::new(30.0, 20.0));
+
+        let circle = &document.shapes[1];
+        assert!(circle.fill.is_none());
+        let stroke = circle.stroke.as_ref().unwrap();
+        assert_eq!(solid(&stroke.paint), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(stroke.style.width, 2.0);
+    }
+
+    #[test]
+    fn lines_are_never_filled() {
+        let document = parse(r#"<line x2="10" stroke="black"/><line x2="10" fill="red"/>"#);
+        assert_eq!(document.shapes.len(), 1);
+        assert!(document.shapes[0].fill.is_none());
+    }
+
+    #[test]
+    fn resolves_bounding_box_gradients_through_href() {
+        let document = parse(
+            r##"<defs>
+                 <linearGradient id="base" spreadMethod="reflect">
+                   <stop offset="0" stop-color="black"/>
+                   <stop offset="1" stop-color="white"/>
+                 </linearGradient>
+                 <linearGradient id="tilted" href="#base" x2="0%" y2="100%"/>
+               </defs>
+               <rect x="10" y="20" width="40" height="20" fill="url(#tilted)"/>"##,
+        );
+        let Paint::Gradient(gradient) = &document.shapes[0].fill.as_ref().unwrap().paint else {
+            panic!("expected a gradient");
+        };
+        assert_eq!(gradient.stops.len(), 2);
+        assert_eq!(gradient.spread, SpreadMode::Reflect);
+        // objectBoundingBox: 渐变从包围盒顶边走到底边
+        assert!(gradient.offset_at(Vec2::new(30.0, 20.0)).abs() < 1e-5);
+        assert!((gradient.offset_at(Vec2::new(30.0, 40.0)) - 1.0).abs() < 1e-5);
+        assert!((gradient.offset_at(Vec2::new(10.0, 30.0)) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn single_stop_gradients_become_solid_and_missing_ones_use_the_fallback() {
+        let document = parse(
+            r##"<linearGradient id="one"><stop offset="0" stop-color="blue" stop-opacity="0.25"/></linearGradient>
+               <rect width="1" height="1" fill="url(#one)"/>
+               <rect width="1" height="1" fill="url(#missing) red"/>"##,
+        );
+        let fill = document.shapes[0].fill.as_ref().unwrap();
+        assert_eq!(solid(&fill.paint), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(fill.opacity, 0.25);
+        assert_eq!(solid(&document.shapes[1].fill.as_ref().unwrap().paint), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn path_data_handles_relative_and_shorthand_commands() {
+        let path = path_data("m 10 10 h 10 v 10 H 10 z M 0 0 L 5 5");
+        let bounds = bounding_box(path.iter());
+        assert_eq!((bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y), (0.0, 0.0, 20.0, 20.0));
+        assert_eq!(path.iter().filter(|e| matches!(e, lyon::path::Event::Begin { .. })).count(), 2);
+    }
+}
@@ -1,5 +1,4 @@
-
-
 pub mod dep;
 pub mod shapes;
-pub mod animation;
\ No newline at end of file
+pub mod animation;
+pub mod import;
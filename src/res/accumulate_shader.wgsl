@@ -0,0 +1,14 @@
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+// 一个盖住整个屏幕的三角形, 不需要顶点缓冲区
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// 源纹理和目标一样大, 逐像素读取
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(source, vec2<i32>(position.xy), 0);
+}
@@ -0,0 +1,50 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct ParticleInput {
+    @location(5) position: vec2<f32>,
+    @location(6) rotation: f32,
+    // age, lifetime
+    @location(7) life: vec2<f32>,
+    @location(8) size: f32,
+    @location(9) color: vec4<f32>,
+};
+
+struct MVPMatrix {
+    mvp: mat4x4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> mvp_matrix: MVPMatrix;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) local: vec2<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput, particle: ParticleInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.local = model.position.xy;
+    out.color = particle.color;
+    // 空闲槽位移到裁剪空间之外
+    if particle.life.y <= 0.0 {
+        out.clip_position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+        return out;
+    }
+    let c = cos(particle.rotation);
+    let s = sin(particle.rotation);
+    let local = model.position.xy * particle.size;
+    let world = particle.position + vec2<f32>(c * local.x - s * local.y, s * local.x + c * local.y);
+    out.clip_position = mvp_matrix.mvp * vec4<f32>(world, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let edge = 1.0 - smoothstep(0.6, 1.0, length(in.local));
+    return vec4<f32>(in.color.rgb, in.color.a * edge);
+}
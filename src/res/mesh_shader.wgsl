@@ -0,0 +1,163 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+struct ShapeInput {
+    @location(5) m0: vec4<f32>,
+    @location(6) m1: vec4<f32>,
+    @location(7) m2: vec4<f32>,
+    @location(8) m3: vec4<f32>,
+    @location(9) color: vec4<f32>,
+};
+
+struct MVPMatrix {
+    mvp: mat4x4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> mvp_matrix: MVPMatrix;
+
+const MAX_STOPS: u32 = 16u;
+const TAU: f32 = 6.283185307;
+
+// 与 paint.rs 中的 PaintRaw 一致, kind 为 0 时是纯色
+struct Paint {
+    // kind, spread, stop_count
+    header: vec4<u32>,
+    to_gradient: array<vec4<f32>, 2>,
+    params: array<vec4<f32>, 2>,
+    colors: array<vec4<f32>, MAX_STOPS>,
+    offsets: array<vec4<f32>, 4>,
+}
+
+@group(1) @binding(0) var<uniform> paint: Paint;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) local: vec2<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput, shape: ShapeInput) -> VertexOutput {
+    var out: VertexOutput;
+    let model_mat = mat4x4<f32>(shape.m0, shape.m1, shape.m2, shape.m3);
+    out.clip_position = mvp_matrix.mvp * model_mat * vec4<f32>(model.position.xy, 0.0, 1.0);
+    // 顶点颜色乘以图形颜色, alpha 为透明度
+    out.color = vec4<f32>(model.color, 1.0) * shape.color;
+    out.local = model.position.xy;
+    return out;
+}
+
+fn linear_offset(p: vec2<f32>) -> f32 {
+    let start = paint.params[0].xy;
+    let axis = paint.params[0].zw - start;
+    let length_squared = dot(axis, axis);
+    if length_squared <= 1e-12 {
+        return 1.0;
+    }
+    return dot(p - start, axis) / length_squared;
+}
+
+fn radial_offset(p: vec2<f32>) -> f32 {
+    let center = paint.params[0].xy;
+    let focal = paint.params[0].zw;
+    let radius = paint.params[1].x;
+    if radius <= 1e-6 {
+        return 1.0;
+    }
+    // 从焦点出发经过 p 的射线与外圆相交, 偏移量为两段距离之比
+    let distance = length(p - focal);
+    if distance <= 1e-6 {
+        return 0.0;
+    }
+    let dir = (p - focal) / distance;
+    let to_focal = focal - center;
+    let b = dot(to_focal, dir);
+    let c = dot(to_focal, to_focal) - radius * radius;
+    let edge = -b + sqrt(max(b * b - c, 0.0));
+    if edge <= 1e-6 {
+        return 1.0;
+    }
+    return distance / edge;
+}
+
+fn conic_offset(p: vec2<f32>) -> f32 {
+    let d = p - paint.params[0].xy;
+    if dot(d, d) <= 1e-12 {
+        return 0.0;
+    }
+    let turns = (atan2(d.y, d.x) - paint.params[0].z) / TAU;
+    return turns - floor(turns);
+}
+
+fn spread(offset: f32) -> f32 {
+    switch paint.header.y {
+        case 1u: {
+            return offset - floor(offset);
+        }
+        case 2u: {
+            let t = offset - 2.0 * floor(offset * 0.5);
+            return select(t, 2.0 - t, t > 1.0);
+        }
+        default: {
+            return clamp(offset, 0.0, 1.0);
+        }
+    }
+}
+
+fn stop_offset(i: u32) -> f32 {
+    return paint.offsets[i / 4u][i % 4u];
+}
+
+// paint.rs 的测试里有一份手写的副本 (shade), 修改时两边一起改
+fn paint_color(local: vec2<f32>) -> vec4<f32> {
+    let kind = paint.header.x;
+    if kind == 0u {
+        return vec4<f32>(1.0);
+    }
+    let count = min(paint.header.z, MAX_STOPS);
+    if count == 0u {
+        return vec4<f32>(0.0);
+    }
+    let m = paint.to_gradient;
+    let p = mat2x2<f32>(m[0].xy, m[0].zw) * local + m[1].xy;
+    var offset: f32;
+    switch kind {
+        case 1u: {
+            offset = linear_offset(p);
+        }
+        case 2u: {
+            offset = radial_offset(p);
+        }
+        default: {
+            offset = conic_offset(p);
+        }
+    }
+    offset = spread(offset);
+
+    if offset <= stop_offset(0u) {
+        return paint.colors[0];
+    }
+    for (var i = 1u; i < count; i++) {
+        let next = stop_offset(i);
+        if offset < next {
+            let prev = stop_offset(i - 1u);
+            let span = next - prev;
+            let t = select(1.0, (offset - prev) / span, span > 0.0);
+            return mix(paint.colors[i - 1u], paint.colors[i], t);
+        }
+    }
+    return paint.colors[count - 1u];
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color * paint_color(in.local);
+}
+
+// 阴影只需要覆盖率: 图形透明度乘以填充的透明度
+@fragment
+fn fs_silhouette(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color.a * paint_color(in.local).a);
+}
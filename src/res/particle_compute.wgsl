@@ -0,0 +1,177 @@
+struct Particle {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+    rotation: f32,
+    spin: f32,
+    age: f32,
+    // 0 表示空闲槽位
+    lifetime: f32,
+    base_size: f32,
+    size: f32,
+    _pad: vec2<f32>,
+    color: vec4<f32>,
+};
+
+struct SimParams {
+    position: vec2<f32>,
+    gravity: vec2<f32>,
+    line_from: vec2<f32>,
+    line_to: vec2<f32>,
+    lifetime: vec2<f32>,
+    speed: vec2<f32>,
+    size: vec2<f32>,
+    angle: vec2<f32>,
+    spin: vec2<f32>,
+    rotation: f32,
+    direction: f32,
+    spread: f32,
+    drag: f32,
+    radius: f32,
+    dt: f32,
+    shape: u32,
+    edge: u32,
+    path_len: u32,
+    spawn: u32,
+    capacity: u32,
+    seed: u32,
+    has_color_curve: u32,
+    _pad: u32,
+    color: vec4<f32>,
+    life_color: array<vec4<f32>, 16>,
+    life_scale: array<vec4<f32>, 16>,
+};
+
+struct FreeList {
+    count: atomic<u32>,
+    indices: array<u32>,
+};
+
+@group(0) @binding(0) var<uniform> params: SimParams;
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<storage, read_write> free_list: FreeList;
+@group(0) @binding(3) var<storage, read> path: array<vec2<f32>>;
+
+const TAU: f32 = 6.283185307;
+
+// PCG 哈希, 每次调用推进状态
+fn random(state: ptr<function, u32>) -> f32 {
+    *state = *state * 747796405u + 2891336453u;
+    var word = ((*state >> ((*state >> 28u) + 4u)) ^ *state) * 277803737u;
+    word = (word >> 22u) ^ word;
+    return f32(word) / 4294967295.0;
+}
+
+fn between(range: vec2<f32>, state: ptr<function, u32>) -> f32 {
+    return mix(range.x, range.y, random(state));
+}
+
+fn rotate(v: vec2<f32>, angle: f32) -> vec2<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec2<f32>(c * v.x - s * v.y, s * v.x + c * v.y);
+}
+
+fn sample_color(life: f32) -> vec4<f32> {
+    let x = clamp(life, 0.0, 1.0) * 15.0;
+    let i = min(u32(x), 14u);
+    return mix(params.life_color[i], params.life_color[i + 1u], x - f32(i));
+}
+
+fn sample_scale(life: f32) -> vec4<f32> {
+    let x = clamp(life, 0.0, 1.0) * 15.0;
+    let i = min(u32(x), 14u);
+    return mix(params.life_scale[i], params.life_scale[i + 1u], x - f32(i));
+}
+
+// 与 ParticleSystem 的 integrate 相同: 先加年龄和力, 再移动并更新外观
+fn integrate(p: Particle, dt: f32) -> Particle {
+    var out = p;
+    out.age = p.age + dt;
+    let life = out.age / out.lifetime;
+    out.velocity = (p.velocity + params.gravity * dt) * exp(-params.drag * dt);
+    let scale = sample_scale(life);
+    out.position = p.position + out.velocity * scale.y * dt;
+    out.rotation = p.rotation + p.spin * dt;
+    out.size = p.base_size * scale.x;
+    let curve = sample_color(life);
+    var rgb = params.color.rgb;
+    if params.has_color_curve != 0u {
+        rgb = curve.rgb;
+    }
+    out.color = vec4<f32>(rgb, curve.a);
+    return out;
+}
+
+fn spawn_offset(state: ptr<function, u32>) -> vec2<f32> {
+    switch params.shape {
+        case 1u: {
+            return mix(params.line_from, params.line_to, random(state));
+        }
+        case 2u: {
+            var distance = params.radius;
+            if params.edge == 0u {
+                distance = params.radius * sqrt(random(state));
+            }
+            let angle = random(state) * TAU;
+            return vec2<f32>(cos(angle), sin(angle)) * distance;
+        }
+        case 3u: {
+            let x = random(state) * f32(params.path_len - 1u);
+            let i = min(u32(x), params.path_len - 2u);
+            return mix(path[i], path[i + 1u], x - f32(i));
+        }
+        default: {
+            return vec2<f32>(0.0, 0.0);
+        }
+    }
+}
+
+@compute @workgroup_size(64)
+fn simulate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= params.capacity {
+        return;
+    }
+    var p = particles[index];
+    if p.lifetime <= 0.0 {
+        return;
+    }
+    p = integrate(p, params.dt);
+    if p.age >= p.lifetime {
+        p.lifetime = 0.0;
+        p.color = vec4<f32>(0.0);
+        // 这一趟只归还槽位, 不取出, 所以写入不会和读取冲突
+        let slot = atomicAdd(&free_list.count, 1u);
+        free_list.indices[slot] = index;
+    }
+    particles[index] = p;
+}
+
+@compute @workgroup_size(64)
+fn emit(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.spawn {
+        return;
+    }
+    let count = atomicSub(&free_list.count, 1u);
+    // 没有空闲槽位 (或被其他线程减到了下溢), 还原计数后放弃
+    if count == 0u || count > params.capacity {
+        atomicAdd(&free_list.count, 1u);
+        return;
+    }
+    let index = free_list.indices[count - 1u];
+
+    var state = params.seed ^ (id.x * 2654435769u);
+    random(&state);
+    let turn = params.rotation;
+    let direction = turn + params.direction + (random(&state) - 0.5) * params.spread;
+    var p: Particle;
+    p.position = params.position + rotate(spawn_offset(&state), turn);
+    p.velocity = vec2<f32>(cos(direction), sin(direction)) * between(params.speed, &state);
+    p.rotation = between(params.angle, &state);
+    p.spin = between(params.spin, &state);
+    p.age = 0.0;
+    p.lifetime = max(between(params.lifetime, &state), 1e-6);
+    p.base_size = between(params.size, &state);
+    // 在这一步内随机出生, 避免粒子成批出现; 提前死亡的由下一次 simulate 回收
+    particles[index] = integrate(p, random(&state) * params.dt);
+}
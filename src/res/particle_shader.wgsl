@@ -0,0 +1,42 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct InstanceInput {
+    @location(5) p1: vec4<f32>,
+    @location(6) p2: vec4<f32>,
+    @location(7) p3: vec4<f32>,
+    @location(8) p4: vec4<f32>,
+    @location(10) color: vec4<f32>,
+};
+
+struct MVPMatrix {
+    mvp: mat4x4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> mvp_matrix: MVPMatrix;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) local: vec2<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+    let inst_mat = mat4x4<f32>(instance.p1, instance.p2, instance.p3, instance.p4);
+    out.clip_position = mvp_matrix.mvp * inst_mat * vec4<f32>(model.position.xy, 0.0, 1.0);
+    out.color = instance.color;
+    out.local = model.position.xy;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 四边形内画一个边缘柔和的圆点, 半径为 1
+    let edge = 1.0 - smoothstep(0.6, 1.0, length(in.local));
+    return vec4<f32>(in.color.rgb, in.color.a * edge);
+}
@@ -9,6 +9,7 @@ struct InstanceInput {
 @location(7) p3: vec4<f32>,
 @location(8) p4: vec4<f32>,
 @location(9) p5: vec3<f32>,
+@location(10) color: vec4<f32>,
 };
 
 struct Uniforms {
@@ -36,7 +37,8 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
     @location(1) uv: vec2<f32>,
-    @location(2) @interpolate(flat)  origin: vec3<f32>
+    @location(2) @interpolate(flat)  origin: vec3<f32>,
+    @location(3) alpha: f32,
 };
 
 fn rotate2D(angle: f32) -> mat3x3<f32> {
@@ -48,8 +50,34 @@ fn rotate2D(angle: f32) -> mat3x3<f32> {
     return rotate;
 }
 
-fn createScaleMatrix(elapsedTime: f32, targetScale: f32, duration: f32) -> mat2x2<f32> {
-  let t = clamp(elapsedTime / duration, 0.0, 1.0);
+// CSS cubic-bezier(x1, y1, x2, y2), curve = (x1, y1, x2, y2); linear is (0, 0, 1, 1).
+// 与 animation::easing::CubicBezier 的求解方式一致
+fn bezier_component(a: f32, b: f32, s: f32) -> f32 {
+    let inv = 1.0 - s;
+    return 3.0 * inv * inv * s * a + 3.0 * inv * s * s * b + s * s * s;
+}
+
+fn ease_cubic_bezier(curve: vec4<f32>, x: f32) -> f32 {
+    if x <= 0.0 || x >= 1.0 {
+        return clamp(x, 0.0, 1.0);
+    }
+    // 二分求解 x(s) = x, 再求 y(s)
+    var lo = 0.0;
+    var hi = 1.0;
+    var s = x;
+    for (var i = 0; i < 20; i++) {
+        if bezier_component(curve.x, curve.z, s) < x {
+            lo = s;
+        } else {
+            hi = s;
+        }
+        s = (lo + hi) * 0.5;
+    }
+    return bezier_component(curve.y, curve.w, s);
+}
+
+fn createScaleMatrix(elapsedTime: f32, targetScale: f32, duration: f32, curve: vec4<f32>) -> mat2x2<f32> {
+  let t = ease_cubic_bezier(curve, clamp(elapsedTime / duration, 0.0, 1.0));
   let current_scale = mix(1.0, targetScale, t);
   return mat2x2<f32>(current_scale, 0.0, 0.0, current_scale);
 }
@@ -86,13 +114,14 @@ fn vs_main(
     //let pos = rotate2D(action_matrix.theta) * model.position;
     var pos = rotate_around_point(model.position.xy, vec2<f32>(0.4, 0.2), action_matrix.theta);
 //    if action_matrix.theta > 0.0 {
-//       let scaleMat= createScaleMatrix(0.02, 2.0, 5.0);
+//       let scaleMat= createScaleMatrix(0.02, 2.0, 5.0, vec4<f32>(0.42, 0.0, 0.58, 1.0));
 //       pos = pos * scaleMat;
 //    }
     out.clip_position = vec4<f32>((mvp_matrix.mvp * inst_mat * vec4<f32>(pos.xy, 0.0, 1.0)).xy, 0.9, 1.0);
     out.uv = (mvp_matrix.mvp * inst_mat * vec4<f32>(model.position.xy, 0.0, 1.0)).xy;
     out.origin = instance.p5;
-    out.color = model.color;
+    out.color = model.color * instance.color.rgb;
+    out.alpha = instance.color.a;
     //out.uv = (vec3(model.position.xy, 1.0) * action_matrix.action_mat).xy;
 
     return out;
@@ -117,6 +146,6 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let maxLen = 1.0;
     //let alpha = 1- smoothstep(0.0, 1.0, dist);
     let fade = clamp(dist  / maxLen, 0.0, 1.0);
-    return vec4<f32>(in.color.rgb*fade , 0.7);
+    return vec4<f32>(in.color.rgb*fade , 0.7 * in.alpha);
     //return mix(uniforms.color, vec4<f32>(1.0, 1.0, 1.0, 1.0), fade);
 }
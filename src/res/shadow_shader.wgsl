@@ -0,0 +1,77 @@
+struct ShadowParams {
+    // rgb 为阴影颜色, a 为不透明度
+    color: vec4<f32>,
+    // 合成时阴影纹理在屏幕上的位置 (左上角, 已加上偏移) 和大小, 单位为像素
+    rect: vec4<f32>,
+    screen: vec2<f32>,
+    // 高斯模糊的标准差, 单位为像素
+    sigma: f32,
+    _pad: f32,
+}
+
+// group 0 留给场景的 mvp 矩阵, 这里用不到
+@group(1) @binding(0) var source: texture_2d<f32>;
+@group(1) @binding(1) var source_sampler: sampler;
+@group(1) @binding(2) var<uniform> shadow: ShadowParams;
+
+// 一个盖住整个屏幕的三角形
+fn fullscreen_corner(index: u32) -> vec4<f32> {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    return fullscreen_corner(index);
+}
+
+fn blur(position: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(source));
+    let uv = position / size;
+    if shadow.sigma < 0.5 {
+        return textureSampleLevel(source, source_sampler, uv, 0.0);
+    }
+    // 采样覆盖 3 倍标准差, 最多 32 对, 半径更大时拉开间距
+    let reach = ceil(shadow.sigma * 3.0);
+    let taps = i32(min(reach, 32.0));
+    let spacing = reach / f32(taps);
+    var sum = 0.0;
+    var total = 0.0;
+    for (var i = -taps; i <= taps; i += 1) {
+        let x = f32(i) * spacing;
+        let weight = exp(-0.5 * x * x / (shadow.sigma * shadow.sigma));
+        sum += textureSampleLevel(source, source_sampler, uv + direction * x / size, 0.0).r * weight;
+        total += weight;
+    }
+    return vec4<f32>(sum / total);
+}
+
+@fragment
+fn fs_blur_x(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return blur(position.xy, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_y(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return blur(position.xy, vec2<f32>(0.0, 1.0));
+}
+
+@vertex
+fn vs_composite(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // 两个三角形拼出阴影所在的矩形, 像素坐标 y 朝下
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+    );
+    let pixel = shadow.rect.xy + corners[index] * shadow.rect.zw;
+    let ndc = pixel / shadow.screen * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    return vec4<f32>(ndc, 0.0, 1.0);
+}
+
+@fragment
+fn fs_composite(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // 纹理可能比矩形大, 只用到左上角那一块
+    let uv = (position.xy - shadow.rect.xy) / vec2<f32>(textureDimensions(source));
+    let coverage = textureSampleLevel(source, source_sampler, uv, 0.0).r;
+    return vec4<f32>(shadow.color.rgb, shadow.color.a * coverage);
+}
@@ -0,0 +1,50 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) bones: vec4<u32>,
+    @location(3) weights: vec4<f32>,
+};
+
+struct ShapeInput {
+    @location(5) m0: vec4<f32>,
+    @location(6) m1: vec4<f32>,
+    @location(7) m2: vec4<f32>,
+    @location(8) m3: vec4<f32>,
+    @location(9) color: vec4<f32>,
+};
+
+struct MVPMatrix {
+    mvp: mat4x4<f32>,
+}
+
+// 与 skin.rs 中的 MAX_BONES 保持一致
+struct Bones {
+    matrices: array<mat4x4<f32>, 64>,
+}
+
+@group(0) @binding(0) var<uniform> mvp_matrix: MVPMatrix;
+@group(1) @binding(0) var<uniform> bones: Bones;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput, shape: ShapeInput) -> VertexOutput {
+    var out: VertexOutput;
+    let model_mat = mat4x4<f32>(shape.m0, shape.m1, shape.m2, shape.m3);
+    // 线性混合蒙皮: 按权重混合骨骼矩阵
+    let skin = bones.matrices[model.bones.x] * model.weights.x
+        + bones.matrices[model.bones.y] * model.weights.y
+        + bones.matrices[model.bones.z] * model.weights.z
+        + bones.matrices[model.bones.w] * model.weights.w;
+    out.clip_position = mvp_matrix.mvp * model_mat * skin * vec4<f32>(model.position.xy, 0.0, 1.0);
+    out.color = vec4<f32>(model.color, 1.0) * shape.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
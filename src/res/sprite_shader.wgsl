@@ -0,0 +1,44 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct SpriteInput {
+    @location(5) m0: vec4<f32>,
+    @location(6) m1: vec4<f32>,
+    @location(7) m2: vec4<f32>,
+    @location(8) m3: vec4<f32>,
+    @location(9) color: vec4<f32>,
+    @location(10) uv_rect: vec4<f32>,
+};
+
+struct MVPMatrix {
+    mvp: mat4x4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> mvp_matrix: MVPMatrix;
+@group(1) @binding(0) var atlas: texture_2d<f32>;
+@group(1) @binding(1) var atlas_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput, sprite: SpriteInput) -> VertexOutput {
+    var out: VertexOutput;
+    let model_mat = mat4x4<f32>(sprite.m0, sprite.m1, sprite.m2, sprite.m3);
+    out.clip_position = mvp_matrix.mvp * model_mat * vec4<f32>(model.position.xy, 0.0, 1.0);
+    out.color = vec4<f32>(model.color, 1.0) * sprite.color;
+    // 单位四边形的 uv 映射到当前帧在图集中的矩形
+    out.uv = mix(sprite.uv_rect.xy, sprite.uv_rect.zw, model.uv);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(atlas, atlas_sampler, in.uv) * in.color;
+}
@@ -0,0 +1,199 @@
+use glam::Vec2;
+use lyon::path::iterator::PathIterator;
+use lyon::path::{Path, PathEvent};
+
+/// One flattened subpath of a lyon path.
+#[derive(Clone, Debug)]
+pub struct Contour {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+impl Contour {
+    /// Splits `path` into polylines, flattening curves to within `tolerance`.
+    pub fn from_path(path: &Path, tolerance: f32) -> Vec<Contour> {
+        let mut contours = vec![];
+        let mut current = vec![];
+        for event in path.iter().flattened(tolerance) {
+            match event {
+                PathEvent::Begin { at } => {
+                    current = vec![Vec2::new(at.x, at.y)];
+                }
+                PathEvent::Line { to, .. } => {
+                    current.push(Vec2::new(to.x, to.y));
+                }
+                PathEvent::End { close, .. } => {
+                    contours.push(Contour { points: std::mem::take(&mut current), closed: close });
+                }
+                // flattened() 之后不会再有曲线
+                PathEvent::Quadratic { to, .. } | PathEvent::Cubic { to, .. } => {
+                    current.push(Vec2::new(to.x, to.y));
+                }
+            }
+        }
+        contours
+    }
+
+    /// Twice the signed area; positive for counter-clockwise contours.
+    pub fn signed_area(&self) -> f32 {
+        let n = self.points.len();
+        (0..n)
+            .map(|i| self.points[i].perp_dot(self.points[(i + 1) % n]))
+            .sum()
+    }
+}
+
+/// Cumulative-length lookup over a path so positions can be sampled by distance
+/// travelled rather than by curve parameter, which keeps motion speed uniform.
+#[derive(Clone, Debug, Default)]
+pub struct ArcLengthTable {
+    segments: Vec<(Vec2, Vec2)>,
+    // 每段起点处的累计长度
+    offsets: Vec<f32>,
+    length: f32,
+}
+
+impl ArcLengthTable {
+    pub fn new(path: &Path, tolerance: f32) -> Self {
+        let mut table = Self::default();
+        for contour in Contour::from_path(path, tolerance) {
+            table.add_contour(&contour);
+        }
+        table
+    }
+
+    pub fn from_contour(contour: &Contour) -> Self {
+        let mut table = Self::default();
+        table.add_contour(contour);
+        table
+    }
+
+    fn add_contour(&mut self, contour: &Contour) {
+        let points = &contour.points;
+        let closing = if contour.closed { points.first().zip(points.last()) } else { None };
+        let edges = points
+            .windows(2)
+            .map(|w| (w[0], w[1]))
+            .chain(closing.map(|(first, last)| (*last, *first)));
+        for (from, to) in edges {
+            let length = from.distance(to);
+            if length <= f32::EPSILON {
+                continue;
+            }
+            self.segments.push((from, to));
+            self.offsets.push(self.length);
+            self.length += length;
+        }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Position and unit tangent at `distance` along the path, clamped to its ends.
+    pub fn sample(&self, distance: f32) -> Option<(Vec2, Vec2)> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        let distance = distance.clamp(0.0, self.length);
+        let index = self.offsets.partition_point(|&offset| offset <= distance).max(1) - 1;
+        let (from, to) = self.segments[index];
+        let segment = to - from;
+        let t = ((distance - self.offsets[index]) / segment.length()).clamp(0.0, 1.0);
+        Some((from + segment * t, segment.normalize()))
+    }
+
+    /// Like `sample`, with `t` in [0, 1] spanning the whole length.
+    pub fn sample_normalized(&self, t: f32) -> Option<(Vec2, Vec2)> {
+        self.sample(t * self.length)
+    }
+
+    /// The polyline between two distances along the path, both clamped to its ends.
+    pub fn slice(&self, start: f32, end: f32) -> Vec<Vec2> {
+        let (start, end) = (start.clamp(0.0, self.length), end.clamp(0.0, self.length));
+        let (Some((first, _)), Some((last, _))) = (self.sample(start), self.sample(end)) else {
+            return vec![];
+        };
+        let mut points = vec![first];
+        for (i, &offset) in self.offsets.iter().enumerate() {
+            if offset > start && offset < end {
+                points.push(self.segments[i].0);
+            }
+        }
+        points.push(last);
+        points
+    }
+
+    /// `count` points evenly spaced by arc length. Closed outlines don't repeat
+    /// their start point at the end.
+    pub fn resample(&self, count: usize, closed: bool) -> Vec<Vec2> {
+        let spans = if closed { count } else { count.saturating_sub(1).max(1) };
+        (0..count)
+            .filter_map(|i| self.sample(self.length * i as f32 / spans as f32))
+            .map(|(position, _)| position)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon::math::point;
+    use super::*;
+
+    fn polyline(points: &[(f32, f32)], closed: bool) -> Path {
+        let mut builder = Path::builder();
+        builder.begin(point(points[0].0, points[0].1));
+        for &(x, y) in &points[1..] {
+            builder.line_to(point(x, y));
+        }
+        builder.end(closed);
+        builder.build()
+    }
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn samples_by_distance_along_a_polyline() {
+        let table = ArcLengthTable::new(&polyline(&[(0.0, 0.0), (3.0, 0.0), (3.0, 4.0)], false), 0.01);
+        assert_eq!(table.length(), 7.0);
+
+        let (position, tangent) = table.sample(1.0).unwrap();
+        assert_near(position, Vec2::new(1.0, 0.0));
+        assert_near(tangent, Vec2::X);
+        let (position, tangent) = table.sample(5.0).unwrap();
+        assert_near(position, Vec2::new(3.0, 2.0));
+        assert_near(tangent, Vec2::Y);
+
+        assert_near(table.sample(-1.0).unwrap().0, Vec2::ZERO);
+        assert_near(table.sample(10.0).unwrap().0, Vec2::new(3.0, 4.0));
+        assert_near(table.sample_normalized(0.5).unwrap().0, Vec2::new(3.0, 0.5));
+        assert_near(table.sample_normalized(1.0).unwrap().0, Vec2::new(3.0, 4.0));
+
+        assert_eq!(table.slice(1.0, 5.0), vec![Vec2::new(1.0, 0.0), Vec2::new(3.0, 0.0), Vec2::new(3.0, 2.0)]);
+        assert!(ArcLengthTable::default().sample(0.0).is_none());
+    }
+
+    #[test]
+    fn closed_outlines_include_the_closing_edge() {
+        let square = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let closed = ArcLengthTable::new(&polyline(&square, true), 0.01);
+        let open = ArcLengthTable::new(&polyline(&square, false), 0.01);
+        assert_eq!(closed.length(), 4.0);
+        assert_eq!(open.length(), 3.0);
+
+        let corners = square.iter().map(|&(x, y)| Vec2::new(x, y)).collect::<Vec<_>>();
+        // 闭合轮廓末尾不重复起点
+        for (points, expected) in [(closed.resample(4, true), &corners), (open.resample(4, false), &corners)] {
+            assert_eq!(points.len(), 4);
+            points.iter().zip(expected).for_each(|(a, b)| assert_near(*a, *b));
+        }
+        assert_eq!(open.resample(1, false), vec![Vec2::ZERO]);
+        assert!(closed.resample(0, true).is_empty());
+    }
+}
@@ -0,0 +1,470 @@
+use std::mem;
+use glam::{Mat4, Vec2};
+use lyon::path::Path;
+use lyon::tessellation::{BuffersBuilder, FillOptions, FillRule, FillTessellator, FillVertex, VertexBuffers};
+use wgpu::util::DeviceExt;
+use super::circle::IVertex;
+use super::paint::Paint;
+use super::stroke::{tessellate_stroke, StrokeStyle};
+use super::{Color, Shadow, Shape, ShapeLayouts};
+
+/// Per-shape data consumed by `mesh_shader.wgsl`, bound as a one-instance vertex buffer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShapeRaw {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+}
+
+impl ShapeRaw {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<ShapeRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+impl IVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<IVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShapeTransform {
+    pub position: Vec2,
+    pub rotation: f32,
+    pub scale: Vec2,
+    /// Local point that sits at `position` and that rotation and scale pivot around.
+    pub anchor: Vec2,
+}
+
+impl Default for ShapeTransform {
+    fn default() -> Self {
+        ShapeTransform {
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+            anchor: Vec2::ZERO,
+        }
+    }
+}
+
+impl ShapeTransform {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_translation(self.position.extend(0.0))
+            * Mat4::from_rotation_z(self.rotation)
+            * Mat4::from_scale(self.scale.extend(1.0))
+            * Mat4::from_translation((-self.anchor).extend(0.0))
+    }
+}
+
+pub fn tessellate_fill(path: &Path, fill_rule: FillRule, color: [f32; 3]) -> VertexBuffers<IVertex, u16> {
+    let mut buffers: VertexBuffers<IVertex, u16> = VertexBuffers::new();
+    let mut tessellator = FillTessellator::new();
+    let result = tessellator.tessellate_path(
+        path,
+        &FillOptions::tolerance(0.02).with_fill_rule(fill_rule),
+        &mut BuffersBuilder::new(&mut buffers, |v: FillVertex| {
+            let pos = v.position().to_array();
+            IVertex {
+                position: [pos[0], pos[1], 0.0],
+                color,
+            }
+        }),
+    );
+    if let Err(e) = result {
+        log::warn!("fill tessellation failed: {:?}", e);
+    }
+    buffers
+}
+
+struct GpuMesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    shape_buffer: wgpu::Buffer,
+    paint_buffer: wgpu::Buffer,
+    paint_bg: wgpu::BindGroup,
+    index_count: u32,
+}
+
+/// A tessellated shape that owns its GPU buffers.
+///
+/// Vertex colors are multiplied by the shape color and opacity in the shader, so
+/// fills are tessellated white and tinted. A gradient paint is evaluated per
+/// fragment in the shape's local space, under the same tint. Stroked shapes keep their source path
+/// and re-tessellate when a stroke property changes. Call `prepare` before
+/// `draw`, outside the render pass, to upload whatever changed.
+pub struct MeshShape {
+    geometry: VertexBuffers<IVertex, u16>,
+    outline: Option<(Path, StrokeStyle)>,
+    pub transform: ShapeTransform,
+    parent: Mat4,
+    color: Color,
+    paint: Paint,
+    opacity: f32,
+    shadow: Option<Shadow>,
+    gpu: Option<GpuMesh>,
+    geometry_dirty: bool,
+    paint_dirty: bool,
+}
+
+impl MeshShape {
+    pub fn new(geometry: VertexBuffers<IVertex, u16>) -> Self {
+        MeshShape {
+            geometry,
+            outline: None,
+            transform: ShapeTransform::default(),
+            parent: Mat4::IDENTITY,
+            color: Color::WHITE,
+            paint: Paint::Solid(Color::WHITE),
+            opacity: 1.0,
+            shadow: None,
+            gpu: None,
+            geometry_dirty: true,
+            paint_dirty: false,
+        }
+    }
+
+    pub fn fill(path: &Path, fill_rule: FillRule, color: Color) -> Self {
+        let mut shape = Self::new(tessellate_fill(path, fill_rule, [1.0, 1.0, 1.0]));
+        shape.set_paint(Paint::Solid(color));
+        shape
+    }
+
+    pub fn stroke(path: Path, style: StrokeStyle, color: Color) -> Self {
+        let mut shape = Self::new(tessellate_stroke(&path, &style, [1.0, 1.0, 1.0]));
+        shape.outline = Some((path, style));
+        shape.set_paint(Paint::Solid(color));
+        shape
+    }
+
+    pub fn with_paint(mut self, paint: Paint) -> Self {
+        self.set_paint(paint);
+        self
+    }
+
+    pub fn geometry(&self) -> &VertexBuffers<IVertex, u16> {
+        &self.geometry
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn paint(&self) -> &Paint {
+        &self.paint
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn stroke_style(&self) -> Option<&StrokeStyle> {
+        self.outline.as_ref().map(|(_, style)| style)
+    }
+
+    /// Transform of whatever this shape is nested in (a layer, a group), applied
+    /// after its own transform.
+    pub fn set_parent_transform(&mut self, parent: Mat4) {
+        self.parent = parent;
+    }
+
+    pub fn model_matrix(&self) -> Mat4 {
+        self.parent * self.transform.matrix()
+    }
+
+    pub fn to_raw(&self) -> ShapeRaw {
+        let color = self.color.to_array();
+        ShapeRaw {
+            model: self.model_matrix().to_cols_array_2d(),
+            color: [color[0], color[1], color[2], self.opacity],
+        }
+    }
+
+    /// Swaps in a new outline, tessellated the way the shape was made: stroked
+    /// with its stroke style, or filled with `fill_rule`. The new vertices are
+    /// white, so colors baked into the old ones are lost; the shape's color and
+    /// paint still apply.
+    pub fn set_path(&mut self, path: Path, fill_rule: FillRule) {
+        match &mut self.outline {
+            Some((outline, style)) => {
+                self.geometry = tessellate_stroke(&path, style, [1.0, 1.0, 1.0]);
+                *outline = path;
+            }
+            None => self.geometry = tessellate_fill(&path, fill_rule, [1.0, 1.0, 1.0]),
+        }
+        self.geometry_dirty = true;
+    }
+
+    /// Vertex and index buffers for `geometry`, and how many indices to draw.
+    fn create_geometry_buffers(device: &wgpu::Device, geometry: &VertexBuffers<IVertex, u16>) -> (wgpu::Buffer, wgpu::Buffer, u32) {
+        // 索引缓冲区的大小要是 4 字节的倍数
+        let mut indices = geometry.indices.clone();
+        if indices.len() % 2 == 1 {
+            indices.push(0);
+        }
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shape Vertex Buffer"),
+            contents: bytemuck::cast_slice(&geometry.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shape Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        (vertex_buffer, index_buffer, geometry.indices.len() as u32)
+    }
+
+    /// Layout of the paint uniform, bind group 1 of the shape pipeline.
+    pub fn paint_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Paint Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+
+    fn update_stroke(&mut self, update: impl FnOnce(&mut StrokeStyle)) {
+        if let Some((path, style)) = &mut self.outline {
+            update(style);
+            self.geometry = tessellate_stroke(path, style, [1.0, 1.0, 1.0]);
+            self.geometry_dirty = true;
+        }
+    }
+}
+
+impl Shape for MeshShape {
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Also resets the tint: to the solid color, or to white under a gradient.
+    fn set_paint(&mut self, paint: Paint) {
+        self.color = paint.tint();
+        self.paint = paint;
+        self.paint_dirty = true;
+    }
+
+    fn set_shadow(&mut self, shadow: Shadow) {
+        self.shadow = Some(shadow);
+    }
+
+    fn shadow(&self) -> Option<Shadow> {
+        self.shadow
+    }
+
+    fn set_shadow_color(&mut self, color: Color) {
+        self.shadow.get_or_insert_with(Shadow::default).color = color;
+    }
+
+    fn set_shadow_offset(&mut self, offset: Vec2) {
+        self.shadow.get_or_insert_with(Shadow::default).offset = offset;
+    }
+
+    fn set_shadow_blur(&mut self, blur_radius: f32) {
+        self.shadow.get_or_insert_with(Shadow::default).blur_radius = blur_radius;
+    }
+
+    fn set_shadow_opacity(&mut self, opacity: f32) {
+        self.shadow.get_or_insert_with(Shadow::default).opacity = opacity;
+    }
+
+    fn set_position(&mut self, position: Vec2) {
+        self.transform.position = position;
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.transform.rotation = rotation;
+    }
+
+    fn set_scale(&mut self, scale: Vec2) {
+        self.transform.scale = scale;
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+
+    fn set_anchor(&mut self, anchor: Vec2) {
+        self.transform.anchor = anchor;
+    }
+
+    fn set_geometry(&mut self, geometry: &VertexBuffers<IVertex, u16>) {
+        self.geometry.vertices.clone_from(&geometry.vertices);
+        self.geometry.indices.clone_from(&geometry.indices);
+        self.geometry_dirty = true;
+    }
+
+    fn set_stroke_width(&mut self, width: f32) {
+        self.update_stroke(|style| style.width = width);
+    }
+
+    fn set_dash_offset(&mut self, offset: f32) {
+        self.update_stroke(|style| style.dash_offset = offset);
+    }
+
+    fn set_trim_start(&mut self, start: f32) {
+        self.update_stroke(|style| style.trim_start = start);
+    }
+
+    fn set_trim_end(&mut self, end: f32) {
+        self.update_stroke(|style| style.trim_end = end);
+    }
+
+    fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        let mut points = self.geometry.vertices.iter().map(|v| Vec2::new(v.position[0], v.position[1]));
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+        let model = self.model_matrix();
+        let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+            .map(|corner| model.transform_point3(corner.extend(0.0)).truncate());
+        Some(corners.iter().fold((corners[0], corners[0]), |(min, max), &c| (min.min(c), max.max(c))))
+    }
+
+    fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, layouts: &ShapeLayouts) {
+        let raw = self.to_raw();
+        let Some(gpu) = &mut self.gpu else {
+            let (vertex_buffer, index_buffer, index_count) = Self::create_geometry_buffers(device, &self.geometry);
+            let paint_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Paint Buffer"),
+                contents: bytemuck::cast_slice(&[self.paint.to_raw()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+            let paint_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Paint Bind Group"),
+                layout: &layouts.paint,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: paint_buffer.as_entire_binding(),
+                }],
+            });
+            self.gpu = Some(GpuMesh {
+                vertex_buffer,
+                index_buffer,
+                shape_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Shape Buffer"),
+                    contents: bytemuck::cast_slice(&[raw]),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                }),
+                paint_buffer,
+                paint_bg,
+                index_count,
+            });
+            self.geometry_dirty = false;
+            self.paint_dirty = false;
+            return;
+        };
+        if self.geometry_dirty {
+            // 顶点数量可能已经改变, 只重建几何体的缓冲区
+            (gpu.vertex_buffer, gpu.index_buffer, gpu.index_count) = Self::create_geometry_buffers(device, &self.geometry);
+            self.geometry_dirty = false;
+        }
+        queue.write_buffer(&gpu.shape_buffer, 0, bytemuck::cast_slice(&[raw]));
+        if self.paint_dirty {
+            queue.write_buffer(&gpu.paint_buffer, 0, bytemuck::cast_slice(&[self.paint.to_raw()]));
+            self.paint_dirty = false;
+        }
+    }
+
+    /// Expects the shape pipeline to be bound, with the view matrix at group 0.
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        let Some(gpu) = &self.gpu else {
+            return;
+        };
+        if gpu.index_count == 0 {
+            return;
+        }
+        render_pass.set_bind_group(1, &gpu.paint_bg, &[]);
+        render_pass.set_vertex_buffer(0, gpu.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, gpu.shape_buffer.slice(..));
+        render_pass.set_index_buffer(gpu.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..gpu.index_count, 0, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon::math::{point, Box2D};
+    use lyon::path::Winding;
+
+    fn square() -> MeshShape {
+        let mut builder = Path::builder();
+        builder.add_rectangle(&Box2D::new(point(-0.5, -0.25), point(0.5, 0.25)), Winding::Positive);
+        MeshShape::fill(&builder.build(), FillRule::NonZero, Color::WHITE)
+    }
+
+    #[test]
+    fn shadow_setters_start_from_the_default_shadow() {
+        let mut shape = square();
+        assert_eq!(shape.shadow(), None);
+        shape.set_shadow_blur(0.1);
+        assert_eq!(shape.shadow(), Some(Shadow { blur_radius: 0.1, ..Shadow::default() }));
+        shape.set_shadow_offset(Vec2::new(0.2, 0.3));
+        let shadow = shape.shadow().unwrap();
+        assert_eq!((shadow.blur_radius, shadow.offset), (0.1, Vec2::new(0.2, 0.3)));
+    }
+
+    #[test]
+    fn bounds_follow_the_transform() {
+        let mut shape = square();
+        assert_eq!(shape.bounds(), Some((Vec2::new(-0.5, -0.25), Vec2::new(0.5, 0.25))));
+        shape.set_parent_transform(Mat4::from_translation(glam::Vec3::new(1.0, 0.0, 0.0)) * Mat4::from_rotation_z(std::f32::consts::FRAC_PI_2));
+        let (min, max) = shape.bounds().unwrap();
+        assert!((min - Vec2::new(0.75, -0.5)).length() < 1e-5 && (max - Vec2::new(1.25, 0.5)).length() < 1e-5);
+        assert_eq!(MeshShape::new(VertexBuffers::new()).bounds(), None);
+    }
+}
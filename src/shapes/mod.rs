@@ -1,17 +1,138 @@
 pub mod circle;
+pub mod measure;
+pub mod mesh;
+pub mod paint;
+pub mod primitive;
+pub mod skin;
+pub mod sprite;
+pub mod stroke;
 
+use std::ops::{Add, Mul, Sub};
+use lyon::tessellation::VertexBuffers;
+use circle::IVertex;
+use paint::Paint;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Color {
     data : glam::Vec3
 }
 
+impl Color {
+    pub const BLACK: Color = Color { data: glam::Vec3::ZERO };
+    pub const WHITE: Color = Color { data: glam::Vec3::ONE };
+
+    pub fn new(r: f32, g: f32, b: f32) -> Self {
+        Color { data: glam::Vec3::new(r, g, b) }
+    }
+
+    pub fn from_vec3(data: glam::Vec3) -> Self {
+        Color { data }
+    }
+
+    pub fn to_vec3(&self) -> glam::Vec3 {
+        self.data
+    }
+
+    pub fn to_array(&self) -> [f32; 3] {
+        self.data.to_array()
+    }
+}
+
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, rhs: Color) -> Color {
+        Color { data: self.data + rhs.data }
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, rhs: Color) -> Color {
+        Color { data: self.data - rhs.data }
+    }
+}
 
+impl Mul<f32> for Color {
+    type Output = Color;
+
+    fn mul(self, rhs: f32) -> Color {
+        Color { data: self.data * rhs }
+    }
+}
+
+
+/// A blurred, tinted copy of a shape's silhouette drawn underneath it.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Shadow {
     pub color: Color,
-    pub offset: (f32, f32),
+    /// View units; y points up.
+    pub offset: glam::Vec2,
+    /// View units over which the edge fades out; 0 gives a hard shadow.
     pub blur_radius: f32,
+    pub opacity: f32,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Shadow {
+            color: Color::BLACK,
+            offset: glam::Vec2::new(0.0, -0.02),
+            blur_radius: 0.04,
+            opacity: 0.35,
+        }
+    }
 }
+
+/// Bind group layouts shapes build their bind groups against. The renderer
+/// creates them once and shares them with the pipelines that use them.
+pub struct ShapeLayouts {
+    pub paint: wgpu::BindGroupLayout,
+}
+
+impl ShapeLayouts {
+    pub fn new(device: &wgpu::Device) -> Self {
+        ShapeLayouts { paint: mesh::MeshShape::paint_layout(device) }
+    }
+}
+
+/// Anything an `Animator` can drive. The transform setters default to no-ops so a
+/// shape only has to implement the properties it can actually display.
 pub trait Shape {
     fn set_color(&mut self, color: Color);
+    /// A gradient paints in the shape's local space and is tinted by the color.
+    fn set_paint(&mut self, _paint: Paint) {}
     fn set_shadow(&mut self, shadow: Shadow);
+    /// The shadow the renderer should draw under this shape, if any.
+    fn shadow(&self) -> Option<Shadow> {
+        None
+    }
+    /// Setting a shadow parameter on a shape without a shadow gives it
+    /// `Shadow::default()` with that parameter changed.
+    fn set_shadow_color(&mut self, _color: Color) {}
+    fn set_shadow_offset(&mut self, _offset: glam::Vec2) {}
+    fn set_shadow_blur(&mut self, _blur_radius: f32) {}
+    fn set_shadow_opacity(&mut self, _opacity: f32) {}
+    fn set_position(&mut self, _position: glam::Vec2) {}
+    fn set_rotation(&mut self, _rotation: f32) {}
+    fn set_scale(&mut self, _scale: glam::Vec2) {}
+    fn set_opacity(&mut self, _opacity: f32) {}
+    /// Pivot for rotation and scale, in the shape's local coordinates.
+    fn set_anchor(&mut self, _anchor: glam::Vec2) {}
+    fn set_stroke_width(&mut self, _width: f32) {}
+    fn set_dash_offset(&mut self, _offset: f32) {}
+    fn set_trim_start(&mut self, _start: f32) {}
+    fn set_trim_end(&mut self, _end: f32) {}
+    /// Which atlas frame a sprite shows.
+    fn set_frame(&mut self, _frame: usize) {}
+    /// Replaces the tessellated outline, e.g. while morphing.
+    fn set_geometry(&mut self, _geometry: &VertexBuffers<IVertex, u16>) {}
+    /// Axis-aligned box around what `draw` covers, in view space, if known.
+    fn bounds(&self) -> Option<(glam::Vec2, glam::Vec2)> {
+        None
+    }
+    /// Uploads changed state to the GPU; called every frame before the render pass begins.
+    fn prepare(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, _layouts: &ShapeLayouts) {}
     fn draw(&self, render_pass: &mut wgpu::RenderPass);
 }
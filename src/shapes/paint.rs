@@ -0,0 +1,467 @@
+use std::f32::consts::TAU;
+use glam::{Affine2, Vec2};
+use super::Color;
+
+/// Most stops a gradient can have on the GPU; the size of the stop arrays in
+/// `mesh_shader.wgsl`. Gradients with more are resampled to this many evenly
+/// spaced stops.
+pub const MAX_STOPS: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GradientStop {
+    /// Position along the gradient in [0, 1].
+    pub offset: f32,
+    pub color: Color,
+    pub opacity: f32,
+}
+
+impl GradientStop {
+    pub fn new(offset: f32, color: Color) -> Self {
+        GradientStop { offset, color, opacity: 1.0 }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GradientKind {
+    Linear { start: Vec2, end: Vec2 },
+    /// `focal` is where offset 0 sits; offset 1 is the circle around `center`.
+    Radial { center: Vec2, radius: f32, focal: Vec2 },
+    /// Sweeps counter-clockwise around `center`, offset 0 at `angle` radians from +x.
+    Conic { center: Vec2, angle: f32 },
+}
+
+/// How offsets outside [0, 1] map back onto the stops.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SpreadMode {
+    /// The nearest end stop continues outwards.
+    #[default]
+    Pad,
+    Repeat,
+    /// Repeats, every other copy mirrored.
+    Reflect,
+}
+
+impl SpreadMode {
+    pub fn apply(self, offset: f32) -> f32 {
+        match self {
+            SpreadMode::Pad => offset.clamp(0.0, 1.0),
+            SpreadMode::Repeat => offset - offset.floor(),
+            SpreadMode::Reflect => {
+                let t = offset.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            }
+        }
+    }
+}
+
+/// A color ramp over 2D space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    /// Sorted by offset.
+    pub stops: Vec<GradientStop>,
+    pub spread: SpreadMode,
+    /// Maps gradient space to the space of the shape it paints.
+    pub transform: Affine2,
+}
+
+impl Gradient {
+    pub fn linear(start: Vec2, end: Vec2) -> Self {
+        Gradient {
+            kind: GradientKind::Linear { start, end },
+            stops: vec![],
+            spread: SpreadMode::Pad,
+            transform: Affine2::IDENTITY,
+        }
+    }
+
+    pub fn radial(center: Vec2, radius: f32) -> Self {
+        Gradient {
+            kind: GradientKind::Radial { center, radius, focal: center },
+            stops: vec![],
+            spread: SpreadMode::Pad,
+            transform: Affine2::IDENTITY,
+        }
+    }
+
+    pub fn conic(center: Vec2, angle: f32) -> Self {
+        Gradient {
+            kind: GradientKind::Conic { center, angle },
+            stops: vec![],
+            spread: SpreadMode::Pad,
+            transform: Affine2::IDENTITY,
+        }
+    }
+
+    /// Adds a stop, keeping stops sorted; stops at the same offset keep insertion order.
+    pub fn add_stop(&mut self, stop: GradientStop) {
+        let index = self.stops.partition_point(|s| s.offset <= stop.offset);
+        self.stops.insert(index, stop);
+    }
+
+    pub fn with_stop(mut self, offset: f32, color: Color) -> Self {
+        self.add_stop(GradientStop::new(offset, color));
+        self
+    }
+
+    pub fn with_spread(mut self, spread: SpreadMode) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Affine2) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// A transform that squashes the gradient flat can't be undone; such a
+    /// gradient paints its last stop everywhere.
+    fn is_degenerate(&self) -> bool {
+        let determinant = self.transform.matrix2.determinant();
+        !determinant.is_finite() || determinant.abs() <= 1e-12
+    }
+
+    /// Ramp position of `point`, given in the painted shape's space, before the
+    /// spread mode is applied. Offset 1 under a degenerate transform.
+    pub fn offset_at(&self, point: Vec2) -> f32 {
+        if self.is_degenerate() {
+            return 1.0;
+        }
+        let p = self.transform.inverse().transform_point2(point);
+        match self.kind {
+            GradientKind::Linear { start, end } => {
+                let axis = end - start;
+                let length_squared = axis.length_squared();
+                if length_squared <= f32::EPSILON {
+                    return 1.0;
+                }
+                (p - start).dot(axis) / length_squared
+            }
+            GradientKind::Radial { center, radius, focal } => {
+                if radius <= f32::EPSILON {
+                    return 1.0;
+                }
+                // 求从焦点出发、经过 p 的射线与外圆的交点, 偏移量为 |p - focal| / |交点 - focal|
+                let dir = p - focal;
+                let distance = dir.length();
+                if distance <= f32::EPSILON {
+                    return 0.0;
+                }
+                let dir = dir / distance;
+                let to_focal = focal - center;
+                let b = to_focal.dot(dir);
+                let c = to_focal.length_squared() - radius * radius;
+                let edge = -b + (b * b - c).max(0.0).sqrt();
+                if edge <= f32::EPSILON {
+                    1.0
+                } else {
+                    distance / edge
+                }
+            }
+            GradientKind::Conic { center, angle } => {
+                let d = p - center;
+                if d.length_squared() <= f32::EPSILON * f32::EPSILON {
+                    return 0.0;
+                }
+                ((d.y.atan2(d.x) - angle) / TAU).rem_euclid(1.0)
+            }
+        }
+    }
+
+    /// Color and opacity at `point`, given in the painted shape's space.
+    pub fn sample(&self, point: Vec2) -> (Color, f32) {
+        if self.is_degenerate() {
+            return self.ramp(1.0);
+        }
+        self.ramp(self.spread.apply(self.offset_at(point)))
+    }
+
+    /// Color and opacity at `offset` along the stops.
+    fn ramp(&self, offset: f32) -> (Color, f32) {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return (Color::BLACK, 0.0);
+        };
+        if offset <= first.offset {
+            return (first.color, first.opacity);
+        }
+        if offset >= last.offset {
+            return (last.color, last.opacity);
+        }
+        let next = self.stops.partition_point(|s| s.offset <= offset);
+        let (a, b) = (&self.stops[next - 1], &self.stops[next]);
+        let span = b.offset - a.offset;
+        let t = if span > 0.0 { (offset - a.offset) / span } else { 1.0 };
+        (a.color + (b.color - a.color) * t, a.opacity + (b.opacity - a.opacity) * t)
+    }
+
+    /// Stops as the GPU gets them: at most `MAX_STOPS`, resampled evenly
+    /// between the first and last offsets when there are more.
+    fn gpu_stops(&self) -> Vec<GradientStop> {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return vec![];
+        };
+        if self.stops.len() <= MAX_STOPS {
+            return self.stops.clone();
+        }
+        log::warn!("gradient has {} stops, resampled to {}", self.stops.len(), MAX_STOPS);
+        (0..MAX_STOPS)
+            .map(|i| {
+                let offset = first.offset + (last.offset - first.offset) * i as f32 / (MAX_STOPS - 1) as f32;
+                let (color, opacity) = self.ramp(offset);
+                GradientStop { offset, color, opacity }
+            })
+            .collect()
+    }
+
+    /// The gradient as `mesh_shader.wgsl` evaluates it, per fragment.
+    pub fn to_raw(&self) -> PaintRaw {
+        if self.is_degenerate() {
+            // 起点终点重合的线性渐变处处取最后一个色标
+            let flat = Gradient {
+                kind: GradientKind::Linear { start: Vec2::ZERO, end: Vec2::ZERO },
+                stops: self.stops.last().copied().into_iter().collect(),
+                spread: SpreadMode::Pad,
+                transform: Affine2::IDENTITY,
+            };
+            return flat.to_raw();
+        }
+        let (kind, params) = match self.kind {
+            GradientKind::Linear { start, end } => (PAINT_LINEAR, [[start.x, start.y, end.x, end.y], [0.0; 4]]),
+            GradientKind::Radial { center, radius, focal } => {
+                (PAINT_RADIAL, [[center.x, center.y, focal.x, focal.y], [radius, 0.0, 0.0, 0.0]])
+            }
+            GradientKind::Conic { center, angle } => (PAINT_CONIC, [[center.x, center.y, angle, 0.0], [0.0; 4]]),
+        };
+        let spread = match self.spread {
+            SpreadMode::Pad => 0,
+            SpreadMode::Repeat => 1,
+            SpreadMode::Reflect => 2,
+        };
+        let stops = self.gpu_stops();
+        let inverse = self.transform.inverse();
+        let (x, y, t) = (inverse.matrix2.x_axis, inverse.matrix2.y_axis, inverse.translation);
+        let mut raw = PaintRaw {
+            header: [kind, spread, stops.len() as u32, 0],
+            to_gradient: [[x.x, x.y, y.x, y.y], [t.x, t.y, 0.0, 0.0]],
+            params,
+            ..PaintRaw::SOLID
+        };
+        for (i, stop) in stops.iter().enumerate() {
+            let color = stop.color.to_array();
+            raw.colors[i] = [color[0], color[1], color[2], stop.opacity];
+            raw.offsets[i / 4][i % 4] = stop.offset;
+        }
+        raw
+    }
+}
+
+/// What the inside of a fill or stroke is painted with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    Solid(Color),
+    Gradient(Gradient),
+}
+
+impl Paint {
+    /// The tint a shape painted this way multiplies the paint with.
+    pub fn tint(&self) -> Color {
+        match self {
+            Paint::Solid(color) => *color,
+            Paint::Gradient(_) => Color::WHITE,
+        }
+    }
+
+    pub fn to_raw(&self) -> PaintRaw {
+        match self {
+            Paint::Solid(_) => PaintRaw::SOLID,
+            Paint::Gradient(gradient) => gradient.to_raw(),
+        }
+    }
+}
+
+const PAINT_LINEAR: u32 = 1;
+const PAINT_RADIAL: u32 = 2;
+const PAINT_CONIC: u32 = 3;
+
+/// `Paint` in `mesh_shader.wgsl`, bound as a uniform at group 1 of the shape pipeline.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PaintRaw {
+    /// Kind (0 solid), spread mode, stop count.
+    pub header: [u32; 4],
+    /// Shape space to gradient space: matrix columns, then translation.
+    pub to_gradient: [[f32; 4]; 2],
+    pub params: [[f32; 4]; 2],
+    /// Linear rgb and opacity.
+    pub colors: [[f32; 4]; MAX_STOPS],
+    pub offsets: [[f32; 4]; MAX_STOPS / 4],
+}
+
+impl PaintRaw {
+    /// Paints white, leaving the vertex and shape colors as they are.
+    pub const SOLID: PaintRaw = PaintRaw {
+        header: [0; 4],
+        to_gradient: [[1.0, 0.0, 0.0, 1.0], [0.0; 4]],
+        params: [[0.0; 4]; 2],
+        colors: [[0.0; 4]; MAX_STOPS],
+        offsets: [[0.0; 4]; MAX_STOPS / 4],
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `paint_color` from `mesh_shader.wgsl`, copied by hand and run over the
+    /// uploaded data. The tests check the upload against this copy, not the
+    /// shader itself, so keep the two in step when either changes.
+    fn shade(raw: &PaintRaw, local: Vec2) -> [f32; 4] {
+        let count = (raw.header[2] as usize).min(MAX_STOPS);
+        if raw.header[0] == 0 {
+            return [1.0; 4];
+        }
+        if count == 0 {
+            return [0.0; 4];
+        }
+        let m = raw.to_gradient;
+        let p = Vec2::new(m[0][0], m[0][1]) * local.x + Vec2::new(m[0][2], m[0][3]) * local.y + Vec2::new(m[1][0], m[1][1]);
+        let params = raw.params;
+        let offset = match raw.header[0] {
+            PAINT_LINEAR => {
+                let start = Vec2::new(params[0][0], params[0][1]);
+                let axis = Vec2::new(params[0][2], params[0][3]) - start;
+                if axis.length_squared() <= 1e-12 { 1.0 } else { (p - start).dot(axis) / axis.length_squared() }
+            }
+            PAINT_RADIAL => {
+                let (center, focal, radius) = (Vec2::new(params[0][0], params[0][1]), Vec2::new(params[0][2], params[0][3]), params[1][0]);
+                let distance = (p - focal).length();
+                if radius <= 1e-6 {
+                    1.0
+                } else if distance <= 1e-6 {
+                    0.0
+                } else {
+                    let dir = (p - focal) / distance;
+                    let to_focal = focal - center;
+                    let b = to_focal.dot(dir);
+                    let c = to_focal.length_squared() - radius * radius;
+                    let edge = -b + (b * b - c).max(0.0).sqrt();
+                    if edge <= 1e-6 { 1.0 } else { distance / edge }
+                }
+            }
+            _ => {
+                let d = p - Vec2::new(params[0][0], params[0][1]);
+                if d.length_squared() <= 1e-12 { 0.0 } else { let turns = (d.y.atan2(d.x) - params[0][2]) / TAU; turns - turns.floor() }
+            }
+        };
+        let offset = match raw.header[1] {
+            1 => offset - offset.floor(),
+            2 => {
+                let t = offset - 2.0 * (offset * 0.5).floor();
+                if t > 1.0 { 2.0 - t } else { t }
+            }
+            _ => offset.clamp(0.0, 1.0),
+        };
+        let stop_offset = |i: usize| raw.offsets[i / 4][i % 4];
+        if offset <= stop_offset(0) {
+            return raw.colors[0];
+        }
+        for i in 1..count {
+            let next = stop_offset(i);
+            if offset < next {
+                let prev = stop_offset(i - 1);
+                let span = next - prev;
+                let t = if span > 0.0 { (offset - prev) / span } else { 1.0 };
+                let (a, b) = (raw.colors[i - 1], raw.colors[i]);
+                return [0, 1, 2, 3].map(|c| a[c] + (b[c] - a[c]) * t);
+            }
+        }
+        raw.colors[count - 1]
+    }
+
+    fn assert_matches(gradient: &Gradient, tolerance: f32) {
+        let raw = gradient.to_raw();
+        for y in -4..=4 {
+            for x in -4..=4 {
+                let point = Vec2::new(x as f32, y as f32) * 0.37 + Vec2::new(0.011, 0.007);
+                let (color, opacity) = gradient.sample(point);
+                let cpu = [color.to_array()[0], color.to_array()[1], color.to_array()[2], opacity];
+                let shaded = shade(&raw, point);
+                for c in 0..4 {
+                    assert!((cpu[c] - shaded[c]).abs() <= tolerance, "{:?} at {}: {:?} != {:?}", gradient.kind, point, cpu, shaded);
+                }
+            }
+        }
+    }
+
+    fn ramp(gradient: Gradient) -> Gradient {
+        gradient
+            .with_stop(0.0, Color::new(1.0, 0.0, 0.0))
+            .with_stop(0.4, Color::new(0.0, 1.0, 0.0))
+            .with_stop(1.0, Color::new(0.0, 0.0, 1.0))
+    }
+
+    #[test]
+    fn spread_modes_fold_offsets_into_the_ramp() {
+        let cases = [(-0.25, [0.0, 0.75, 0.25]), (0.5, [0.5, 0.5, 0.5]), (1.25, [1.0, 0.25, 0.75]), (2.0, [1.0, 0.0, 0.0])];
+        for (offset, [pad, repeat, reflect]) in cases {
+            assert_eq!(SpreadMode::Pad.apply(offset), pad);
+            assert_eq!(SpreadMode::Repeat.apply(offset), repeat);
+            assert_eq!(SpreadMode::Reflect.apply(offset), reflect);
+        }
+    }
+
+    #[test]
+    fn offsets_follow_the_gradient_kind() {
+        let linear = Gradient::linear(Vec2::new(1.0, 0.0), Vec2::new(3.0, 0.0));
+        assert_eq!(linear.offset_at(Vec2::new(2.0, 5.0)), 0.5);
+        assert_eq!(linear.offset_at(Vec2::new(-1.0, 0.0)), -1.0);
+        let radial = Gradient::radial(Vec2::ZERO, 2.0);
+        assert_eq!(radial.offset_at(Vec2::new(0.0, 1.0)), 0.5);
+        let conic = Gradient::conic(Vec2::ZERO, 0.0);
+        assert!((conic.offset_at(Vec2::new(0.0, 1.0)) - 0.25).abs() < 1e-6);
+        assert!((conic.offset_at(Vec2::new(0.0, -1.0)) - 0.75).abs() < 1e-6);
+        // 变换把形状空间映射回渐变空间
+        let moved = linear.with_transform(Affine2::from_translation(Vec2::new(10.0, 0.0)));
+        assert_eq!(moved.offset_at(Vec2::new(12.0, 0.0)), 0.5);
+    }
+
+    #[test]
+    fn uploaded_data_paints_what_sample_says() {
+        let transform = Affine2::from_scale_angle_translation(Vec2::new(1.5, 0.5), 0.3, Vec2::new(0.2, -0.1));
+        for spread in [SpreadMode::Pad, SpreadMode::Repeat, SpreadMode::Reflect] {
+            let gradients = [
+                Gradient::linear(Vec2::new(-1.0, -0.5), Vec2::new(1.0, 0.5)),
+                Gradient::radial(Vec2::new(0.1, 0.2), 1.2),
+                Gradient::conic(Vec2::new(0.05, -0.05), 0.7),
+            ];
+            for gradient in gradients {
+                assert_matches(&ramp(gradient.clone()).with_spread(spread), 1e-4);
+                assert_matches(&ramp(gradient).with_spread(spread).with_transform(transform), 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_transforms_paint_the_last_stop() {
+        let flat = Affine2::from_mat2(glam::Mat2::from_cols(Vec2::new(1.0, 0.0), Vec2::ZERO));
+        let gradient = ramp(Gradient::linear(Vec2::ZERO, Vec2::X)).with_spread(SpreadMode::Repeat).with_transform(flat);
+        assert_eq!(gradient.sample(Vec2::new(0.3, 0.3)), (Color::new(0.0, 0.0, 1.0), 1.0));
+        assert_matches(&gradient, 0.0);
+        assert!(gradient.to_raw().to_gradient.iter().flatten().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn extra_stops_are_resampled() {
+        let mut gradient = Gradient::linear(Vec2::new(-1.5, 0.0), Vec2::new(1.5, 0.0));
+        for i in 0..=40 {
+            let t = i as f32 / 40.0;
+            gradient.add_stop(GradientStop::new(t, Color::new(t, 1.0 - t, (t * 7.0).sin().abs())));
+        }
+        let raw = gradient.to_raw();
+        assert_eq!(raw.header[2], MAX_STOPS as u32);
+        assert_eq!((raw.offsets[0][0], raw.offsets[3][3]), (0.0, 1.0));
+        assert_eq!(raw.colors[MAX_STOPS - 1], [1.0, 0.0, 7.0f32.sin().abs(), 1.0]);
+        // 中间的色标被重新采样, 只会近似
+        assert_matches(&gradient, 0.1);
+    }
+}
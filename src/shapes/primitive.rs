@@ -0,0 +1,474 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+use glam::{Mat4, Vec2};
+use lyon::algorithms::rounded_polygon;
+use lyon::math::{point, vector, Angle, Box2D, Point};
+use lyon::path::builder::BorderRadii;
+use lyon::path::{FillRule, Path, Polygon, Winding, NO_ATTRIBUTES};
+use lyon::tessellation::VertexBuffers;
+use super::circle::{circle_path, IVertex};
+use super::mesh::MeshShape;
+use super::paint::Paint;
+use super::stroke::StrokeStyle;
+use super::{Color, Shadow, Shape, ShapeLayouts};
+
+/// A built-in outline, centred on its local origin.
+pub trait Primitive {
+    fn path(&self) -> Path;
+
+    /// Open primitives have no inside and are drawn as strokes.
+    fn is_closed(&self) -> bool {
+        true
+    }
+}
+
+fn polygon_path(points: &[Point], closed: bool) -> Path {
+    let mut builder = Path::builder();
+    builder.add_polygon(Polygon { points, closed });
+    builder.build()
+}
+
+/// `count` points evenly around a circle, the first straight up.
+fn ring(count: u32, radius: impl Fn(u32) -> f32) -> Vec<Point> {
+    (0..count)
+        .map(|i| {
+            let angle = FRAC_PI_2 + TAU * i as f32 / count as f32;
+            let p = Vec2::from_angle(angle) * radius(i);
+            point(p.x, p.y)
+        })
+        .collect()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Circle {
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn new(radius: f32) -> Self {
+        Circle { radius }
+    }
+}
+
+impl Primitive for Circle {
+    fn path(&self) -> Path {
+        circle_path(self.radius)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ellipse {
+    pub radii: Vec2,
+}
+
+impl Ellipse {
+    pub fn new(radii: Vec2) -> Self {
+        Ellipse { radii }
+    }
+}
+
+impl Primitive for Ellipse {
+    fn path(&self) -> Path {
+        let mut builder = Path::builder();
+        builder.add_ellipse(point(0.0, 0.0), vector(self.radii.x, self.radii.y), Angle::zero(), Winding::Positive);
+        builder.build()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub size: Vec2,
+}
+
+impl Rect {
+    pub fn new(size: Vec2) -> Self {
+        Rect { size }
+    }
+}
+
+impl Primitive for Rect {
+    fn path(&self) -> Path {
+        let half = self.size.abs() / 2.0;
+        let mut builder = Path::builder();
+        builder.add_rectangle(&Box2D::new(point(-half.x, -half.y), point(half.x, half.y)), Winding::Positive);
+        builder.build()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RoundedRect {
+    pub size: Vec2,
+    /// Clamped to half the shorter side.
+    pub corner_radius: f32,
+}
+
+impl RoundedRect {
+    pub fn new(size: Vec2, corner_radius: f32) -> Self {
+        RoundedRect { size, corner_radius }
+    }
+}
+
+impl Primitive for RoundedRect {
+    fn path(&self) -> Path {
+        let half = self.size.abs() / 2.0;
+        let radius = self.corner_radius.clamp(0.0, half.min_element());
+        let mut builder = Path::builder();
+        builder.add_rounded_rectangle(
+            &Box2D::new(point(-half.x, -half.y), point(half.x, half.y)),
+            &BorderRadii::new(radius),
+            Winding::Positive,
+        );
+        builder.build()
+    }
+}
+
+/// Equal sides with a corner straight up; many sides approximate a circle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RegularPolygon {
+    pub sides: u32,
+    /// Centre to corner.
+    pub radius: f32,
+}
+
+impl RegularPolygon {
+    pub fn new(sides: u32, radius: f32) -> Self {
+        RegularPolygon { sides, radius }
+    }
+}
+
+impl Primitive for RegularPolygon {
+    fn path(&self) -> Path {
+        polygon_path(&ring(self.sides.max(3), |_| self.radius), true)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Star {
+    pub points: u32,
+    pub outer_radius: f32,
+    pub inner_radius: f32,
+}
+
+impl Star {
+    pub fn new(points: u32, outer_radius: f32, inner_radius: f32) -> Self {
+        Star { points, outer_radius, inner_radius }
+    }
+}
+
+impl Primitive for Star {
+    fn path(&self) -> Path {
+        // 外顶点和内顶点交替, 第一个尖角朝上
+        let corners = ring(self.points.max(2) * 2, |i| {
+            if i % 2 == 0 { self.outer_radius } else { self.inner_radius }
+        });
+        polygon_path(&corners, true)
+    }
+}
+
+/// Points along +x, tail to tip `length` long.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Arrow {
+    pub length: f32,
+    pub shaft_width: f32,
+    pub head_length: f32,
+    pub head_width: f32,
+    /// Rounds every corner, tip included.
+    pub corner_radius: f32,
+}
+
+impl Arrow {
+    pub fn new(length: f32, shaft_width: f32) -> Self {
+        Arrow {
+            length,
+            shaft_width,
+            head_length: length * 0.4,
+            head_width: shaft_width * 3.0,
+            corner_radius: 0.0,
+        }
+    }
+
+    pub fn with_head(mut self, length: f32, width: f32) -> Self {
+        self.head_length = length;
+        self.head_width = width;
+        self
+    }
+
+    pub fn with_corner_radius(mut self, radius: f32) -> Self {
+        self.corner_radius = radius;
+        self
+    }
+}
+
+impl Primitive for Arrow {
+    fn path(&self) -> Path {
+        let tip = self.length / 2.0;
+        let tail = -tip;
+        let neck = tip - self.head_length.clamp(0.0, self.length);
+        let (shaft, head) = (self.shaft_width / 2.0, self.head_width / 2.0);
+        let points = [
+            point(tail, -shaft),
+            point(neck, -shaft),
+            point(neck, -head),
+            point(tip, 0.0),
+            point(neck, head),
+            point(neck, shaft),
+            point(tail, shaft),
+        ];
+        if self.corner_radius <= 0.0 {
+            return polygon_path(&points, true);
+        }
+        let mut builder = Path::builder();
+        rounded_polygon::add_rounded_polygon(
+            &mut builder,
+            Polygon { points: &points, closed: true },
+            self.corner_radius,
+            NO_ATTRIBUTES,
+        );
+        builder.build()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Line {
+    pub from: Vec2,
+    pub to: Vec2,
+}
+
+impl Line {
+    pub fn new(from: Vec2, to: Vec2) -> Self {
+        Line { from, to }
+    }
+}
+
+impl Primitive for Line {
+    fn path(&self) -> Path {
+        polygon_path(&[point(self.from.x, self.from.y), point(self.to.x, self.to.y)], false)
+    }
+
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+/// The vertex color every vertex shares, if they all do.
+fn uniform_tint(geometry: &VertexBuffers<IVertex, u16>) -> Option<[f32; 3]> {
+    let first = geometry.vertices.first()?.color;
+    geometry.vertices.iter().all(|v| v.color == first).then_some(first)
+}
+
+/// A primitive drawn through a `MeshShape`, which owns the tessellated geometry
+/// and GPU buffers. Changing the primitive re-tessellates it.
+pub struct PrimitiveShape<P: Primitive> {
+    primitive: P,
+    mesh: MeshShape,
+}
+
+impl<P: Primitive> PrimitiveShape<P> {
+    /// Fills closed primitives and strokes open ones with the default stroke.
+    pub fn new(primitive: P, color: Color) -> Self {
+        if !primitive.is_closed() {
+            return Self::outlined(primitive, StrokeStyle::default(), color);
+        }
+        let mesh = MeshShape::fill(&primitive.path(), FillRule::NonZero, color);
+        PrimitiveShape { primitive, mesh }
+    }
+
+    pub fn outlined(primitive: P, style: StrokeStyle, color: Color) -> Self {
+        let mesh = MeshShape::stroke(primitive.path(), style, color);
+        PrimitiveShape { primitive, mesh }
+    }
+
+    pub fn with_paint(mut self, paint: Paint) -> Self {
+        self.mesh.set_paint(paint);
+        self
+    }
+
+    pub fn primitive(&self) -> &P {
+        &self.primitive
+    }
+
+    /// Changes the primitive, e.g. a star's point count, and re-tessellates.
+    /// The shape keeps its color, and a tint baked into its vertices carries
+    /// over to the new ones.
+    pub fn update(&mut self, update: impl FnOnce(&mut P)) {
+        update(&mut self.primitive);
+        let color = self.mesh.color();
+        let tint = uniform_tint(self.mesh.geometry());
+        self.mesh.set_path(self.primitive.path(), FillRule::NonZero);
+        if let Some(tint) = tint.filter(|tint| *tint != [1.0, 1.0, 1.0]) {
+            let mut geometry = self.mesh.geometry().clone();
+            geometry.vertices.iter_mut().for_each(|v| v.color = tint);
+            self.mesh.set_geometry(&geometry);
+        }
+        self.mesh.set_color(color);
+    }
+
+    pub fn mesh(&self) -> &MeshShape {
+        &self.mesh
+    }
+
+    pub fn mesh_mut(&mut self) -> &mut MeshShape {
+        &mut self.mesh
+    }
+
+    pub fn model_matrix(&self) -> Mat4 {
+        self.mesh.model_matrix()
+    }
+}
+
+impl<P: Primitive> Shape for PrimitiveShape<P> {
+    fn set_color(&mut self, color: Color) {
+        self.mesh.set_color(color);
+    }
+
+    fn set_paint(&mut self, paint: Paint) {
+        self.mesh.set_paint(paint);
+    }
+
+    fn set_shadow(&mut self, shadow: Shadow) {
+        self.mesh.set_shadow(shadow);
+    }
+
+    fn shadow(&self) -> Option<Shadow> {
+        self.mesh.shadow()
+    }
+
+    fn set_shadow_color(&mut self, color: Color) {
+        self.mesh.set_shadow_color(color);
+    }
+
+    fn set_shadow_offset(&mut self, offset: Vec2) {
+        self.mesh.set_shadow_offset(offset);
+    }
+
+    fn set_shadow_blur(&mut self, blur_radius: f32) {
+        self.mesh.set_shadow_blur(blur_radius);
+    }
+
+    fn set_shadow_opacity(&mut self, opacity: f32) {
+        self.mesh.set_shadow_opacity(opacity);
+    }
+
+    fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        self.mesh.bounds()
+    }
+
+    fn set_position(&mut self, position: Vec2) {
+        self.mesh.set_position(position);
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.mesh.set_rotation(rotation);
+    }
+
+    fn set_scale(&mut self, scale: Vec2) {
+        self.mesh.set_scale(scale);
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.mesh.set_opacity(opacity);
+    }
+
+    fn set_anchor(&mut self, anchor: Vec2) {
+        self.mesh.set_anchor(anchor);
+    }
+
+    fn set_stroke_width(&mut self, width: f32) {
+        self.mesh.set_stroke_width(width);
+    }
+
+    fn set_dash_offset(&mut self, offset: f32) {
+        self.mesh.set_dash_offset(offset);
+    }
+
+    fn set_trim_start(&mut self, start: f32) {
+        self.mesh.set_trim_start(start);
+    }
+
+    fn set_trim_end(&mut self, end: f32) {
+        self.mesh.set_trim_end(end);
+    }
+
+    fn set_geometry(&mut self, geometry: &VertexBuffers<IVertex, u16>) {
+        self.mesh.set_geometry(geometry);
+    }
+
+    fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, layouts: &ShapeLayouts) {
+        self.mesh.prepare(device, queue, layouts);
+    }
+
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        self.mesh.draw(render_pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon::path::PathEvent;
+    use super::*;
+
+    /// Where each edge of the outline starts, in order.
+    fn corners(path: &Path) -> Vec<Vec2> {
+        path.iter()
+            .filter_map(|event| match event {
+                PathEvent::Begin { at } => Some(Vec2::new(at.x, at.y)),
+                PathEvent::Line { to, .. } => Some(Vec2::new(to.x, to.y)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn star_points_alternate_from_the_top() {
+        let corners = corners(&Star::new(5, 2.0, 1.0).path());
+        assert_eq!(corners.len(), 10);
+        assert!(corners[0].abs_diff_eq(Vec2::new(0.0, 2.0), 1e-6));
+        for (i, corner) in corners.iter().enumerate() {
+            let expected = if i % 2 == 0 { 2.0 } else { 1.0 };
+            assert!((corner.length() - expected).abs() < 1e-5, "corner {} at {:?}", i, corner);
+        }
+        // 逆时针排列
+        assert!(corners[1].x < 0.0);
+    }
+
+    #[test]
+    fn arrow_runs_tail_to_tip() {
+        let corners = corners(&Arrow::new(4.0, 0.5).with_head(1.0, 2.0).path());
+        let expected = [(-2.0, -0.25), (1.0, -0.25), (1.0, -1.0), (2.0, 0.0), (1.0, 1.0), (1.0, 0.25), (-2.0, 0.25)];
+        assert_eq!(corners, expected.iter().map(|&(x, y)| Vec2::new(x, y)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rounded_rect_radius_is_clamped_to_the_shorter_side() {
+        let shape = PrimitiveShape::new(RoundedRect::new(Vec2::new(4.0, 2.0), 10.0), Color::WHITE);
+        let (min, max) = shape.bounds().unwrap();
+        assert!(min.abs_diff_eq(Vec2::new(-2.0, -1.0), 1e-3) && max.abs_diff_eq(Vec2::new(2.0, 1.0), 1e-3));
+        // 半径被限制为 1, 两端是半圆, 角上没有顶点
+        let vertices = &shape.mesh().geometry().vertices;
+        for v in vertices.iter().filter(|v| v.position[0].abs() > 1.0) {
+            let centre = Vec2::new(v.position[0].signum(), 0.0);
+            assert!(Vec2::new(v.position[0], v.position[1]).distance(centre) <= 1.0 + 1e-3, "{:?}", v.position);
+        }
+    }
+
+    #[test]
+    fn lines_are_stroked() {
+        let line = PrimitiveShape::new(Line::new(Vec2::ZERO, Vec2::new(1.0, 0.0)), Color::WHITE);
+        assert!(line.mesh().stroke_style().is_some());
+        assert!(!line.mesh().geometry().indices.is_empty());
+        assert!(PrimitiveShape::new(Rect::new(Vec2::ONE), Color::WHITE).mesh().stroke_style().is_none());
+    }
+
+    #[test]
+    fn update_keeps_the_color_and_tint() {
+        let mut shape = PrimitiveShape::new(Star::new(5, 1.0, 0.5), Color::new(1.0, 0.0, 0.0));
+        let mut geometry = shape.mesh().geometry().clone();
+        geometry.vertices.iter_mut().for_each(|v| v.color = [0.0, 1.0, 0.0]);
+        shape.set_geometry(&geometry);
+
+        shape.update(|star| star.points = 7);
+        assert_eq!(shape.primitive().points, 7);
+        assert_eq!(shape.mesh().color(), Color::new(1.0, 0.0, 0.0));
+        assert!(shape.mesh().geometry().vertices.len() > geometry.vertices.len());
+        assert!(shape.mesh().geometry().vertices.iter().all(|v| v.color == [0.0, 1.0, 0.0]));
+    }
+}
@@ -0,0 +1,382 @@
+use std::mem;
+use glam::{Mat4, Vec2};
+use lyon::tessellation::VertexBuffers;
+use wgpu::util::DeviceExt;
+use crate::animation::skeleton::Skeleton;
+use super::circle::IVertex;
+use super::mesh::{ShapeRaw, ShapeTransform};
+use super::{Color, Shadow, Shape, ShapeLayouts};
+
+/// Most bones one skinned mesh can have; the size of the bone array in `skin_shader.wgsl`.
+pub const MAX_BONES: usize = 64;
+
+/// Bones weighted per vertex; weights that don't fit are dropped.
+pub const BONES_PER_VERTEX: usize = 4;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub bones: [u32; 4],
+    /// Sum to 1.
+    pub weights: [f32; 4],
+}
+
+impl SkinVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<SkinVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Uint32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 10]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Splits every triangle into four, level by level, until no edge is longer
+/// than `max_edge`, so the mesh has vertices to bend at. Tessellated fills only
+/// have vertices on their outline. Stops early rather than overflow u16 indices.
+pub fn subdivide(geometry: &VertexBuffers<IVertex, u16>, max_edge: f32) -> VertexBuffers<IVertex, u16> {
+    let mut vertices = geometry.vertices.clone();
+    let mut indices = geometry.indices.clone();
+    let position = |v: &IVertex| Vec2::new(v.position[0], v.position[1]);
+    loop {
+        let longest = indices
+            .chunks_exact(3)
+            .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .map(|(a, b)| position(&vertices[a as usize]).distance(position(&vertices[b as usize])))
+            .fold(0.0, f32::max);
+        // 每层细分最多新增 1.5 倍三角形数量的顶点
+        if longest <= max_edge || max_edge <= 0.0 || vertices.len() + indices.len() / 2 > u16::MAX as usize {
+            break;
+        }
+        let mut midpoints = std::collections::HashMap::new();
+        let mut midpoint = |a: u16, b: u16, vertices: &mut Vec<IVertex>| -> u16 {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let (va, vb) = (vertices[a as usize], vertices[b as usize]);
+                let mix = |x: [f32; 3], y: [f32; 3]| [(x[0] + y[0]) / 2.0, (x[1] + y[1]) / 2.0, (x[2] + y[2]) / 2.0];
+                vertices.push(IVertex { position: mix(va.position, vb.position), color: mix(va.color, vb.color) });
+                (vertices.len() - 1) as u16
+            })
+        };
+        let mut split = Vec::with_capacity(indices.len() * 4);
+        for t in indices.chunks_exact(3) {
+            let ab = midpoint(t[0], t[1], &mut vertices);
+            let bc = midpoint(t[1], t[2], &mut vertices);
+            let ca = midpoint(t[2], t[0], &mut vertices);
+            split.extend_from_slice(&[t[0], ab, ca, ab, t[1], bc, ca, bc, t[2], ab, bc, ca]);
+        }
+        indices = split;
+    }
+    let mut subdivided = VertexBuffers::new();
+    subdivided.vertices = vertices;
+    subdivided.indices = indices;
+    subdivided
+}
+
+fn segment_distance(p: Vec2, (a, b): (Vec2, Vec2)) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_squared() > 0.0 { ((p - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0) } else { 0.0 };
+    p.distance(a + ab * t)
+}
+
+/// Weights each vertex to the nearest bones by distance to their bind-pose
+/// segments. Good enough for limbs and tails; hand-painted weights can be passed
+/// to `SkinnedMesh::new` instead.
+pub fn auto_weights(geometry: &VertexBuffers<IVertex, u16>, skeleton: &Skeleton) -> VertexBuffers<SkinVertex, u16> {
+    let segments = skeleton.rest_segments();
+    let mut skinned = VertexBuffers::new();
+    skinned.indices.clone_from(&geometry.indices);
+    skinned.vertices = geometry
+        .vertices
+        .iter()
+        .map(|v| {
+            let p = Vec2::new(v.position[0], v.position[1]);
+            let mut nearest = segments
+                .iter()
+                .enumerate()
+                // 偏移量决定关节附近的过渡宽度, 以视图坐标为单位
+                .map(|(bone, segment)| (bone, (segment_distance(p, *segment) + 0.05).powi(-4)))
+                .collect::<Vec<_>>();
+            nearest.sort_by(|a, b| b.1.total_cmp(&a.1));
+            nearest.truncate(BONES_PER_VERTEX);
+            let total = nearest.iter().map(|(_, w)| w).sum::<f32>();
+            let mut bones = [0u32; 4];
+            let mut weights = [0.0f32; 4];
+            for (slot, (bone, weight)) in nearest.into_iter().enumerate() {
+                bones[slot] = bone as u32;
+                weights[slot] = weight / total;
+            }
+            if total <= 0.0 {
+                weights = [1.0, 0.0, 0.0, 0.0];
+            }
+            SkinVertex { position: v.position, color: v.color, bones, weights }
+        })
+        .collect();
+    skinned
+}
+
+struct GpuSkin {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    shape_buffer: wgpu::Buffer,
+    bone_buffer: wgpu::Buffer,
+    bone_bg: wgpu::BindGroup,
+    index_count: u32,
+}
+
+/// A mesh deformed by a `Skeleton` with linear-blend skinning on the GPU.
+///
+/// Vertices are given in skeleton space in the bind pose. The mesh as a whole
+/// moves with its own transform, like a `MeshShape`; the bones are posed through
+/// `skeleton_mut`. Draw it with the skin pipeline, see `skin_shader.wgsl`.
+pub struct SkinnedMesh {
+    geometry: VertexBuffers<SkinVertex, u16>,
+    skeleton: Skeleton,
+    pub transform: ShapeTransform,
+    color: Color,
+    opacity: f32,
+    gpu: Option<GpuSkin>,
+}
+
+impl SkinnedMesh {
+    pub fn new(geometry: VertexBuffers<SkinVertex, u16>, skeleton: Skeleton) -> Self {
+        if skeleton.bones().len() > MAX_BONES {
+            log::warn!("skeleton has {} bones, only the first {} deform the mesh", skeleton.bones().len(), MAX_BONES);
+        }
+        SkinnedMesh {
+            geometry,
+            skeleton,
+            transform: ShapeTransform::default(),
+            color: Color::WHITE,
+            opacity: 1.0,
+            gpu: None,
+        }
+    }
+
+    /// Skins `geometry` to `skeleton` with `auto_weights`.
+    pub fn bind(geometry: &VertexBuffers<IVertex, u16>, skeleton: Skeleton) -> Self {
+        Self::new(auto_weights(geometry, &skeleton), skeleton)
+    }
+
+    pub fn geometry(&self) -> &VertexBuffers<SkinVertex, u16> {
+        &self.geometry
+    }
+
+    pub fn skeleton(&self) -> &Skeleton {
+        &self.skeleton
+    }
+
+    pub fn skeleton_mut(&mut self) -> &mut Skeleton {
+        &mut self.skeleton
+    }
+
+    pub fn to_raw(&self) -> ShapeRaw {
+        let color = self.color.to_array();
+        ShapeRaw {
+            model: self.transform.matrix().to_cols_array_2d(),
+            color: [color[0], color[1], color[2], self.opacity],
+        }
+    }
+
+    fn bone_data(&self) -> Vec<[[f32; 4]; 4]> {
+        let mut matrices = self.skeleton.skin_matrices();
+        matrices.resize(MAX_BONES, Mat4::IDENTITY);
+        matrices.iter().map(Mat4::to_cols_array_2d).collect()
+    }
+
+    /// Layout of the bone matrices, bind group 1 of the skin pipeline.
+    pub fn bone_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bone Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+}
+
+impl Shape for SkinnedMesh {
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_shadow(&mut self, _shadow: Shadow) {}
+
+    fn set_position(&mut self, position: Vec2) {
+        self.transform.position = position;
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.transform.rotation = rotation;
+    }
+
+    fn set_scale(&mut self, scale: Vec2) {
+        self.transform.scale = scale;
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+
+    fn set_anchor(&mut self, anchor: Vec2) {
+        self.transform.anchor = anchor;
+    }
+
+    fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, _layouts: &ShapeLayouts) {
+        let raw = self.to_raw();
+        let bones = self.bone_data();
+        if let Some(gpu) = &self.gpu {
+            queue.write_buffer(&gpu.shape_buffer, 0, bytemuck::cast_slice(&[raw]));
+            queue.write_buffer(&gpu.bone_buffer, 0, bytemuck::cast_slice(&bones));
+            return;
+        }
+        let mut indices = self.geometry.indices.clone();
+        if indices.len() % 2 == 1 {
+            indices.push(0);
+        }
+        let bone_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bone Buffer"),
+            contents: bytemuck::cast_slice(&bones),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bone_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bone Bind Group"),
+            layout: &Self::bone_layout(device),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: bone_buffer.as_entire_binding(),
+            }],
+        });
+        self.gpu = Some(GpuSkin {
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Skin Vertex Buffer"),
+                contents: bytemuck::cast_slice(&self.geometry.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Skin Index Buffer"),
+                contents: bytemuck::cast_slice(&indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            shape_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Skin Shape Buffer"),
+                contents: bytemuck::cast_slice(&[raw]),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            }),
+            bone_buffer,
+            bone_bg,
+            index_count: self.geometry.indices.len() as u32,
+        });
+    }
+
+    /// Expects the skin pipeline to be bound, with the view matrix at group 0.
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        let Some(gpu) = &self.gpu else {
+            return;
+        };
+        if gpu.index_count == 0 {
+            return;
+        }
+        render_pass.set_bind_group(1, &gpu.bone_bg, &[]);
+        render_pass.set_vertex_buffer(0, gpu.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, gpu.shape_buffer.slice(..));
+        render_pass.set_index_buffer(gpu.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..gpu.index_count, 0, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::skeleton::BoneTransform;
+
+    fn triangle(size: f32) -> VertexBuffers<IVertex, u16> {
+        let mut geometry = VertexBuffers::new();
+        geometry.vertices = [[0.0, 0.0], [size, 0.0], [0.0, size]]
+            .iter()
+            .map(|p| IVertex { position: [p[0], p[1], 0.0], color: [1.0; 3] })
+            .collect();
+        geometry.indices = vec![0, 1, 2];
+        geometry
+    }
+
+    fn longest_edge(geometry: &VertexBuffers<IVertex, u16>) -> f32 {
+        let position = |i: u16| Vec2::new(geometry.vertices[i as usize].position[0], geometry.vertices[i as usize].position[1]);
+        geometry
+            .indices
+            .chunks_exact(3)
+            .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .map(|(a, b)| position(a).distance(position(b)))
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn subdivide_splits_until_edges_fit() {
+        let subdivided = subdivide(&triangle(1.0), 0.3);
+        assert!(longest_edge(&subdivided) <= 0.3);
+        // 三层细分: 64 个三角形, 45 个顶点, 相邻三角形共用中点
+        assert_eq!(subdivided.indices.len(), 64 * 3);
+        assert_eq!(subdivided.vertices.len(), 45);
+
+        assert_eq!(subdivide(&triangle(1.0), 2.0).indices, vec![0, 1, 2]);
+        assert_eq!(subdivide(&triangle(1.0), 0.0).indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn subdivide_stops_before_u16_indices_overflow() {
+        let subdivided = subdivide(&triangle(1.0), 1e-6);
+        assert!(subdivided.vertices.len() <= u16::MAX as usize + 1);
+        assert!(subdivided.indices.iter().all(|&i| (i as usize) < subdivided.vertices.len()));
+        assert!(longest_edge(&subdivided) > 1e-6);
+    }
+
+    #[test]
+    fn auto_weights_sum_to_one_over_the_nearest_bones() {
+        let mut skeleton = Skeleton::new();
+        for i in 0..6usize {
+            let parent = i.checked_sub(1);
+            skeleton.add_bone(&format!("bone{}", i), parent, BoneTransform::new(Vec2::new(0.1, 0.0), 0.0), 0.1);
+        }
+        let skinned = auto_weights(&subdivide(&triangle(0.8), 0.1), &skeleton);
+        for vertex in &skinned.vertices {
+            assert!((vertex.weights.iter().sum::<f32>() - 1.0).abs() < 1e-5, "{:?}", vertex.weights);
+            assert!(vertex.bones.iter().all(|&bone| (bone as usize) < 6));
+            // 只保留最近的 BONES_PER_VERTEX 根骨骼, 权重从大到小
+            assert!(vertex.weights.windows(2).all(|w| w[0] >= w[1]));
+        }
+
+        // 骨骼末端附近的顶点主要跟最后一根骨骼
+        let distance = |v: &SkinVertex| Vec2::new(v.position[0], v.position[1]).distance(Vec2::new(0.65, 0.0));
+        let tip = skinned.vertices.iter().min_by(|a, b| distance(a).total_cmp(&distance(b))).unwrap();
+        assert_eq!(tip.bones[0], 5);
+    }
+}
@@ -0,0 +1,392 @@
+use std::cell::OnceCell;
+use std::mem;
+use std::rc::Rc;
+use glam::{Mat4, Vec2};
+use wgpu::util::DeviceExt;
+use crate::dep::basic::structure::Vertex;
+use crate::import::png::Image;
+use super::mesh::ShapeTransform;
+use super::{Color, Shadow, Shape, ShapeLayouts};
+
+/// Per-sprite data consumed by `sprite_shader.wgsl`: `ShapeRaw` plus the frame's
+/// rectangle in texture coordinates.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpriteRaw {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+    /// Top-left and bottom-right corners, u right and v down.
+    pub uv_rect: [f32; 4],
+}
+
+impl SpriteRaw {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<SpriteRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// A frame's rectangle in atlas pixels, from the top-left corner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AtlasFrame {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One image holding many sprite frames. Shared between sprites with `Rc`; the
+/// texture is uploaded the first time a sprite using it is prepared.
+pub struct TextureAtlas {
+    image: Image,
+    frames: Vec<AtlasFrame>,
+    /// Nearest-neighbour sampling, for pixel art.
+    pixelated: bool,
+    gpu: OnceCell<wgpu::BindGroup>,
+}
+
+impl TextureAtlas {
+    /// An atlas whose only frame is the whole image.
+    pub fn new(image: Image) -> Self {
+        let whole = AtlasFrame { x: 0, y: 0, width: image.width, height: image.height };
+        TextureAtlas { image, frames: vec![whole], pixelated: false, gpu: OnceCell::new() }
+    }
+
+    /// Cuts the image into `columns` × `rows` equal frames, numbered row by row.
+    pub fn grid(image: Image, columns: u32, rows: u32) -> Self {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let (width, height) = (image.width / columns, image.height / rows);
+        let frames = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| AtlasFrame { x: column * width, y: row * height, width, height }))
+            .collect();
+        TextureAtlas { frames, ..Self::new(image) }
+    }
+
+    /// An atlas with exactly the given frames, or `None` if there are none.
+    pub fn with_frames(image: Image, frames: Vec<AtlasFrame>) -> Option<Self> {
+        if frames.is_empty() {
+            return None;
+        }
+        Some(TextureAtlas { frames, ..Self::new(image) })
+    }
+
+    pub fn with_pixelated(mut self, pixelated: bool) -> Self {
+        self.pixelated = pixelated;
+        self
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn frames(&self) -> &[AtlasFrame] {
+        &self.frames
+    }
+
+    /// The frame at `index`, wrapping around so any index shows something.
+    pub fn frame(&self, index: usize) -> AtlasFrame {
+        // 构造时保证至少有一帧
+        self.frames[index % self.frames.len()]
+    }
+
+    pub fn uv_rect(&self, index: usize) -> [f32; 4] {
+        let frame = self.frame(index);
+        let (w, h) = (self.image.width.max(1) as f32, self.image.height.max(1) as f32);
+        [
+            frame.x as f32 / w,
+            frame.y as f32 / h,
+            (frame.x + frame.width) as f32 / w,
+            (frame.y + frame.height) as f32 / h,
+        ]
+    }
+
+    /// Layout of the texture and sampler, bind group 1 of the sprite pipeline.
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Atlas Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    /// Uploads the image on first use.
+    pub fn bind_group(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> &wgpu::BindGroup {
+        self.gpu.get_or_init(|| {
+            let size = wgpu::Extent3d {
+                width: self.image.width.max(1),
+                height: self.image.height.max(1),
+                depth_or_array_layers: 1,
+            };
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Atlas Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            if !self.image.pixels.is_empty() {
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &self.image.pixels,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * self.image.width),
+                        rows_per_image: Some(self.image.height),
+                    },
+                    size,
+                );
+            }
+            let filter = if self.pixelated { wgpu::FilterMode::Nearest } else { wgpu::FilterMode::Linear };
+            let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Atlas Sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                mag_filter: filter,
+                min_filter: filter,
+                ..Default::default()
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Atlas Bind Group"),
+                layout: &Self::bind_group_layout(device),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            })
+        })
+    }
+}
+
+/// Unit quad from (0, 0) to (1, 1); v runs down the image while y runs up.
+const QUAD: [Vertex; 4] = [
+    Vertex { position: [0.0, 0.0, 0.0], color: [1.0, 1.0, 1.0], uv: [0.0, 1.0] },
+    Vertex { position: [1.0, 0.0, 0.0], color: [1.0, 1.0, 1.0], uv: [1.0, 1.0] },
+    Vertex { position: [1.0, 1.0, 0.0], color: [1.0, 1.0, 1.0], uv: [1.0, 0.0] },
+    Vertex { position: [0.0, 1.0, 0.0], color: [1.0, 1.0, 1.0], uv: [0.0, 0.0] },
+];
+const QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+struct GpuSprite {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    sprite_buffer: wgpu::Buffer,
+}
+
+/// A textured quad showing one frame of a `TextureAtlas`, tinted by its color
+/// and opacity. Change the frame with `Shape::set_frame`, e.g. from a
+/// `FlipbookAnimator`. Draw it with the sprite pipeline, see `sprite_shader.wgsl`.
+pub struct Sprite {
+    atlas: Rc<TextureAtlas>,
+    frame: usize,
+    /// Width and height of the quad in view units.
+    pub size: Vec2,
+    /// Point of the quad at the transform's origin, (0, 0) bottom-left to (1, 1) top-right.
+    pub pivot: Vec2,
+    pub transform: ShapeTransform,
+    color: Color,
+    opacity: f32,
+    gpu: Option<GpuSprite>,
+}
+
+impl Sprite {
+    pub fn new(atlas: Rc<TextureAtlas>, size: Vec2) -> Self {
+        Sprite {
+            atlas,
+            frame: 0,
+            size,
+            pivot: Vec2::splat(0.5),
+            transform: ShapeTransform::default(),
+            color: Color::WHITE,
+            opacity: 1.0,
+            gpu: None,
+        }
+    }
+
+    pub fn atlas(&self) -> &Rc<TextureAtlas> {
+        &self.atlas
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn model_matrix(&self) -> Mat4 {
+        self.transform.matrix() * Mat4::from_scale(self.size.extend(1.0)) * Mat4::from_translation((-self.pivot).extend(0.0))
+    }
+
+    pub fn to_raw(&self) -> SpriteRaw {
+        let color = self.color.to_array();
+        SpriteRaw {
+            model: self.model_matrix().to_cols_array_2d(),
+            color: [color[0], color[1], color[2], self.opacity],
+            uv_rect: self.atlas.uv_rect(self.frame),
+        }
+    }
+}
+
+impl Shape for Sprite {
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_shadow(&mut self, _shadow: Shadow) {}
+
+    fn set_position(&mut self, position: Vec2) {
+        self.transform.position = position;
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.transform.rotation = rotation;
+    }
+
+    fn set_scale(&mut self, scale: Vec2) {
+        self.transform.scale = scale;
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+
+    fn set_anchor(&mut self, anchor: Vec2) {
+        self.transform.anchor = anchor;
+    }
+
+    fn set_frame(&mut self, frame: usize) {
+        self.frame = frame;
+    }
+
+    fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, _layouts: &ShapeLayouts) {
+        self.atlas.bind_group(device, queue);
+        let raw = self.to_raw();
+        if let Some(gpu) = &self.gpu {
+            queue.write_buffer(&gpu.sprite_buffer, 0, bytemuck::cast_slice(&[raw]));
+            return;
+        }
+        self.gpu = Some(GpuSprite {
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Sprite Vertex Buffer"),
+                contents: bytemuck::cast_slice(&QUAD),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Sprite Index Buffer"),
+                contents: bytemuck::cast_slice(&QUAD_INDICES),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            sprite_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Sprite Buffer"),
+                contents: bytemuck::cast_slice(&[raw]),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            }),
+        });
+    }
+
+    /// Expects the sprite pipeline to be bound, with the view matrix at group 0.
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        let (Some(gpu), Some(atlas)) = (&self.gpu, self.atlas.gpu.get()) else {
+            return;
+        };
+        render_pass.set_bind_group(1, atlas, &[]);
+        render_pass.set_vertex_buffer(0, gpu.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, gpu.sprite_buffer.slice(..));
+        render_pass.set_index_buffer(gpu.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..QUAD_INDICES.len() as u32, 0, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32) -> Image {
+        Image::new(width, height, vec![0; (width * height * 4) as usize])
+    }
+
+    #[test]
+    fn grid_frames_are_numbered_row_by_row() {
+        let atlas = TextureAtlas::grid(image(8, 4), 4, 2);
+        assert_eq!(atlas.frames().len(), 8);
+        assert_eq!(atlas.frame(5), AtlasFrame { x: 2, y: 2, width: 2, height: 2 });
+        assert_eq!(atlas.uv_rect(5), [0.25, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn frame_indices_wrap() {
+        let atlas = TextureAtlas::grid(image(4, 2), 2, 1);
+        assert_eq!(atlas.frame(3), atlas.frame(1));
+        assert_eq!(TextureAtlas::new(image(4, 2)).frame(7), AtlasFrame { x: 0, y: 0, width: 4, height: 2 });
+    }
+
+    #[test]
+    fn explicit_frames_must_not_be_empty() {
+        assert!(TextureAtlas::with_frames(image(4, 4), vec![]).is_none());
+        let frame = AtlasFrame { x: 1, y: 1, width: 2, height: 2 };
+        let atlas = TextureAtlas::with_frames(image(4, 4), vec![frame]).unwrap();
+        assert_eq!(atlas.frame(2), frame);
+    }
+}
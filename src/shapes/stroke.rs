@@ -0,0 +1,232 @@
+use glam::Vec2;
+use lyon::math::point;
+use lyon::path::{Path, Polygon};
+use lyon::tessellation::{BuffersBuilder, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers};
+pub use lyon::tessellation::{LineCap, LineJoin};
+use super::circle::IVertex;
+use super::measure::{ArcLengthTable, Contour};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    pub miter_limit: f32,
+    /// Alternating dash and gap lengths, repeated along each subpath. Empty means solid.
+    pub dashes: Vec<f32>,
+    /// Shifts the dash pattern along the path; animate it to make dashes crawl.
+    pub dash_offset: f32,
+    /// Visible part of the path as fractions of its total length.
+    pub trim_start: f32,
+    pub trim_end: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            width: 0.02,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT,
+            dashes: vec![],
+            dash_offset: 0.0,
+            trim_start: 0.0,
+            trim_end: 1.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        StrokeStyle { width, ..Default::default() }
+    }
+
+    fn options(&self) -> StrokeOptions {
+        StrokeOptions::tolerance(0.01)
+            .with_line_width(self.width)
+            .with_line_cap(self.cap)
+            .with_line_join(self.join)
+            .with_miter_limit(self.miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT))
+    }
+
+    fn is_dashed(&self) -> bool {
+        self.dashes.iter().all(|d| *d >= 0.0) && self.dashes.iter().sum::<f32>() > 0.0
+    }
+
+    fn is_trimmed(&self) -> bool {
+        self.trim_start > 0.0 || self.trim_end < 1.0
+    }
+
+    /// The "on" ranges of the dash pattern that overlap `[0, length]`.
+    fn dash_ranges(&self, length: f32) -> Vec<(f32, f32)> {
+        // 奇数个数的虚线数组按 SVG 规则重复一次
+        let mut pattern = self.dashes.clone();
+        if pattern.len() % 2 == 1 {
+            pattern.extend_from_within(..);
+        }
+        let period: f32 = pattern.iter().sum();
+
+        let mut ranges = vec![];
+        let mut at = -self.dash_offset.rem_euclid(period);
+        while at < length {
+            for pair in pattern.chunks(2) {
+                let (on, off) = (pair[0], pair[1]);
+                if at + on > 0.0 && at < length && on > 0.0 {
+                    ranges.push((at.max(0.0), (at + on).min(length)));
+                }
+                at += on + off;
+            }
+        }
+        ranges
+    }
+}
+
+/// Tessellates the outline of `path`, applying the style's dashes and trim.
+pub fn tessellate_stroke(path: &Path, style: &StrokeStyle, color: [f32; 3]) -> VertexBuffers<IVertex, u16> {
+    let mut buffers: VertexBuffers<IVertex, u16> = VertexBuffers::new();
+    if style.width <= 0.0 {
+        return buffers;
+    }
+
+    let visible = if style.is_dashed() || style.is_trimmed() {
+        visible_path(path, style)
+    } else {
+        path.clone()
+    };
+
+    let mut tessellator = StrokeTessellator::new();
+    let result = tessellator.tessellate_path(
+        &visible,
+        &style.options(),
+        &mut BuffersBuilder::new(&mut buffers, |v: StrokeVertex| {
+            let pos = v.position().to_array();
+            IVertex {
+                position: [pos[0], pos[1], 0.0],
+                color,
+            }
+        }),
+    );
+    if let Err(e) = result {
+        log::warn!("stroke tessellation failed: {:?}", e);
+    }
+    buffers
+}
+
+/// Cuts the path down to the pieces that are both inside the trim range and on a dash.
+fn visible_path(path: &Path, style: &StrokeStyle) -> Path {
+    let contours = Contour::from_path(path, 0.01);
+    let tables = contours.iter().map(ArcLengthTable::from_contour).collect::<Vec<_>>();
+    let total: f32 = tables.iter().map(ArcLengthTable::length).sum();
+
+    let (mut trim_start, mut trim_end) = (style.trim_start.clamp(0.0, 1.0), style.trim_end.clamp(0.0, 1.0));
+    if trim_start > trim_end {
+        std::mem::swap(&mut trim_start, &mut trim_end);
+    }
+    let (trim_start, trim_end) = (trim_start * total, trim_end * total);
+
+    let mut builder = Path::builder();
+    let mut offset = 0.0;
+    for (contour, table) in contours.iter().zip(&tables) {
+        let length = table.length();
+        let lo = (trim_start - offset).max(0.0);
+        let hi = (trim_end - offset).min(length);
+        offset += length;
+        if lo >= hi {
+            continue;
+        }
+
+        let whole = lo <= 0.0 && hi >= length;
+        if whole && !style.is_dashed() {
+            add_polyline(&mut builder, &contour.points, contour.closed);
+            continue;
+        }
+
+        let ranges = if style.is_dashed() { style.dash_ranges(length) } else { vec![(0.0, length)] };
+        for (a, b) in ranges {
+            let (a, b) = (a.max(lo), b.min(hi));
+            if a < b {
+                add_polyline(&mut builder, &table.slice(a, b), false);
+            }
+        }
+    }
+    builder.build()
+}
+
+fn add_polyline(builder: &mut lyon::path::path::Builder, points: &[Vec2], closed: bool) {
+    if points.len() < 2 {
+        return;
+    }
+    let points = points.iter().map(|p| point(p.x, p.y)).collect::<Vec<_>>();
+    builder.add_polygon(Polygon { points: &points, closed });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(from: Vec2, to: Vec2) -> Path {
+        let mut builder = Path::builder();
+        add_polyline(&mut builder, &[from, to], false);
+        builder.build()
+    }
+
+    fn trimmed(start: f32, end: f32) -> StrokeStyle {
+        StrokeStyle { trim_start: start, trim_end: end, ..StrokeStyle::new(1.0) }
+    }
+
+    fn pieces(path: &Path) -> Vec<Vec<Vec2>> {
+        Contour::from_path(path, 0.01).into_iter().map(|c| c.points).collect()
+    }
+
+    #[test]
+    fn dash_ranges_follow_the_offset() {
+        let dashed = |dashes: Vec<f32>, dash_offset: f32| StrokeStyle { dashes, dash_offset, ..StrokeStyle::default() };
+        assert_eq!(dashed(vec![2.0, 1.0], 0.0).dash_ranges(7.0), vec![(0.0, 2.0), (3.0, 5.0), (6.0, 7.0)]);
+        assert_eq!(dashed(vec![2.0, 1.0], 1.0).dash_ranges(7.0), vec![(0.0, 1.0), (2.0, 4.0), (5.0, 7.0)]);
+        assert_eq!(dashed(vec![2.0, 1.0], -1.0).dash_ranges(4.0), vec![(1.0, 3.0)]);
+        // 奇数个数的数组重复一次: [1] 即 [1, 1]
+        assert_eq!(dashed(vec![1.0], 0.0).dash_ranges(4.0), vec![(0.0, 1.0), (2.0, 3.0)]);
+    }
+
+    #[test]
+    fn trim_keeps_the_range_in_either_order() {
+        let path = line(Vec2::ZERO, Vec2::new(10.0, 0.0));
+        let expected = vec![vec![Vec2::new(2.0, 0.0), Vec2::new(6.0, 0.0)]];
+        assert_eq!(pieces(&visible_path(&path, &trimmed(0.2, 0.6))), expected);
+        assert_eq!(pieces(&visible_path(&path, &trimmed(0.6, 0.2))), expected);
+        assert!(pieces(&visible_path(&path, &trimmed(0.5, 0.5))).is_empty());
+
+        let geometry = tessellate_stroke(&path, &trimmed(0.2, 0.6), [1.0; 3]);
+        assert!(!geometry.indices.is_empty());
+        assert!(geometry.vertices.iter().all(|v| (2.0..=6.0).contains(&v.position[0])));
+        assert!(tessellate_stroke(&path, &trimmed(0.5, 0.5), [1.0; 3]).indices.is_empty());
+    }
+
+    #[test]
+    fn trim_spans_several_contours() {
+        let mut builder = Path::builder();
+        add_polyline(&mut builder, &[Vec2::ZERO, Vec2::new(10.0, 0.0)], false);
+        add_polyline(&mut builder, &[Vec2::new(0.0, 5.0), Vec2::new(10.0, 5.0)], false);
+        let path = builder.build();
+        assert_eq!(
+            pieces(&visible_path(&path, &trimmed(0.25, 0.75))),
+            vec![
+                vec![Vec2::new(5.0, 0.0), Vec2::new(10.0, 0.0)],
+                vec![Vec2::new(0.0, 5.0), Vec2::new(5.0, 5.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn dashes_are_cut_to_the_trim() {
+        let path = line(Vec2::ZERO, Vec2::new(10.0, 0.0));
+        let style = StrokeStyle { dashes: vec![2.0, 2.0], dash_offset: 1.0, ..trimmed(0.0, 0.5) };
+        assert_eq!(
+            pieces(&visible_path(&path, &style)),
+            vec![
+                vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)],
+                vec![Vec2::new(3.0, 0.0), Vec2::new(5.0, 0.0)],
+            ]
+        );
+    }
+}